@@ -1,71 +1,297 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
 
 use bytemuck::cast_slice;
-use wgpu::{BindGroup, Buffer, Device, RenderPipeline, ShaderModule, Texture, TextureFormat};
+use instant::Instant;
+use wgpu::{BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device, RenderPipeline, Sampler, ShaderModule, Texture, TextureFormat, TextureView};
 use wgpu::util::DeviceExt;
 use winit::keyboard::KeyCode;
+use winit::window::Fullscreen;
 
-use crate::application::AppCommand::{LOAD_ROM, RESET};
-use crate::chip8::{Chip8, Chip8Rom, QuirkConfig};
+use crate::application::AppCommand::{ADD_BREAKPOINT, ADD_WATCHPOINT, ADVANCE_FRAME, LOAD_ROM, LOAD_ROM_BYTES, LOAD_STATE, PAUSE, PLAY_MOVIE, REMOVE_BREAKPOINT, REMOVE_WATCHPOINT, RESET, RESET_KEY_BINDINGS, RESUME, SAVE_STATE, SET_AUDIO, SET_EFFECT, SET_EFFECT_PARAM, SET_INSTRUCTIONS_PER_FRAME, SET_PALETTE, SET_PERSISTENCE, SET_SPEED, SET_TIMING, START_REBINDING, START_RECORDING, STEP_INTO, STEP_OUT, STEP_OVER, STOP_PLAYBACK, STOP_RECORDING};
+use crate::audio::{AudioBackend, AudioConfig, create_backend};
+use crate::chip8::{Chip8Rom, CycleOutcome};
+use crate::chip8::debugger::{Breakpoint, DebugOutcome, Debugger, Watchpoint};
+use crate::chip8::display::{Display, HIRES_HEIGHT, HIRES_WIDTH, LORES_HEIGHT, LORES_WIDTH};
+use crate::chip8::quirks::QuirkConfig;
+use crate::gamepad::{GamepadButton, GamepadEvent, PadButton};
+use crate::gamepad;
+use crate::hash::sha1;
+use crate::keymap::{default_layout, is_bindable, key_name, KeyConfig, Rebinding};
+use crate::movie::{Movie, MoviePlayer, MovieRecorder};
+use crate::palette::{DEFAULT_PALETTE, Palette, palette_to_text, PaletteConfig};
+use crate::persistence::{Persistence, PersistenceConfig};
+use crate::postprocess::{EFFECTS, MAX_PARAMS, PostProcessing, PRELUDE};
+use crate::scheduler::{InstructionMeter, Speed, Tick, TIMER_FREQUENCY, Timing};
+use crate::session::{SAVE_SLOTS, Session};
+use crate::storage;
 use crate::wgpu_runtime::{RuntimeContext, Vertex, WgpuRuntime};
 use crate::wgpu_runtime::wgpu_math::Vec2i;
 
+const TITLE: &str = "Chip 8 Emulator - Bitechular Innovations";
+
+pub const DEFAULT_WINDOW_SCALE: u32 = 10;
+
+// Quick-save to the slot of the pressed key, the load keys restore the same slots
+const SAVE_STATE_KEYS: [KeyCode; SAVE_SLOTS] = [KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4];
+const LOAD_STATE_KEYS: [KeyCode; SAVE_SLOTS] = [KeyCode::F5, KeyCode::F6, KeyCode::F7, KeyCode::F8];
+
+// The machine plays backwards while the key is held, for up to 30 seconds
+const REWIND_KEY: KeyCode = KeyCode::Backspace;
+
+const PAUSE_KEY: KeyCode = KeyCode::F9;
+const STEP_OVER_KEY: KeyCode = KeyCode::F10;
+const STEP_INTO_KEY: KeyCode = KeyCode::F11;
+const STEP_OUT_KEY: KeyCode = KeyCode::F12;
+
+// Recording restarts the ROM and saves the movie when it stops, playback finds the ROM by its hash
+const RECORD_KEY: KeyCode = KeyCode::Insert;
+const PLAYBACK_KEY: KeyCode = KeyCode::Home;
+const MOVIE_FILE: &str = "movie.c8m";
+
+// Asks for a new key for every keypad key, for all ROMs or only the current one. Escape keeps the
+// current binding of a key.
+const REBIND_KEY: KeyCode = KeyCode::PageUp;
+const REBIND_ROM_KEY: KeyCode = KeyCode::PageDown;
+const SKIP_BINDING_KEY: KeyCode = KeyCode::Escape;
+
+// Runs a single frame while paused and pauses when running. The speed keys go through
+// SPEEDS and the instruction keys change the instructions per frame by one, the timing key
+// switches between that fixed rate and the COSMAC VIP timing.
+const FRAME_ADVANCE_KEY: KeyCode = KeyCode::End;
+const FASTER_KEY: KeyCode = KeyCode::Equal;
+const SLOWER_KEY: KeyCode = KeyCode::Minus;
+const MORE_INSTRUCTIONS_KEY: KeyCode = KeyCode::BracketRight;
+const FEWER_INSTRUCTIONS_KEY: KeyCode = KeyCode::BracketLeft;
+const TIMING_KEY: KeyCode = KeyCode::Backslash;
+
+const SPEEDS: [Speed; 6] = [Speed::Scaled(0.25), Speed::Scaled(0.5), Speed::Scaled(1.0), Speed::Scaled(2.0), Speed::Scaled(4.0), Speed::Uncapped];
+const MAX_INSTRUCTIONS_PER_FRAME: u32 = 1000;
+
+// Uncapped runs the machine for this long every update, leaving the rest of the time for rendering
+const UNCAPPED_TIME: Duration = Duration::from_millis(10);
+
+// Keys for the named buttons of the ROM database, on top of the keypad
+const DATABASE_KEYS: [(&str, KeyCode); 6] = [
+    ("up", KeyCode::ArrowUp),
+    ("down", KeyCode::ArrowDown),
+    ("left", KeyCode::ArrowLeft),
+    ("right", KeyCode::ArrowRight),
+    ("a", KeyCode::Space),
+    ("b", KeyCode::Enter),
+];
+
+// Gamepad buttons for the named buttons of the ROM database, the second player uses the second pad
+const DATABASE_BUTTONS: [(&str, PadButton); 12] = [
+    ("up", (0, GamepadButton::DPadUp)),
+    ("down", (0, GamepadButton::DPadDown)),
+    ("left", (0, GamepadButton::DPadLeft)),
+    ("right", (0, GamepadButton::DPadRight)),
+    ("a", (0, GamepadButton::South)),
+    ("b", (0, GamepadButton::East)),
+    ("player2Up", (1, GamepadButton::DPadUp)),
+    ("player2Down", (1, GamepadButton::DPadDown)),
+    ("player2Left", (1, GamepadButton::DPadLeft)),
+    ("player2Right", (1, GamepadButton::DPadRight)),
+    ("player2A", (1, GamepadButton::South)),
+    ("player2B", (1, GamepadButton::East)),
+];
+
 pub enum AppCommand {
     RESET,
     LOAD_ROM(u8),
+    LOAD_ROM_BYTES { name: String, data: Vec<u8>, quirks: Option<QuirkConfig> },
+    SAVE_STATE(u8),
+    LOAD_STATE(u8),
+    PAUSE,
+    RESUME,
+    STEP_INTO,
+    STEP_OVER,
+    STEP_OUT,
+    ADD_BREAKPOINT(Breakpoint),
+    REMOVE_BREAKPOINT(u16),
+    ADD_WATCHPOINT(Watchpoint),
+    REMOVE_WATCHPOINT(u16, u16),
+    START_RECORDING,
+    STOP_RECORDING,
+    PLAY_MOVIE(String),
+    STOP_PLAYBACK,
+    SET_AUDIO(AudioConfig),
+    START_REBINDING { rom_only: bool },
+    RESET_KEY_BINDINGS { rom_only: bool },
+    ADVANCE_FRAME,
+    SET_SPEED(Speed),
+    SET_INSTRUCTIONS_PER_FRAME(u32),
+    SET_TIMING(Timing),
+    /// Picks the colours for every ROM or only the current one, no palette goes back to the
+    /// colours from before
+    SET_PALETTE { palette: Option<Palette>, rom_only: bool },
+    /// Picks how long pixels stay lit for every ROM or only the current one, no mode goes back to
+    /// the mode from before
+    SET_PERSISTENCE { persistence: Option<Persistence>, rom_only: bool },
+    /// Turns a post-processing effect on or off by its name
+    SET_EFFECT { effect: String, enabled: bool },
+    SET_EFFECT_PARAM { effect: String, param: String, value: f32 },
+}
+
+/// How the emulator starts, filled in from the command line on desktop
+pub struct LaunchOptions {
+    /// Index of the bundled ROM to start
+    pub rom: u8,
+    /// A ROM file to start instead of a bundled one, as a name and its contents
+    pub rom_file: Option<(String, Vec<u8>)>,
+    /// Replaces the quirks of the started ROM
+    pub quirks: Option<QuirkConfig>,
+    /// Replaces the speed of every ROM
+    pub instructions_per_second: Option<f32>,
+    pub timing: Timing,
+    /// Window size in pixels per low resolution CHIP-8 pixel
+    pub scale: u32,
+    /// Replaces the colours of every ROM
+    pub palette: Option<Palette>,
+    /// Replaces the persistence of every ROM
+    pub persistence: Option<Persistence>,
+    /// The post-processing effects that are on
+    pub effects: PostProcessing,
+    pub fullscreen: bool,
+    pub paused: bool,
+}
+
+impl LaunchOptions {
+    pub fn new() -> Self {
+        LaunchOptions {
+            rom: 0,
+            rom_file: None,
+            quirks: None,
+            instructions_per_second: None,
+            timing: Timing::Fixed,
+            scale: DEFAULT_WINDOW_SCALE,
+            palette: None,
+            persistence: None,
+            effects: PostProcessing::new(),
+            fullscreen: false,
+            paused: false,
+        }
+    }
+}
+
+impl Default for LaunchOptions {
+    fn default() -> Self {
+        LaunchOptions::new()
+    }
 }
 
 pub struct RuntimeData {
-    chip8: Chip8,
-    buffer: [[bool; 64]; 32],
-    render_pipeline: RenderPipeline,
-    uniform_buffer: Buffer,
-    bind_group: BindGroup,
-    clockspeed: f32,
-    elapsed_time: f32,
+    session: Session,
+    pipeline: DisplayPipeline,
+    meter: InstructionMeter,
+    /// The measured instructions per second, shared with the web page
+    instruction_rate: Arc<AtomicU32>,
     key_map: HashMap<KeyCode, u8>,
-    current_rom: u8,
-    roms: Vec<Chip8Rom>,
+    button_map: HashMap<PadButton, u8>,
+    rewinding: bool,
+    debugger: Debugger,
+    recorder: Option<MovieRecorder>,
+    player: Option<MoviePlayer>,
+    audio: Box<dyn AudioBackend>,
+    palette: Palette,
+    palette_override: Option<Palette>,
+    palette_config: PaletteConfig,
+    persistence: Persistence,
+    persistence_override: Option<Persistence>,
+    persistence_config: PersistenceConfig,
+    /// Emulated frames since the display was last rendered, the pixel history moves on by as many
+    new_frames: u32,
+    post_processing: PostProcessing,
+    key_config: KeyConfig,
+    rebinding: Option<Rebinding>,
+    advance_frame: bool,
 }
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub(crate) struct ShaderUniform {
-    value: [u32; 2048],
+    value: [u32; 512],
+    palette: [[f32; 4]; 4],
     width: f32,
     height: f32,
-    padding: [u8; 8],
+    columns: f32,
+    rows: f32,
+    persistence: u32,
+    decay: f32,
+    frames: u32,
+    reset: u32,
+    advance: u32,
+    padding: [u32; 3],
 }
 
-pub fn start_application() -> WgpuRuntime<RuntimeData, AppCommand> {
+pub fn start_application(options: LaunchOptions) -> WgpuRuntime<RuntimeData, AppCommand> {
     println!("Chip 8 Emulator by Bitechular Innovations");
 
+    let window_size = Vec2i::new((LORES_WIDTH as u32 * options.scale) as i32, (LORES_HEIGHT as u32 * options.scale) as i32);
+
     let mut runtime = WgpuRuntime::<_, AppCommand>::new(
-        "Chip 8 Emulator - Bitechular Innovations",
-        Vec2i::new(640, 320),
-        |context| {
-            let roms = create_rom_list();
-            let mut device = roms[0].to_device();
+        TITLE,
+        window_size,
+        move |context| {
+            let session = Session::new(create_rom_list(), options.rom, options.instructions_per_second);
+            let current_rom = session.current_rom();
 
             let shader = create_shader(&context.gfx.device);
-            let (render_pipeline, uniform_buffer, bind_group) = create_pipeline
-                (&context.gfx.device, &shader, context.gfx.texture_format);
-
-            let key_map = create_key_map();
-
-            RuntimeData {
-                chip8: device,
-                render_pipeline,
-                uniform_buffer,
-                bind_group,
-                elapsed_time: 0.0,
-                clockspeed: 1000.0 / 700.0,
-                key_map,
-                current_rom: 0,
-                roms,
-                buffer: [[false; 64]; 32],
+            let pipeline = create_pipeline(&context.gfx.device, &shader, context.gfx.texture_format);
+
+            if options.fullscreen {
+                context.gfx.window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+            }
+
+            let mut data = RuntimeData {
+                session,
+                pipeline,
+                meter: InstructionMeter::new(),
+                instruction_rate: Arc::new(AtomicU32::new(0)),
+                key_map: default_layout(),
+                button_map: gamepad::default_layout(),
+                rewinding: false,
+                debugger: Debugger::new(),
+                recorder: None,
+                player: None,
+                audio: create_backend(AudioConfig::new()),
+                palette: DEFAULT_PALETTE,
+                palette_override: options.palette,
+                palette_config: PaletteConfig::load(),
+                persistence: Persistence::default(),
+                persistence_override: options.persistence,
+                persistence_config: PersistenceConfig::load(),
+                new_frames: 0,
+                post_processing: options.effects,
+                key_config: KeyConfig::load(),
+                rebinding: None,
+                advance_frame: false,
+            };
+
+            match options.rom_file {
+                Some((name, rom)) => data.load_rom_bytes(name, rom, options.quirks),
+                None => {
+                    if options.quirks.is_some() {
+                        data.session.roms[current_rom as usize].quirks = options.quirks;
+                    }
+                    data.reset_device();
+                }
+            }
+
+            if options.timing != Timing::Fixed {
+                data.set_timing(options.timing);
             }
+
+            if options.paused {
+                data.pause();
+            }
+
+            data
         },
     );
 
@@ -73,45 +299,22 @@ pub fn start_application() -> WgpuRuntime<RuntimeData, AppCommand> {
     runtime.on_render(render);
     runtime.on_update(update);
     runtime.on_key_event(input);
+    runtime.on_gamepad_event(gamepad_input);
+    runtime.on_file_dropped(file_dropped);
 
     return runtime;
 }
 
-fn create_key_map() -> HashMap<KeyCode, u8> {
-    let mut key_map: HashMap<KeyCode, u8> = HashMap::new();
-
-    key_map.insert(KeyCode::Digit1, 0x1);
-    key_map.insert(KeyCode::Digit2, 0x2);
-    key_map.insert(KeyCode::Digit3, 0x3);
-    key_map.insert(KeyCode::Digit4, 0xC);
-
-    key_map.insert(KeyCode::KeyQ, 0x4);
-    key_map.insert(KeyCode::KeyW, 0x5);
-    key_map.insert(KeyCode::KeyE, 0x6);
-    key_map.insert(KeyCode::KeyR, 0xD);
-
-    key_map.insert(KeyCode::KeyA, 0x7);
-    key_map.insert(KeyCode::KeyS, 0x8);
-    key_map.insert(KeyCode::KeyD, 0x9);
-    key_map.insert(KeyCode::KeyF, 0xE);
-
-    key_map.insert(KeyCode::KeyZ, 0xA);
-    key_map.insert(KeyCode::KeyX, 0x0);
-    key_map.insert(KeyCode::KeyC, 0xB);
-    key_map.insert(KeyCode::KeyV, 0xF);
-    key_map
-}
-
-fn create_rom_list() -> Vec<Chip8Rom> {
+/// The ROMs that are compiled in, in the order of their ids
+pub fn create_rom_list() -> Vec<Chip8Rom> {
     vec![
         Chip8Rom::new("Test: IBM Logo", include_bytes!("roms/tests/ibm.ch8").to_vec()),
         Chip8Rom::new("Test: Corax Plus", include_bytes!("roms/tests/corax.plus.ch8").to_vec()),
-        Chip8Rom::new_quirks("Test: Quirks", include_bytes!("roms/tests/quirks.ch8").to_vec(), QuirkConfig::create(true, true)),
-        Chip8Rom::new_quirks("Test: Flags", include_bytes!("roms/tests/flags.ch8").to_vec(),
-                             QuirkConfig::create(false, false)),
+        Chip8Rom::new("Test: Quirks", include_bytes!("roms/tests/quirks.ch8").to_vec()),
+        Chip8Rom::new("Test: Flags", include_bytes!("roms/tests/flags.ch8").to_vec()),
         Chip8Rom::new("Test: Keypad", include_bytes!("roms/tests/keypad.ch8").to_vec()),
         Chip8Rom::new("15 Puzzle", include_bytes!("roms/games/15puzzle.ch8").to_vec()),
-        Chip8Rom::new_quirks("Blinky", include_bytes!("roms/games/blinky.ch8").to_vec(), QuirkConfig::create(true, false)),
+        Chip8Rom::new("Blinky", include_bytes!("roms/games/blinky.ch8").to_vec()),
         Chip8Rom::new("Blitz", include_bytes!("roms/games/blitz.ch8").to_vec()),
         Chip8Rom::new("Brix", include_bytes!("roms/games/brix.ch8").to_vec()),
         Chip8Rom::new("Guess", include_bytes!("roms/games/guess.ch8").to_vec()),
@@ -134,85 +337,612 @@ fn create_rom_list() -> Vec<Chip8Rom> {
     ]
 }
 
-fn on_message(_app: &mut RuntimeContext, data: &mut RuntimeData, command: AppCommand) {
+fn on_message(app: &mut RuntimeContext, data: &mut RuntimeData, command: AppCommand) {
     match command {
         RESET => {
+            data.stop_movie();
             data.reset_device();
         }
         LOAD_ROM(id) => {
             data.set_rom(id);
         }
+        LOAD_ROM_BYTES { name, data: rom, quirks } => {
+            data.load_rom_bytes(name, rom, quirks);
+        }
+        SAVE_STATE(slot) => {
+            data.save_state(slot);
+        }
+        LOAD_STATE(slot) => {
+            data.load_state(slot);
+        }
+        PAUSE => {
+            data.pause();
+        }
+        RESUME => {
+            data.debugger.resume(&data.session.chip8);
+        }
+        STEP_INTO => {
+            data.debugger.step_into(&data.session.chip8);
+        }
+        STEP_OVER => {
+            data.debugger.step_over(&data.session.chip8);
+        }
+        STEP_OUT => {
+            data.debugger.step_out(&data.session.chip8);
+        }
+        ADD_BREAKPOINT(breakpoint) => {
+            data.debugger.add_breakpoint(breakpoint);
+        }
+        REMOVE_BREAKPOINT(address) => {
+            data.debugger.remove_breakpoint(address);
+        }
+        ADD_WATCHPOINT(watchpoint) => {
+            data.debugger.add_watchpoint(watchpoint);
+        }
+        REMOVE_WATCHPOINT(start, end) => {
+            data.debugger.remove_watchpoint(start, end);
+        }
+        START_RECORDING => {
+            data.start_recording();
+        }
+        STOP_RECORDING => {
+            data.stop_recording();
+        }
+        PLAY_MOVIE(text) => {
+            data.play_movie(&text);
+        }
+        STOP_PLAYBACK => {
+            data.stop_movie();
+        }
+        SET_AUDIO(config) => {
+            data.audio.set_config(config);
+        }
+        START_REBINDING { rom_only } => {
+            data.start_rebinding(rom_only);
+        }
+        RESET_KEY_BINDINGS { rom_only } => {
+            data.reset_key_bindings(rom_only);
+        }
+        ADVANCE_FRAME => {
+            data.advance_frame();
+        }
+        SET_SPEED(speed) => {
+            data.set_speed(speed);
+        }
+        SET_INSTRUCTIONS_PER_FRAME(instructions) => {
+            data.set_instructions_per_frame(instructions);
+        }
+        SET_TIMING(timing) => {
+            data.set_timing(timing);
+        }
+        SET_PALETTE { palette, rom_only } => {
+            data.set_palette(palette, rom_only);
+        }
+        SET_PERSISTENCE { persistence, rom_only } => {
+            data.set_persistence(persistence, rom_only);
+        }
+        SET_EFFECT { effect, enabled } => {
+            match data.post_processing.set_enabled(&effect, enabled) {
+                Ok(()) => log::info!("Effect {} {}", effect, if enabled { "on" } else { "off" }),
+                Err(error) => log::error!("{}", error),
+            }
+        }
+        SET_EFFECT_PARAM { effect, param, value } => {
+            if let Err(error) = data.post_processing.set_param(&effect, &param, value) {
+                log::error!("{}", error);
+            }
+        }
+    }
+    app.gfx.window.set_title(&data.title());
+}
+
+fn update(app: &mut RuntimeContext, data: &mut RuntimeData, elapsed: Duration) {
+    let running = !data.rewinding && data.session.fault.is_none() && !data.session.exited && !data.debugger.is_paused();
+    let mut executed = 0;
+
+    if data.rewinding {
+        if data.session.fault.is_some() {
+            app.gfx.window.set_title(TITLE);
+        }
+
+        // One state back for every timer tick that would have run
+        data.session.scheduler.advance(elapsed);
+        while let Some(tick) = data.session.scheduler.next_tick() {
+            if tick == Tick::Timer {
+                data.step_back();
+            }
+        }
+    } else if data.advance_frame && data.session.fault.is_none() && !data.session.exited {
+        data.advance_frame = false;
+        data.debugger.resume(&data.session.chip8);
+        data.session.scheduler.advance_frame();
+        executed = run_scheduled(app, data, None);
+
+        if !data.debugger.is_paused() {
+            data.debugger.pause();
+        }
+        log::info!("{}", data.session.chip8.debug_summary());
+    } else if running {
+        data.session.scheduler.advance(elapsed);
+        let deadline = (data.session.scheduler.speed() == Speed::Uncapped).then(|| Instant::now() + UNCAPPED_TIME);
+        executed = run_scheduled(app, data, deadline);
+    }
+
+    if !running {
+        data.audio.frame(false);
+    }
+
+    if data.meter.record(elapsed, executed) {
+        data.instruction_rate.store(data.meter.instructions_per_second().round() as u32, Ordering::Relaxed);
+        app.gfx.window.set_title(&data.title());
+    }
+}
+
+/// Runs the instructions and timer ticks that are due, until the deadline when there is one.
+/// Returns the number of instructions that ran.
+fn run_scheduled(app: &mut RuntimeContext, data: &mut RuntimeData, deadline: Option<Instant>) -> u64 {
+    let mut executed = 0;
+
+    loop {
+        let Some(tick) = data.session.scheduler.next_tick() else {
+            return executed;
+        };
+
+        if tick == Tick::Timer {
+            data.timer_tick();
+
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break;
+            }
+            continue;
+        }
+
+        if let Some(player) = data.player.as_mut() {
+            player.apply(&mut data.session.chip8);
+        }
+
+        // The cost depends on the state before the instruction, like the registers a skip compares
+        let timing = (data.session.scheduler.timing() == Timing::CosmacVip).then(|| data.session.chip8.vip_timing());
+
+        match data.debugger.cycle(&mut data.session.chip8) {
+            Ok(DebugOutcome::Cycle(CycleOutcome::Executed)) => {
+                if let Some(timing) = timing {
+                    data.session.scheduler.spend(timing);
+                }
+                executed += 1;
+            }
+            Ok(DebugOutcome::Stopped(event)) => {
+                log::info!("{}", event);
+                log::info!("{}", data.session.chip8.debug_summary());
+                break;
+            }
+            Ok(DebugOutcome::Cycle(CycleOutcome::Exited)) => {
+                log::info!("{} exited", data.session.rom().name);
+                data.session.exited = true;
+                break;
+            }
+            Ok(_) => {}
+            Err(error) => {
+                log::error!("Halted {}: {}", data.session.rom().name, error);
+                data.session.fault = Some(error);
+                app.gfx.window.set_title(&data.title());
+                break;
+            }
+        }
+    }
+
+    // What is still due when the machine stopped or the time ran out isn't caught up later
+    data.session.scheduler.skip();
+    executed
+}
+
+fn file_dropped(app: &mut RuntimeContext, data: &mut RuntimeData, path: PathBuf) {
+    match std::fs::read(&path) {
+        Ok(rom) => {
+            let name = path.file_stem().map_or("ROM".to_string(), |stem| stem.to_string_lossy().to_string());
+            data.load_rom_bytes(name, rom, None);
+            app.gfx.window.set_title(TITLE);
+        }
+        Err(error) => log::error!("Failed to read {}: {}", path.display(), error),
     }
 }
 
-fn update(_app: &mut RuntimeContext, data: &mut RuntimeData, elapsed: f32) {
-    data.elapsed_time += elapsed;
+fn input(app: &mut RuntimeContext, data: &mut RuntimeData, keycode: KeyCode, pressed: bool) {
+    if data.rebinding.is_some() {
+        if pressed {
+            data.rebind(keycode);
+            app.gfx.window.set_title(&data.title());
+        }
+        return;
+    }
+
+    if keycode == REWIND_KEY {
+        data.rewinding = pressed;
+        return;
+    }
+
+    if pressed {
+        match keycode {
+            PAUSE_KEY if data.debugger.is_paused() => {
+                data.debugger.resume(&data.session.chip8);
+                return;
+            }
+            PAUSE_KEY => {
+                data.pause();
+                return;
+            }
+            STEP_OVER_KEY => {
+                data.debugger.step_over(&data.session.chip8);
+                return;
+            }
+            STEP_INTO_KEY => {
+                data.debugger.step_into(&data.session.chip8);
+                return;
+            }
+            STEP_OUT_KEY => {
+                data.debugger.step_out(&data.session.chip8);
+                return;
+            }
+            RECORD_KEY if data.recorder.is_some() => {
+                data.stop_recording();
+                return;
+            }
+            RECORD_KEY => {
+                data.start_recording();
+                return;
+            }
+            PLAYBACK_KEY if data.player.is_some() => {
+                data.stop_movie();
+                return;
+            }
+            PLAYBACK_KEY => {
+                match storage::read(MOVIE_FILE) {
+                    Some(text) => data.play_movie(&text),
+                    None => log::warn!("No movie recorded yet"),
+                }
+                return;
+            }
+            REBIND_KEY | REBIND_ROM_KEY => {
+                data.start_rebinding(keycode == REBIND_ROM_KEY);
+                app.gfx.window.set_title(&data.title());
+                return;
+            }
+            FRAME_ADVANCE_KEY => {
+                data.advance_frame();
+                return;
+            }
+            FASTER_KEY | SLOWER_KEY => {
+                let current = SPEEDS.iter().position(|speed| *speed == data.session.scheduler.speed()).unwrap_or(2);
+                let next = if keycode == FASTER_KEY { (current + 1).min(SPEEDS.len() - 1) } else { current.saturating_sub(1) };
+                data.set_speed(SPEEDS[next]);
+                return;
+            }
+            MORE_INSTRUCTIONS_KEY => {
+                data.set_instructions_per_frame(data.instructions_per_frame() + 1);
+                return;
+            }
+            FEWER_INSTRUCTIONS_KEY => {
+                data.set_instructions_per_frame(data.instructions_per_frame().saturating_sub(1));
+                return;
+            }
+            TIMING_KEY => {
+                let timing = if data.session.scheduler.timing() == Timing::Fixed { Timing::CosmacVip } else { Timing::Fixed };
+                data.set_timing(timing);
+                return;
+            }
+            _ => {}
+        }
+
+        if let Some(slot) = SAVE_STATE_KEYS.iter().position(|&key| key == keycode) {
+            data.save_state(slot as u8);
+            return;
+        }
+
+        if let Some(slot) = LOAD_STATE_KEYS.iter().position(|&key| key == keycode) {
+            data.load_state(slot as u8);
+            app.gfx.window.set_title(TITLE);
+            return;
+        }
+    }
+
+    // A movie that is playing owns the keypad
+    if data.player.is_some() {
+        return;
+    }
 
-    data.chip8.update();
-    while data.elapsed_time >= data.clockspeed {
-        data.elapsed_time -= data.clockspeed;
-        data.chip8.cycle();
+    if let Some(&key) = data.key_map.get(&keycode) {
+        data.set_keypad(key, pressed);
     }
 }
 
+fn gamepad_input(app: &mut RuntimeContext, data: &mut RuntimeData, event: GamepadEvent) {
+    let button = (event.gamepad, event.button);
+
+    if data.rebinding.is_some() {
+        if event.pressed {
+            data.rebind_button(button);
+            app.gfx.window.set_title(&data.title());
+        }
+        return;
+    }
+
+    if data.player.is_some() {
+        return;
+    }
 
-fn input(_app: &mut RuntimeContext, data: &mut RuntimeData, keycode: KeyCode, pressed: bool) {
-    if let Some(key) = data.key_map.get(&keycode) {
-        data.chip8.set_input(*key, pressed);
+    if let Some(&key) = data.button_map.get(&button) {
+        data.set_keypad(key, event.pressed);
     }
 }
 
+/// Bindable keys that control the emulator instead
+fn is_hotkey(keycode: KeyCode) -> bool {
+    [FRAME_ADVANCE_KEY, FASTER_KEY, SLOWER_KEY, MORE_INSTRUCTIONS_KEY, FEWER_INSTRUCTIONS_KEY, TIMING_KEY].contains(&keycode)
+}
+
+/// Renders the next pixel history from the current frame first, then the display from that history
+/// and the effects that are turned on, one after the other
 fn render(context: &mut RuntimeContext, data: &mut RuntimeData, target: &Texture) {
+    let advance = std::mem::take(&mut data.new_frames);
+
+    // The history of the other resolution doesn't line up with the pixels, it starts over
+    let display = &data.session.chip8.display;
+    let size = (display.width(), display.height());
+    let reset = size != data.pipeline.history_size;
+    data.pipeline.history_size = size;
+
+    let (width, height) = (context.gfx.surface_config.width, context.gfx.surface_config.height);
+    let mut uniform = ShaderUniform::from_display(display, data.palette, width, height);
+    uniform.set_persistence(data.persistence, advance, reset);
+    context.gfx.queue.write_buffer(&data.pipeline.uniform_buffer, 0, cast_slice(&[uniform]));
+
+    let effects = data.post_processing.enabled();
+    if !effects.is_empty() {
+        data.pipeline.effects.resize(&context.gfx.device, width, height);
+    }
+
+    let current = data.pipeline.current;
+    let next = 1 - current;
+    let pipeline = &data.pipeline;
+    let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
     let mut encoder = context.gfx.device.create_command_encoder
     (&wgpu::CommandEncoderDescriptor { label: None });
-    {
-        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
-
-        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: None,
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                    store: true,
-                },
-            })],
-            depth_stencil_attachment: None,
-        });
 
-        let display = combine_buffers(&data.buffer, &data.chip8.display);
-        data.buffer = data.chip8.display;
+    draw(context, &mut encoder, &pipeline.history[next], &pipeline.history_pipeline, &pipeline.bind_groups[current]);
+
+    // Every effect reads the picture of the one before it, the last one draws to the window
+    let display_view = if effects.is_empty() { &view } else { &pipeline.effects.views[0] };
+    draw(context, &mut encoder, display_view, &pipeline.render_pipeline, &pipeline.bind_groups[next]);
+
+    for (step, &effect) in effects.iter().enumerate() {
+        let source = step % 2;
+        let output = if step + 1 == effects.len() { &view } else { &pipeline.effects.views[1 - source] };
+        let pass = &pipeline.effects.passes[effect];
 
-        context.gfx.queue.write_buffer(
-            &data.uniform_buffer,
-            0,
-            cast_slice(&[ShaderUniform::from_display(display, context.gfx.surface_config.width, context.gfx.surface_config.height)]),
-        );
-        rpass.set_bind_group(0, &data.bind_group, &[]);
-        rpass.set_pipeline(&data.render_pipeline);
-        rpass.set_vertex_buffer(0, context.gfx.vertex_buffer.slice(..));
-        rpass.set_index_buffer(context.gfx.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-        rpass.draw_indexed(0..6, 0, 0..1);
+        let uniform = EffectUniform {
+            params: data.post_processing.settings(effect).params,
+            resolution: [width as f32, height as f32],
+            display: [size.0 as f32, size.1 as f32],
+        };
+        context.gfx.queue.write_buffer(&pass.uniform_buffer, 0, cast_slice(&[uniform]));
+
+        draw(context, &mut encoder, output, &pass.pipeline, &pass.bind_groups[source]);
     }
+
     context.gfx.queue.submit(Some(encoder.finish()));
+    data.pipeline.current = next;
+}
+
+/// Draws the full window quad to the view with the pipeline
+fn draw(context: &RuntimeContext, encoder: &mut CommandEncoder, view: &TextureView, pipeline: &RenderPipeline, bind_group: &BindGroup) {
+    let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: None,
+    });
+
+    rpass.set_bind_group(0, bind_group, &[]);
+    rpass.set_pipeline(pipeline);
+    rpass.set_vertex_buffer(0, context.gfx.vertex_buffer.slice(..));
+    rpass.set_index_buffer(context.gfx.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+    rpass.draw_indexed(0..6, 0, 0..1);
+}
+
+/// The display pipeline and the pixel history it reads, which is kept in two textures that take
+/// turns being read and written
+struct DisplayPipeline {
+    render_pipeline: RenderPipeline,
+    history_pipeline: RenderPipeline,
+    uniform_buffer: Buffer,
+    /// The uniform with each history texture, in the same order
+    bind_groups: [BindGroup; 2],
+    history: [TextureView; 2],
+    /// The history texture that holds the last rendered frame
+    current: usize,
+    /// Display size the history was rendered at
+    history_size: (usize, usize),
+    effects: EffectPipeline,
+}
+
+/// A pass for every effect in `EFFECTS`, built from the list so a new effect needs no code here.
+/// The passes take turns reading and writing two textures of the window's size.
+struct EffectPipeline {
+    passes: Vec<EffectPass>,
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+    format: TextureFormat,
+    size: (u32, u32),
+    views: [TextureView; 2],
+}
+
+struct EffectPass {
+    pipeline: RenderPipeline,
+    uniform_buffer: Buffer,
+    /// Reads the first or the second texture
+    bind_groups: [BindGroup; 2],
 }
 
-fn combine_buffers(data_buffer: &[[bool; 64]; 32], self_buffer: &[[bool; 64]; 32]) -> [[bool; 64]; 32] {
-    let mut new_buffer = [[false; 64]; 32];
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct EffectUniform {
+    params: [f32; MAX_PARAMS],
+    resolution: [f32; 2],
+    display: [f32; 2],
+}
 
-    for (i, (data_row, self_row)) in data_buffer.iter().zip(self_buffer.iter()).enumerate() {
-        for (j, (data_val, self_val)) in data_row.iter().zip(self_row.iter()).enumerate() {
-            new_buffer[i][j] = *data_val || *self_val;
+impl EffectPipeline {
+    /// Makes the textures the size of the window, the passes get new bind groups to read them
+    fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        let size = (width.max(1), height.max(1));
+        if size == self.size {
+            return;
+        }
+
+        self.size = size;
+        self.views = create_effect_textures(device, self.format, size);
+        for pass in self.passes.iter_mut() {
+            pass.bind_groups = create_effect_bind_groups(device, &self.bind_group_layout, &self.sampler, &pass.uniform_buffer, &self.views);
         }
     }
+}
+
+fn create_effect_pipeline(device: &Device, format: TextureFormat) -> EffectPipeline {
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }
+        ],
+        label: Some("effect_bind_group_layout"),
+    });
+
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("effect"),
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    });
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    let size = (1, 1);
+    let views = create_effect_textures(device, format, size);
+
+    let passes = EFFECTS.iter().map(|effect| {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(effect.name),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(format!("{}\n{}", PRELUDE, effect.source))),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(effect.name),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[
+                    Vertex::get_layout(),
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_effect",
+                targets: &[Some(format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(effect.name),
+            size: std::mem::size_of::<EffectUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_groups = create_effect_bind_groups(device, &bind_group_layout, &sampler, &uniform_buffer, &views);
+
+        EffectPass { pipeline, uniform_buffer, bind_groups }
+    }).collect();
 
-    new_buffer
+    EffectPipeline { passes, bind_group_layout, sampler, format, size, views }
+}
+
+fn create_effect_textures(device: &Device, format: TextureFormat, (width, height): (u32, u32)) -> [TextureView; 2] {
+    [0, 1].map(|_| {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("effect"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        }).create_view(&wgpu::TextureViewDescriptor::default())
+    })
 }
 
+fn create_effect_bind_groups(device: &Device, layout: &BindGroupLayout, sampler: &Sampler, uniform_buffer: &Buffer, views: &[TextureView; 2]) -> [BindGroup; 2] {
+    [0, 1].map(|index| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&views[index]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                }
+            ],
+            label: Some("effect_bind_group"),
+        })
+    })
+}
 
-fn create_pipeline(device: &Device, shader: &ShaderModule, format: TextureFormat) ->
-(RenderPipeline, Buffer, BindGroup) {
+fn create_pipeline(device: &Device, shader: &ShaderModule, format: TextureFormat) -> DisplayPipeline {
     let uniform = ShaderUniform::new();
 
     let uniform_buffer = device.create_buffer_init(
@@ -235,20 +965,50 @@ fn create_pipeline(device: &Device, shader: &ShaderModule, format: TextureFormat
                     min_binding_size: None,
                 },
                 count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Uint,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
             }
         ],
         label: Some("display_bind_group_layout"),
     });
 
-    let display_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        layout: &display_bind_group_layout,
-        entries: &[
-            wgpu::BindGroupEntry {
-                binding: 0,
-                resource: uniform_buffer.as_entire_binding(),
-            }
-        ],
-        label: Some("display_bind_group"),
+    // Large enough for the high resolution, every pixel keeps its lit frames and intensities
+    let history = [0, 1].map(|_| {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("history"),
+            size: wgpu::Extent3d { width: HIRES_WIDTH as u32, height: HIRES_HEIGHT as u32, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TextureFormat::Rgba32Uint,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        }).create_view(&wgpu::TextureViewDescriptor::default())
+    });
+
+    let bind_groups = [0, 1].map(|index| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &display_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&history[index]),
+                }
+            ],
+            label: Some("display_bind_group"),
+        })
     });
 
 
@@ -279,7 +1039,37 @@ fn create_pipeline(device: &Device, shader: &ShaderModule, format: TextureFormat
         multiview: None,
     });
 
-    (render_pipeline, uniform_buffer, display_bind_group)
+    let history_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("history"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[
+                Vertex::get_layout(),
+            ],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_history",
+            targets: &[Some(TextureFormat::Rgba32Uint.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    });
+
+    DisplayPipeline {
+        render_pipeline,
+        history_pipeline,
+        uniform_buffer,
+        bind_groups,
+        history,
+        current: 0,
+        history_size: (0, 0),
+        effects: create_effect_pipeline(device, format),
+    }
 }
 
 fn create_shader(device: &Device) -> ShaderModule {
@@ -291,16 +1081,348 @@ fn create_shader(device: &Device) -> ShaderModule {
 
 impl RuntimeData {
     pub fn reset_device(&mut self) {
-        self.chip8 = self.roms[self.current_rom as usize].to_device();
+        self.session.reset();
+        self.apply_rom_settings();
+    }
+
+    /// Takes the colours and keys from the ROM database unless they were set on launch, the session
+    /// picks the speed
+    fn apply_rom_settings(&mut self) {
+        if let Some(info) = self.session.rom().info() {
+            let authors = if info.authors.is_empty() { "unknown".to_string() } else { info.authors.join(", ") };
+            log::info!("{} by {}, {}", info.title, authors, info.platform.as_deref().unwrap_or("unknown platform"));
+        }
+
+        self.apply_palette();
+        self.apply_persistence();
+        self.apply_key_bindings();
+    }
+
+    /// The colours given on launch, then the ones picked for the ROM, the database's and the ones
+    /// picked for every ROM
+    fn apply_palette(&mut self) {
+        let rom = self.session.rom();
+        let rom_hash = sha1(&rom.data);
+
+        self.palette = self.palette_override
+            .or_else(|| self.palette_config.roms.get(&rom_hash).copied())
+            .or_else(|| rom.info().and_then(|info| info.palette))
+            .or(self.palette_config.default)
+            .unwrap_or(DEFAULT_PALETTE);
+    }
+
+    /// The mode given on launch, then the ones picked for the ROM and for every ROM
+    fn apply_persistence(&mut self) {
+        let rom_hash = sha1(&self.session.rom().data);
+        self.persistence = self.persistence_override.unwrap_or_else(|| self.persistence_config.get(&rom_hash));
+    }
+
+    /// The default layouts, then the ROM database's keys and the user's bindings for the ROM
+    fn apply_key_bindings(&mut self) {
+        let rom = self.session.rom();
+        let rom_hash = sha1(&rom.data);
+        self.key_map = self.key_config.default.clone().unwrap_or_else(default_layout);
+        self.button_map = self.key_config.gamepad_default.clone().unwrap_or_else(gamepad::default_layout);
+
+        for (name, key) in rom.info().map_or(&[][..], |info| &info.keys) {
+            if let Some((_, keycode)) = DATABASE_KEYS.iter().find(|(button, _)| button == name) {
+                self.key_map.insert(*keycode, *key);
+            }
+            if let Some((_, button)) = DATABASE_BUTTONS.iter().find(|(button, _)| button == name) {
+                self.button_map.insert(*button, *key);
+            }
+        }
+
+        if let Some(overrides) = self.key_config.roms.get(&rom_hash) {
+            self.key_map.extend(overrides);
+        }
+        if let Some(overrides) = self.key_config.gamepad_roms.get(&rom_hash) {
+            self.button_map.extend(overrides);
+        }
+    }
+
+    pub fn start_rebinding(&mut self, rom_only: bool) {
+        let rom_hash = rom_only.then(|| sha1(&self.session.rom().data));
+        self.rebinding = Some(Rebinding::new(rom_hash));
+        log::info!("{}", self.title());
+
+        // The key up events go to the rebinding, release the keys so none stay down
+        for key in 0..16 {
+            if self.session.chip8.is_pressed(key) && self.player.is_none() {
+                self.set_keypad(key, false);
+            }
+        }
+    }
+
+    /// Passes a keypad change to the machine, through the recorder while recording
+    fn set_keypad(&mut self, key: u8, pressed: bool) {
+        match self.recorder.as_mut() {
+            Some(recorder) => recorder.set_input(&mut self.session.chip8, key, pressed),
+            None => self.session.chip8.set_input(key, pressed),
+        }
+    }
+
+    /// Binds the pressed key to the keypad key that is asked for, saves the bindings after the last
+    fn rebind(&mut self, keycode: KeyCode) {
+        let Some(rebinding) = self.rebinding.as_mut() else {
+            return;
+        };
+
+        if keycode == SKIP_BINDING_KEY {
+            rebinding.skip();
+        } else if is_bindable(keycode) && !is_hotkey(keycode) {
+            rebinding.bind(keycode);
+        } else {
+            log::warn!("{} can't be bound", key_name(keycode));
+            return;
+        }
+
+        self.next_binding();
+    }
+
+    /// Binds the pressed gamepad button like [`RuntimeData::rebind`] binds a key
+    fn rebind_button(&mut self, button: PadButton) {
+        let Some(rebinding) = self.rebinding.as_mut() else {
+            return;
+        };
+
+        rebinding.bind_button(button);
+        self.next_binding();
+    }
+
+    /// Asks for the next keypad key, or saves and applies the bindings after the last one
+    fn next_binding(&mut self) {
+        if self.rebinding.as_ref().is_some_and(|rebinding| rebinding.current().is_some()) {
+            log::info!("{}", self.title());
+            return;
+        }
+
+        if let Some(rebinding) = self.rebinding.take() {
+            rebinding.finish(&mut self.key_config);
+            self.key_config.save();
+            self.apply_key_bindings();
+        }
+    }
+
+    pub fn reset_key_bindings(&mut self, rom_only: bool) {
+        let rom_hash = rom_only.then(|| sha1(&self.session.rom().data));
+        self.key_config.reset(rom_hash);
+        self.key_config.save();
+        self.apply_key_bindings();
+    }
+
+    /// The window title with the measured speed, or the fault that halted the machine. Asks for
+    /// the next key while rebinding.
+    fn title(&self) -> String {
+        match (self.rebinding.as_ref().and_then(|rebinding| rebinding.current()), self.session.fault) {
+            (Some(key), _) => format!("{} - Press the key or button for {:X}, Escape to keep it", TITLE, key),
+            (None, Some(error)) => format!("{} - {}", TITLE, error),
+            (None, None) => format!("{} - {} IPS", TITLE, self.instruction_rate.load(Ordering::Relaxed)),
+        }
     }
 
     pub fn set_rom(&mut self, id: u8) {
-        self.current_rom = id;
+        self.stop_movie();
+        self.session.current_rom = id;
+        self.reset_device();
+    }
+
+    /// Adds the ROM to the list and starts it, a ROM that was loaded before is started again
+    /// instead of being added twice
+    pub fn load_rom_bytes(&mut self, name: String, data: Vec<u8>, quirks: Option<QuirkConfig>) {
+        if data.is_empty() {
+            log::warn!("{} is empty", name);
+            return;
+        }
+
+        let existing = self.session.roms.iter().position(|rom| rom.name == name && rom.data == data);
+        let id = match existing {
+            Some(id) => {
+                self.session.roms[id].quirks = quirks;
+                id
+            }
+            None if self.session.roms.len() <= u8::MAX as usize => {
+                self.session.roms.push(Chip8Rom { name, data, quirks });
+                self.session.roms.len() - 1
+            }
+            None => {
+                log::warn!("Too many ROMs loaded, can't add {}", name);
+                return;
+            }
+        };
+
+        self.set_rom(id as u8);
+    }
+
+    pub fn save_state(&mut self, slot: u8) {
+        self.session.save_state(slot);
+    }
+
+    /// Restores the slot with the speed, colours and keys of the ROM it was saved with
+    pub fn load_state(&mut self, slot: u8) {
+        // Jumping to another state can't be replayed from the inputs alone
+        self.stop_movie();
+
+        if self.session.load_state(slot) {
+            self.apply_rom_settings();
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.debugger.pause();
+        log::info!("Paused");
+        log::info!("{}", self.session.chip8.debug_summary());
+    }
+
+    /// The 60 Hz tick: ticks the timers and ends the wait for the display. The rewind history
+    /// keeps one state per tick.
+    fn timer_tick(&mut self) {
+        if self.player.as_ref().is_some_and(|player| player.finished(&self.session.chip8)) {
+            log::info!("Playback finished");
+            self.player = None;
+        }
+
+        self.session.timer_tick();
+        self.new_frames += 1;
+        self.audio.frame(self.session.chip8.sound_timer() > 0);
+    }
+
+    /// The measured instructions per second, kept up to date while the emulator runs
+    pub fn instruction_rate(&self) -> Arc<AtomicU32> {
+        self.instruction_rate.clone()
+    }
+
+    /// Runs one frame on the next update and pauses again, a running machine is only paused
+    pub fn advance_frame(&mut self) {
+        if !self.debugger.is_paused() {
+            self.pause();
+            return;
+        }
+
+        self.advance_frame = true;
+    }
+
+    pub fn set_speed(&mut self, speed: Speed) {
+        let speed = match speed {
+            Speed::Scaled(scale) if scale.is_finite() && scale > 0.0 => speed,
+            Speed::Scaled(_) => {
+                log::warn!("Invalid speed {}", speed);
+                return;
+            }
+            Speed::Uncapped => speed,
+        };
+
+        self.session.scheduler.set_speed(speed);
+        log::info!("Speed {}", speed);
+    }
+
+    /// Instructions run every 60 Hz frame, rounded to a whole number
+    pub fn instructions_per_frame(&self) -> u32 {
+        (self.session.scheduler.instructions_per_second() / TIMER_FREQUENCY).round() as u32
+    }
+
+    /// Changes the speed of the machine for every ROM, like the speed given on launch. A fixed
+    /// rate replaces the COSMAC VIP timing.
+    pub fn set_instructions_per_frame(&mut self, instructions: u32) {
+        let instructions = instructions.clamp(1, MAX_INSTRUCTIONS_PER_FRAME);
+        let instructions_per_second = instructions as f32 * 60.0;
+
+        self.session.set_instructions_per_second(instructions_per_second);
+        log::info!("{} instructions per frame", instructions);
+
+        if self.session.scheduler.timing() != Timing::Fixed {
+            self.set_timing(Timing::Fixed);
+        }
+    }
+
+    /// Stores the colours for every ROM or the current one, they replace the colours given on
+    /// launch
+    pub fn set_palette(&mut self, palette: Option<Palette>, rom_only: bool) {
+        let rom_hash = rom_only.then(|| sha1(&self.session.rom().data));
+        self.palette_config.set(rom_hash, palette);
+        self.palette_config.save();
+
+        self.palette_override = None;
+        self.apply_palette();
+        log::info!("Palette {}", palette_to_text(&self.palette));
+    }
+
+    /// Stores the persistence for every ROM or the current one, it replaces the mode given on
+    /// launch
+    pub fn set_persistence(&mut self, persistence: Option<Persistence>, rom_only: bool) {
+        let rom_hash = rom_only.then(|| sha1(&self.session.rom().data));
+        self.persistence_config.set(rom_hash, persistence);
+        self.persistence_config.save();
+
+        self.persistence_override = None;
+        self.apply_persistence();
+        log::info!("Persistence {}", self.persistence);
+    }
+
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.session.scheduler.set_timing(timing);
+        log::info!("{} timing", timing);
+    }
+
+    /// Restores the state from one update ago, keeps the oldest state once the history runs out
+    pub fn step_back(&mut self) {
+        self.stop_movie();
+
+        if self.session.step_back() {
+            self.new_frames += 1;
+        }
+    }
+
+    /// Restarts the current ROM and records the keypad from the first instruction
+    pub fn start_recording(&mut self) {
+        self.stop_movie();
         self.reset_device();
+        self.recorder = Some(MovieRecorder::new(&self.session.rom().data, &self.session.chip8, &self.session.scheduler));
+        log::info!("Recording {}", self.session.rom().name);
+    }
+
+    pub fn stop_recording(&mut self) {
+        let Some(recorder) = self.recorder.take() else {
+            return;
+        };
+
+        let movie = recorder.finish(&self.session.chip8);
+        match storage::write(MOVIE_FILE, &movie.to_text()) {
+            Ok(()) => log::info!("Saved {} key changes to {}", movie.events.len(), MOVIE_FILE),
+            Err(error) => log::error!("Failed to save {}: {}", MOVIE_FILE, error),
+        }
+    }
+
+    pub fn play_movie(&mut self, text: &str) {
+        let movie = match Movie::from_text(text) {
+            Ok(movie) => movie,
+            Err(error) => {
+                log::error!("Failed to read movie: {}", error);
+                return;
+            }
+        };
+
+        let Some(id) = self.session.roms.iter().position(|rom| sha1(&rom.data) == movie.rom_hash) else {
+            log::error!("The ROM this movie was recorded with isn't available");
+            return;
+        };
+
+        self.set_rom(id as u8);
+        self.player = MoviePlayer::start(movie, &self.session.roms[id].data, &mut self.session.chip8, &mut self.session.scheduler);
+        log::info!("Playing movie of {}", self.session.roms[id].name);
+    }
+
+    /// Ends the recording or playback, a recording is saved
+    pub fn stop_movie(&mut self) {
+        self.stop_recording();
+
+        if self.player.take().is_some() {
+            log::info!("Playback stopped");
+        }
     }
 
     pub fn rom_list(&self) -> Vec<String> {
-        self.roms.iter().map(|rom| rom.name.clone()).collect()
+        self.session.roms.iter().map(|rom| rom.name.clone()).collect()
     }
 }
 
@@ -309,23 +1431,60 @@ impl ShaderUniform {
         ShaderUniform {
             width: 320.0,
             height: 160.0,
-            value: [0; 2048],
-            padding: [0; 8],
+            columns: LORES_WIDTH as f32,
+            rows: LORES_HEIGHT as f32,
+            value: [0; 512],
+            palette: DEFAULT_PALETTE,
+            persistence: 0,
+            decay: 0.0,
+            frames: 0,
+            reset: 1,
+            advance: 0,
+            padding: [0; 3],
         }
     }
 
-    pub fn from_display(display: [[bool; 64]; 32], width: u32, height: u32) -> Self {
+    /// How the display turns the pixel history into brightness, after `advance` emulated frames
+    /// since the last render. Without a new frame only the current frame is replaced, so a paused
+    /// machine keeps its history. Resetting forgets the history.
+    pub fn set_persistence(&mut self, persistence: Persistence, advance: u32, reset: bool) {
+        self.persistence = match persistence {
+            Persistence::Off => 0,
+            Persistence::LastFrame => 1,
+            Persistence::Decay { .. } => 2,
+            Persistence::Majority { .. } => 3,
+        };
+        self.decay = persistence.decay(advance as f32 * 1000.0 / TIMER_FREQUENCY as f32);
+        self.frames = match persistence {
+            Persistence::Majority { frames } => frames,
+            _ => 0,
+        };
+        self.reset = reset as u32;
+        self.advance = advance;
+    }
+
+    /// Packs every plane into one bit per pixel, row by row, so both resolutions fit the same buffer.
+    /// The second plane starts halfway into the buffer.
+    pub fn from_display(display: &Display, palette: Palette, width: u32, height: u32) -> Self {
         let mut n = ShaderUniform {
-            value: [0; 2048],
+            value: [0; 512],
+            palette,
             width: width as f32,
             height: height as f32,
-            padding: [0; 8],
+            columns: display.width() as f32,
+            rows: display.height() as f32,
+            persistence: 0,
+            decay: 0.0,
+            frames: 0,
+            reset: 0,
+            advance: 0,
+            padding: [0; 3],
         };
 
-        for (row_index, row) in display.iter().enumerate() {
-            for (col_index, &col_value) in row.iter().enumerate() {
-                if col_value {
-                    n.value[row_index * 64 + col_index] = 1;
+        for (index, &pixel) in display.pixels().iter().enumerate() {
+            for plane in 0..2 {
+                if pixel & (1 << plane) != 0 {
+                    n.value[plane * 256 + index / 32] |= 1 << (index % 32);
                 }
             }
         }
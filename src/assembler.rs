@@ -0,0 +1,532 @@
+//! Assembles Octo style source into a ROM that is loaded at 0x200.
+//!
+//! Supported are labels (`: name`), constants (`:const name value`), register names
+//! (`:alias name vx`), subroutine calls (`:call name` or just `name`), data bytes written as bare
+//! numbers, and every instruction the [`Chip8`](crate::chip8::Chip8) core decodes, written the
+//! way the disassembler prints them. Labels can be used before they are defined.
+//!
+//! Listings in the Octo syntax of the [`disassembler`](crate::disassembler) assemble back into
+//! the same bytes. The Cowgod syntax is only meant for reading and can't be assembled.
+
+use std::collections::HashMap;
+use std::fmt;
+
+const PROGRAM_START: u16 = 0x200;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssemblerError {
+    UnexpectedEnd { line: usize },
+    UnexpectedToken { line: usize, token: String },
+    InvalidRegister { line: usize, token: String },
+    ValueOutOfRange { line: usize, value: i64 },
+    UndefinedName { line: usize, name: String },
+    DuplicateName { line: usize, name: String },
+    RomTooLarge,
+}
+
+impl fmt::Display for AssemblerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssemblerError::UnexpectedEnd { line } =>
+                write!(f, "Line {}: unexpected end of source", line),
+            AssemblerError::UnexpectedToken { line, token } =>
+                write!(f, "Line {}: unexpected '{}'", line, token),
+            AssemblerError::InvalidRegister { line, token } =>
+                write!(f, "Line {}: '{}' is not a register", line, token),
+            AssemblerError::ValueOutOfRange { line, value } =>
+                write!(f, "Line {}: value {} is out of range", line, value),
+            AssemblerError::UndefinedName { line, name } =>
+                write!(f, "Line {}: '{}' is not defined", line, name),
+            AssemblerError::DuplicateName { line, name } =>
+                write!(f, "Line {}: '{}' is already defined", line, name),
+            AssemblerError::RomTooLarge =>
+                write!(f, "Program does not fit in memory"),
+        }
+    }
+}
+
+impl std::error::Error for AssemblerError {}
+
+pub struct Assembly {
+    pub rom: Vec<u8>,
+    /// Every label with its address, in order of definition
+    pub symbols: Vec<(String, u16)>,
+}
+
+impl Assembly {
+    /// One `address name` line per label, sorted by address
+    pub fn symbol_map(&self) -> String {
+        let mut symbols = self.symbols.clone();
+        symbols.sort_by_key(|(_, address)| *address);
+
+        symbols.iter().map(|(name, address)| format!("0x{:03X} {}\n", address, name)).collect()
+    }
+}
+
+pub fn assemble(source: &str) -> Result<Assembly, AssemblerError> {
+    let mut assembler = Assembler {
+        tokens: tokenize(source),
+        position: 0,
+        rom: vec![],
+        labels: HashMap::new(),
+        symbols: vec![],
+        constants: HashMap::new(),
+        aliases: HashMap::new(),
+        fixups: vec![],
+    };
+
+    while assembler.position < assembler.tokens.len() {
+        assembler.statement()?;
+    }
+
+    assembler.resolve_fixups()?;
+
+    if PROGRAM_START as usize + assembler.rom.len() > 0x10000 {
+        return Err(AssemblerError::RomTooLarge);
+    }
+
+    Ok(Assembly { rom: assembler.rom, symbols: assembler.symbols })
+}
+
+#[derive(Copy, Clone)]
+struct Token<'a> {
+    line: usize,
+    text: &'a str,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum FixupKind {
+    /// Lower 12 bits of the instruction at the offset
+    Address,
+    /// Full 16 bit word at the offset
+    LongAddress,
+}
+
+struct Fixup {
+    offset: usize,
+    kind: FixupKind,
+    name: String,
+    line: usize,
+}
+
+/// Either a resolved value or a label that isn't defined yet
+enum Target {
+    Value(u16),
+    Label(String),
+}
+
+struct Assembler<'a> {
+    tokens: Vec<Token<'a>>,
+    position: usize,
+    rom: Vec<u8>,
+    labels: HashMap<String, u16>,
+    symbols: Vec<(String, u16)>,
+    constants: HashMap<String, i64>,
+    aliases: HashMap<String, u8>,
+    fixups: Vec<Fixup>,
+}
+
+fn tokenize(source: &str) -> Vec<Token<'_>> {
+    source.lines().enumerate()
+        .flat_map(|(index, line)| {
+            let code = line.split('#').next().unwrap_or("");
+            code.split_whitespace().map(move |text| Token { line: index + 1, text })
+        })
+        .collect()
+}
+
+impl<'a> Assembler<'a> {
+    fn statement(&mut self) -> Result<(), AssemblerError> {
+        let token = self.next()?;
+        let line = token.line;
+
+        match token.text {
+            ":" => {
+                let name = self.name()?;
+                let address = self.address();
+                if self.labels.insert(name.to_string(), address).is_some() {
+                    return Err(AssemblerError::DuplicateName { line, name: name.to_string() });
+                }
+                self.symbols.push((name.to_string(), address));
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.value()?;
+                if self.constants.insert(name.to_string(), value).is_some() {
+                    return Err(AssemblerError::DuplicateName { line, name: name.to_string() });
+                }
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.register()?;
+                self.aliases.insert(name.to_string(), register as u8);
+            }
+            ":call" => self.address_instruction(0x2000)?,
+            "clear" => self.emit(0x00E0),
+            "return" => self.emit(0x00EE),
+            "scroll-right" => self.emit(0x00FB),
+            "scroll-left" => self.emit(0x00FC),
+            "exit" => self.emit(0x00FD),
+            "lores" => self.emit(0x00FE),
+            "hires" => self.emit(0x00FF),
+            "audio" => self.emit(0xF002),
+            "scroll-down" => {
+                let n = self.nibble()?;
+                self.emit(0x00C0 | n);
+            }
+            "scroll-up" => {
+                let n = self.nibble()?;
+                self.emit(0x00D0 | n);
+            }
+            "jump" => self.address_instruction(0x1000)?,
+            "jump0" => self.address_instruction(0xB000)?,
+            "if" => self.condition()?,
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let n = self.nibble()?;
+                self.emit(0xD000 | x << 8 | y << 4 | n);
+            }
+            "save" => self.register_range(0xF055, 0x5002)?,
+            "load" => self.register_range(0xF065, 0x5003)?,
+            "saveflags" => self.register_instruction(0xF075)?,
+            "loadflags" => self.register_instruction(0xF085)?,
+            "bcd" => self.register_instruction(0xF033)?,
+            "plane" => {
+                let n = self.nibble()?;
+                self.emit(0xF001 | n << 8);
+            }
+            "delay" => {
+                self.expect(":=")?;
+                self.register_instruction(0xF015)?;
+            }
+            "buzzer" => {
+                self.expect(":=")?;
+                self.register_instruction(0xF018)?;
+            }
+            "pitch" => {
+                self.expect(":=")?;
+                self.register_instruction(0xF03A)?;
+            }
+            "i" => self.index_assignment()?,
+            text if self.is_register(text) => {
+                let x = self.to_register(token)?;
+                self.register_assignment(x)?;
+            }
+            text if self.constants.contains_key(text) || parse_number(text).is_some() => {
+                self.position -= 1;
+                let byte = self.byte()?;
+                self.rom.push(byte);
+            }
+            text if is_name(text) => {
+                self.position -= 1;
+                self.address_instruction(0x2000)?;
+            }
+            text => return Err(AssemblerError::UnexpectedToken { line, token: text.to_string() }),
+        }
+
+        Ok(())
+    }
+
+    /// `if vx <op> <operand> then`, Octo describes when the next instruction runs so every
+    /// condition maps to the skip that tests its opposite
+    fn condition(&mut self) -> Result<(), AssemblerError> {
+        let x = self.register()?;
+        let comparison = self.next()?;
+
+        let instruction = match comparison.text {
+            "key" => 0xE0A1 | x << 8,
+            "-key" => 0xE09E | x << 8,
+            "==" | "!=" => {
+                let equal = comparison.text == "==";
+                if self.peek().is_some_and(|token| self.is_register(token.text)) {
+                    let y = self.register()?;
+                    (if equal { 0x9000 } else { 0x5000 }) | x << 8 | y << 4
+                } else {
+                    let nn = self.byte()? as u16;
+                    (if equal { 0x4000 } else { 0x3000 }) | x << 8 | nn
+                }
+            }
+            text => return Err(AssemblerError::UnexpectedToken { line: comparison.line, token: text.to_string() }),
+        };
+
+        self.expect("then")?;
+        self.emit(instruction);
+        Ok(())
+    }
+
+    fn index_assignment(&mut self) -> Result<(), AssemblerError> {
+        let operator = self.next()?;
+
+        match operator.text {
+            "+=" => self.register_instruction(0xF01E),
+            ":=" => match self.peek().map(|token| token.text) {
+                Some("hex") => {
+                    self.position += 1;
+                    self.register_instruction(0xF029)
+                }
+                Some("bighex") => {
+                    self.position += 1;
+                    self.register_instruction(0xF030)
+                }
+                Some("long") => {
+                    self.position += 1;
+                    let line = self.line();
+                    let target = self.target(0xFFFF)?;
+                    self.emit(0xF000);
+                    self.emit(0x0000);
+                    self.emit_target(target, FixupKind::LongAddress, line);
+                    Ok(())
+                }
+                _ => self.address_instruction(0xA000),
+            },
+            text => Err(AssemblerError::UnexpectedToken { line: operator.line, token: text.to_string() }),
+        }
+    }
+
+    fn register_assignment(&mut self, x: u16) -> Result<(), AssemblerError> {
+        let operator = self.next()?;
+
+        let instruction = match operator.text {
+            ":=" => match self.peek().map(|token| token.text) {
+                Some("random") => {
+                    self.position += 1;
+                    0xC000 | x << 8 | self.byte()? as u16
+                }
+                Some("delay") => {
+                    self.position += 1;
+                    0xF007 | x << 8
+                }
+                Some("key") => {
+                    self.position += 1;
+                    0xF00A | x << 8
+                }
+                Some(text) if self.is_register(text) => 0x8000 | x << 8 | self.register()? << 4,
+                _ => 0x6000 | x << 8 | self.byte()? as u16,
+            },
+            "+=" => match self.peek() {
+                Some(token) if self.is_register(token.text) => 0x8004 | x << 8 | self.register()? << 4,
+                _ => 0x7000 | x << 8 | self.byte()? as u16,
+            },
+            "-=" => match self.peek() {
+                Some(token) if self.is_register(token.text) => 0x8005 | x << 8 | self.register()? << 4,
+                _ => 0x7000 | x << 8 | (self.byte()? as u16).wrapping_neg() & 0xFF,
+            },
+            "|=" => 0x8001 | x << 8 | self.register()? << 4,
+            "&=" => 0x8002 | x << 8 | self.register()? << 4,
+            "^=" => 0x8003 | x << 8 | self.register()? << 4,
+            ">>=" => 0x8006 | x << 8 | self.register()? << 4,
+            "=-" => 0x8007 | x << 8 | self.register()? << 4,
+            "<<=" => 0x800E | x << 8 | self.register()? << 4,
+            text => return Err(AssemblerError::UnexpectedToken { line: operator.line, token: text.to_string() }),
+        };
+
+        self.emit(instruction);
+        Ok(())
+    }
+
+    /// `save vx` / `load vx`, or the XO-CHIP `save vx - vy` / `load vx - vy` ranges
+    fn register_range(&mut self, single: u16, range: u16) -> Result<(), AssemblerError> {
+        let x = self.register()?;
+
+        if self.peek().map(|token| token.text) == Some("-") {
+            self.position += 1;
+            let y = self.register()?;
+            self.emit(range | x << 8 | y << 4);
+        } else {
+            self.emit(single | x << 8);
+        }
+
+        Ok(())
+    }
+
+    fn register_instruction(&mut self, instruction: u16) -> Result<(), AssemblerError> {
+        let x = self.register()?;
+        self.emit(instruction | x << 8);
+        Ok(())
+    }
+
+    fn address_instruction(&mut self, instruction: u16) -> Result<(), AssemblerError> {
+        let line = self.line();
+        let target = self.target(0xFFF)?;
+        self.emit(instruction);
+        self.emit_target(target, FixupKind::Address, line);
+        Ok(())
+    }
+
+    /// Writes an address into the word that was just emitted, or remembers to do so once the
+    /// label is known
+    fn emit_target(&mut self, target: Target, kind: FixupKind, line: usize) {
+        let offset = self.rom.len() - 2;
+
+        match target {
+            Target::Value(value) => patch(&mut self.rom, offset, kind, value),
+            Target::Label(name) => self.fixups.push(Fixup { offset, kind, name, line }),
+        }
+    }
+
+    fn resolve_fixups(&mut self) -> Result<(), AssemblerError> {
+        for fixup in &self.fixups {
+            let Some(&address) = self.labels.get(&fixup.name) else {
+                return Err(AssemblerError::UndefinedName { line: fixup.line, name: fixup.name.clone() });
+            };
+
+            if let FixupKind::Address = fixup.kind {
+                if address > 0xFFF {
+                    return Err(AssemblerError::ValueOutOfRange { line: fixup.line, value: address as i64 });
+                }
+            }
+
+            patch(&mut self.rom, fixup.offset, fixup.kind, address);
+        }
+
+        Ok(())
+    }
+
+    fn target(&mut self, max: i64) -> Result<Target, AssemblerError> {
+        let token = self.next()?;
+
+        if let Some(&address) = self.labels.get(token.text) {
+            return self.range_checked(token.line, address as i64, 0, max).map(Target::Value);
+        }
+
+        match self.resolve(token.text) {
+            Some(value) => self.range_checked(token.line, value, 0, max).map(Target::Value),
+            None if is_name(token.text) => Ok(Target::Label(token.text.to_string())),
+            None => Err(AssemblerError::UnexpectedToken { line: token.line, token: token.text.to_string() }),
+        }
+    }
+
+    fn emit(&mut self, instruction: u16) {
+        self.rom.extend_from_slice(&instruction.to_be_bytes());
+    }
+
+    fn address(&self) -> u16 {
+        PROGRAM_START.wrapping_add(self.rom.len() as u16)
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, AssemblerError> {
+        let token = *self.tokens.get(self.position).ok_or(AssemblerError::UnexpectedEnd { line: self.line() })?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.position).copied()
+    }
+
+    /// Line of the next token, or the last line when the source has ended
+    fn line(&self) -> usize {
+        self.tokens.get(self.position).or(self.tokens.last()).map_or(1, |token| token.line)
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), AssemblerError> {
+        let token = self.next()?;
+
+        if token.text != text {
+            return Err(AssemblerError::UnexpectedToken { line: token.line, token: token.text.to_string() });
+        }
+
+        Ok(())
+    }
+
+    fn name(&mut self) -> Result<&'a str, AssemblerError> {
+        let token = self.next()?;
+
+        if !is_name(token.text) {
+            return Err(AssemblerError::UnexpectedToken { line: token.line, token: token.text.to_string() });
+        }
+
+        Ok(token.text)
+    }
+
+    fn value(&mut self) -> Result<i64, AssemblerError> {
+        let token = self.next()?;
+        self.resolve(token.text).ok_or(AssemblerError::UnexpectedToken { line: token.line, token: token.text.to_string() })
+    }
+
+    /// A byte, negative values are stored as two's complement
+    fn byte(&mut self) -> Result<u8, AssemblerError> {
+        let line = self.line();
+        let value = self.value()?;
+        self.range_checked(line, value, -128, 0xFF).map(|value| value as u8)
+    }
+
+    fn nibble(&mut self) -> Result<u16, AssemblerError> {
+        let line = self.line();
+        let value = self.value()?;
+        self.range_checked(line, value, 0, 0xF)
+    }
+
+    fn range_checked(&self, line: usize, value: i64, min: i64, max: i64) -> Result<u16, AssemblerError> {
+        if value < min || value > max {
+            return Err(AssemblerError::ValueOutOfRange { line, value });
+        }
+
+        Ok(value as u16)
+    }
+
+    fn register(&mut self) -> Result<u16, AssemblerError> {
+        let token = self.next()?;
+        self.to_register(token)
+    }
+
+    fn to_register(&self, token: Token) -> Result<u16, AssemblerError> {
+        parse_register(token.text)
+            .or_else(|| self.aliases.get(token.text).copied())
+            .map(|register| register as u16)
+            .ok_or(AssemblerError::InvalidRegister { line: token.line, token: token.text.to_string() })
+    }
+
+    fn is_register(&self, text: &str) -> bool {
+        parse_register(text).is_some() || self.aliases.contains_key(text)
+    }
+
+    fn resolve(&self, text: &str) -> Option<i64> {
+        self.constants.get(text).copied().or_else(|| parse_number(text))
+    }
+}
+
+fn patch(rom: &mut [u8], offset: usize, kind: FixupKind, address: u16) {
+    match kind {
+        FixupKind::Address => {
+            rom[offset] = (rom[offset] & 0xF0) | (address >> 8) as u8 & 0x0F;
+            rom[offset + 1] = address as u8;
+        }
+        FixupKind::LongAddress => rom[offset..offset + 2].copy_from_slice(&address.to_be_bytes()),
+    }
+}
+
+fn parse_register(text: &str) -> Option<u8> {
+    let digit = text.strip_prefix('v').or_else(|| text.strip_prefix('V'))?;
+
+    if digit.len() != 1 {
+        return None;
+    }
+
+    u8::from_str_radix(digit, 16).ok()
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+
+    let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b").or_else(|| digits.strip_prefix("0B")) {
+        i64::from_str_radix(binary, 2).ok()?
+    } else {
+        digits.parse::<i64>().ok()?
+    };
+
+    Some(if negative { -value } else { value })
+}
+
+fn is_name(text: &str) -> bool {
+    let mut characters = text.chars();
+
+    matches!(characters.next(), Some(first) if first.is_ascii_alphabetic() || first == '_')
+        && characters.all(|character| character.is_ascii_alphanumeric() || character == '_' || character == '-')
+}
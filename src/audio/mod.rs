@@ -0,0 +1,144 @@
+//! The CHIP-8 beeper: a tone that plays while the sound timer is running.
+//!
+//! Backends are told once per 60 Hz frame whether the tone should play. The native and Web
+//! Audio backends produce sound in real time, the WAV backend renders the samples of every frame
+//! into a file so the output can be checked without a sound card.
+
+use std::f32::consts::TAU;
+
+#[cfg(all(feature = "native-audio", not(target_arch = "wasm32")))]
+pub mod native;
+pub mod wav;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
+
+/// Time the volume takes to fade in and out, prevents clicks when the tone starts and stops
+const FADE_SECONDS: f32 = 0.002;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Triangle,
+    Sawtooth,
+    Sine,
+}
+
+impl Waveform {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "square" => Some(Waveform::Square),
+            "triangle" => Some(Waveform::Triangle),
+            "sawtooth" => Some(Waveform::Sawtooth),
+            "sine" => Some(Waveform::Sine),
+            _ => None,
+        }
+    }
+
+    /// Value between -1 and 1 at `phase`, which runs from 0 to 1 over one period
+    fn sample(&self, phase: f32) -> f32 {
+        match self {
+            Waveform::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+            Waveform::Sine => (phase * TAU).sin(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AudioConfig {
+    pub waveform: Waveform,
+    /// Tone frequency in Hz
+    pub frequency: f32,
+    /// From 0 (silent) to 1
+    pub volume: f32,
+}
+
+impl AudioConfig {
+    pub fn new() -> Self {
+        AudioConfig {
+            waveform: Waveform::Square,
+            frequency: 440.0,
+            volume: 0.25,
+        }
+    }
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig::new()
+    }
+}
+
+pub trait AudioBackend {
+    /// Called once per 60 Hz frame with whether the sound timer is running, and with false when the
+    /// machine stops running
+    fn frame(&mut self, playing: bool);
+
+    fn set_config(&mut self, config: AudioConfig);
+}
+
+/// Backend for builds without sound output
+pub struct SilentBackend;
+
+impl AudioBackend for SilentBackend {
+    fn frame(&mut self, _playing: bool) {}
+
+    fn set_config(&mut self, _config: AudioConfig) {}
+}
+
+/// The real time backend for this platform, silent when there is no sound output
+pub fn create_backend(config: AudioConfig) -> Box<dyn AudioBackend> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        Box::new(web::WebAudioBackend::new(config))
+    }
+    #[cfg(all(feature = "native-audio", not(target_arch = "wasm32")))]
+    {
+        match native::NativeBackend::new(config) {
+            Some(backend) => Box::new(backend),
+            None => {
+                log::warn!("No audio output device, sound is disabled");
+                Box::new(SilentBackend)
+            }
+        }
+    }
+    #[cfg(all(not(feature = "native-audio"), not(target_arch = "wasm32")))]
+    {
+        let _ = config;
+        Box::new(SilentBackend)
+    }
+}
+
+/// Produces the samples of the tone one at a time
+pub struct ToneGenerator {
+    config: AudioConfig,
+    sample_rate: f32,
+    phase: f32,
+    level: f32,
+}
+
+impl ToneGenerator {
+    pub fn new(config: AudioConfig, sample_rate: f32) -> Self {
+        ToneGenerator {
+            config,
+            sample_rate,
+            phase: 0.0,
+            level: 0.0,
+        }
+    }
+
+    pub fn set_config(&mut self, config: AudioConfig) {
+        self.config = config;
+    }
+
+    pub fn next_sample(&mut self, playing: bool) -> f32 {
+        let target = if playing { self.config.volume.clamp(0.0, 1.0) } else { 0.0 };
+        let step = 1.0 / (FADE_SECONDS * self.sample_rate);
+        self.level = if self.level < target { (self.level + step).min(target) } else { (self.level - step).max(target) };
+
+        let sample = self.config.waveform.sample(self.phase) * self.level;
+        self.phase = (self.phase + self.config.frequency / self.sample_rate).fract();
+        sample
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::{Arc, Mutex};
+
+use cpal::{FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+use crate::audio::{AudioBackend, AudioConfig, ToneGenerator};
+
+struct Tone {
+    generator: ToneGenerator,
+    playing: bool,
+}
+
+/// Plays the tone on the default output device through cpal
+pub struct NativeBackend {
+    tone: Arc<Mutex<Tone>>,
+    // The sound stops when the stream is dropped
+    _stream: Stream,
+}
+
+impl NativeBackend {
+    pub fn new(config: AudioConfig) -> Option<Self> {
+        let device = cpal::default_host().default_output_device()?;
+        let supported = device.default_output_config().ok()?;
+        let sample_format = supported.sample_format();
+        let stream_config: StreamConfig = supported.into();
+
+        let tone = Arc::new(Mutex::new(Tone {
+            generator: ToneGenerator::new(config, stream_config.sample_rate.0 as f32),
+            playing: false,
+        }));
+
+        let stream = match sample_format {
+            SampleFormat::F32 => build_stream::<f32>(&device, &stream_config, tone.clone()),
+            SampleFormat::I16 => build_stream::<i16>(&device, &stream_config, tone.clone()),
+            SampleFormat::U16 => build_stream::<u16>(&device, &stream_config, tone.clone()),
+            format => {
+                log::warn!("Unsupported sample format {}", format);
+                None
+            }
+        }?;
+        stream.play().ok()?;
+
+        Some(NativeBackend { tone, _stream: stream })
+    }
+}
+
+impl AudioBackend for NativeBackend {
+    fn frame(&mut self, playing: bool) {
+        if let Ok(mut tone) = self.tone.lock() {
+            tone.playing = playing;
+        }
+    }
+
+    fn set_config(&mut self, config: AudioConfig) {
+        if let Ok(mut tone) = self.tone.lock() {
+            tone.generator.set_config(config);
+        }
+    }
+}
+
+fn build_stream<T: SizedSample + FromSample<f32>>(device: &cpal::Device, config: &StreamConfig, tone: Arc<Mutex<Tone>>) -> Option<Stream> {
+    let channels = config.channels as usize;
+
+    device.build_output_stream(
+        config,
+        move |output: &mut [T], _| {
+            let Ok(mut tone) = tone.lock() else {
+                return;
+            };
+            let playing = tone.playing;
+
+            for frame in output.chunks_mut(channels) {
+                let sample = T::from_sample(tone.generator.next_sample(playing));
+                frame.fill(sample);
+            }
+        },
+        |error| log::error!("Audio output failed: {}", error),
+        None,
+    ).ok()
+}
@@ -0,0 +1,77 @@
+use crate::audio::{AudioBackend, AudioConfig, ToneGenerator};
+
+const FRAMES_PER_SECOND: u32 = 60;
+
+/// Renders the tone into 16-bit mono samples instead of playing it
+pub struct WavRenderer {
+    generator: ToneGenerator,
+    sample_rate: u32,
+    samples: Vec<i16>,
+    /// Fraction of a sample left over from the previous frames, keeps rates that aren't a
+    /// multiple of 60 in sync
+    remainder: u32,
+}
+
+impl WavRenderer {
+    pub fn new(config: AudioConfig, sample_rate: u32) -> Self {
+        WavRenderer {
+            generator: ToneGenerator::new(config, sample_rate as f32),
+            sample_rate,
+            samples: vec![],
+            remainder: 0,
+        }
+    }
+
+    pub fn samples(&self) -> &[i16] {
+        &self.samples
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The rendered samples as a PCM WAV file
+    pub fn to_wav(&self) -> Vec<u8> {
+        let data_size = self.samples.len() as u32 * 2;
+
+        let mut wav = Vec::with_capacity(44 + data_size as usize);
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+        wav.extend_from_slice(b"WAVE");
+
+        wav.extend_from_slice(b"fmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        // PCM, one channel
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&self.sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(self.sample_rate * 2).to_le_bytes());
+        // Block alignment and bits per sample
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_size.to_le_bytes());
+        for sample in &self.samples {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        wav
+    }
+}
+
+impl AudioBackend for WavRenderer {
+    fn frame(&mut self, playing: bool) {
+        let total = self.sample_rate + self.remainder;
+        self.remainder = total % FRAMES_PER_SECOND;
+
+        for _ in 0..total / FRAMES_PER_SECOND {
+            let sample = self.generator.next_sample(playing);
+            self.samples.push((sample * i16::MAX as f32) as i16);
+        }
+    }
+
+    fn set_config(&mut self, config: AudioConfig) {
+        self.generator.set_config(config);
+    }
+}
@@ -0,0 +1,88 @@
+use web_sys::{AudioContext, GainNode, OscillatorNode, OscillatorType};
+
+use crate::audio::{AudioBackend, AudioConfig, Waveform};
+
+/// Time constant of the gain ramp in seconds, about the same fade as the sample based backends
+const RAMP_TIME_CONSTANT: f64 = 0.0005;
+
+struct Nodes {
+    context: AudioContext,
+    oscillator: OscillatorNode,
+    gain: GainNode,
+}
+
+/// Plays the tone with a Web Audio oscillator. The nodes are only created once the tone plays for
+/// the first time, browsers don't allow audio before the page has been interacted with.
+pub struct WebAudioBackend {
+    config: AudioConfig,
+    nodes: Option<Nodes>,
+    playing: bool,
+}
+
+impl WebAudioBackend {
+    pub fn new(config: AudioConfig) -> Self {
+        WebAudioBackend {
+            config,
+            nodes: None,
+            playing: false,
+        }
+    }
+
+    fn create_nodes(config: &AudioConfig) -> Option<Nodes> {
+        let context = AudioContext::new().ok()?;
+        let oscillator = context.create_oscillator().ok()?;
+        let gain = context.create_gain().ok()?;
+
+        gain.gain().set_value(0.0);
+        oscillator.connect_with_audio_node(&gain).ok()?;
+        gain.connect_with_audio_node(&context.destination()).ok()?;
+        apply_config(&oscillator, config);
+        oscillator.start().ok()?;
+
+        Some(Nodes { context, oscillator, gain })
+    }
+
+    fn set_volume(&self) {
+        let Some(nodes) = &self.nodes else {
+            return;
+        };
+
+        let volume = if self.playing { self.config.volume.clamp(0.0, 1.0) } else { 0.0 };
+        let _ = nodes.context.resume();
+        let _ = nodes.gain.gain().set_target_at_time(volume, nodes.context.current_time(), RAMP_TIME_CONSTANT);
+    }
+}
+
+impl AudioBackend for WebAudioBackend {
+    fn frame(&mut self, playing: bool) {
+        if playing == self.playing {
+            return;
+        }
+
+        if playing && self.nodes.is_none() {
+            self.nodes = WebAudioBackend::create_nodes(&self.config);
+        }
+
+        self.playing = playing;
+        self.set_volume();
+    }
+
+    fn set_config(&mut self, config: AudioConfig) {
+        self.config = config;
+
+        if let Some(nodes) = &self.nodes {
+            apply_config(&nodes.oscillator, &config);
+        }
+        self.set_volume();
+    }
+}
+
+fn apply_config(oscillator: &OscillatorNode, config: &AudioConfig) {
+    oscillator.set_type(match config.waveform {
+        Waveform::Square => OscillatorType::Square,
+        Waveform::Triangle => OscillatorType::Triangle,
+        Waveform::Sawtooth => OscillatorType::Sawtooth,
+        Waveform::Sine => OscillatorType::Sine,
+    });
+    oscillator.frequency().set_value(config.frequency);
+}
@@ -0,0 +1,48 @@
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
+
+use chip8::assembler::assemble;
+
+fn main() {
+    let mut source_path = None;
+    let mut output_path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output_path = Some(PathBuf::from(args.next().unwrap_or_else(|| usage()))),
+            "--help" | "-h" => usage(),
+            _ if source_path.is_none() => source_path = Some(PathBuf::from(arg)),
+            _ => usage(),
+        }
+    }
+
+    let Some(source_path) = source_path else {
+        usage();
+    };
+    let output_path = output_path.unwrap_or_else(|| source_path.with_extension("ch8"));
+    let symbol_path = output_path.with_extension("sym");
+
+    let source = fs::read_to_string(&source_path).unwrap_or_else(|error| fail(&source_path, error));
+
+    let assembly = assemble(&source).unwrap_or_else(|error| {
+        eprintln!("{}: {}", source_path.display(), error);
+        process::exit(1);
+    });
+
+    fs::write(&output_path, &assembly.rom).unwrap_or_else(|error| fail(&output_path, error));
+    fs::write(&symbol_path, assembly.symbol_map()).unwrap_or_else(|error| fail(&symbol_path, error));
+
+    println!("Wrote {} bytes to {}", assembly.rom.len(), output_path.display());
+}
+
+fn fail(path: &Path, error: std::io::Error) -> ! {
+    eprintln!("{}: {}", path.display(), error);
+    process::exit(1);
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: chip8-asm <source.8o> [-o <rom.ch8>]");
+    eprintln!("Takes Octo syntax, like the output of chip8-disasm --syntax octo");
+    process::exit(1);
+}
@@ -0,0 +1,46 @@
+use std::{env, fs, process};
+
+use chip8::disassembler::{disassemble, Syntax};
+
+fn main() {
+    let mut syntax = Syntax::Octo;
+    let mut path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--syntax" => {
+                syntax = match args.next().as_deref() {
+                    Some("octo") => Syntax::Octo,
+                    Some("cowgod") => Syntax::Cowgod,
+                    _ => usage(),
+                }
+            }
+            "--help" | "-h" => usage(),
+            _ if path.is_none() => path = Some(arg),
+            _ => usage(),
+        }
+    }
+
+    let Some(path) = path else {
+        usage();
+    };
+
+    let rom = fs::read(&path).unwrap_or_else(|error| {
+        eprintln!("Failed to read {}: {}", path, error);
+        process::exit(1);
+    });
+
+    if syntax == Syntax::Octo {
+        println!("# Disassembly of {}", path);
+    } else {
+        println!("; Disassembly of {}", path);
+    }
+    print!("{}", disassemble(&rom, syntax));
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: chip8-disasm [--syntax octo|cowgod] <rom.ch8>");
+    eprintln!("Only the octo syntax can be assembled again with chip8-asm");
+    process::exit(1);
+}
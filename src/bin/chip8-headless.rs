@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
+
+use chip8::audio::{AudioBackend, AudioConfig, Waveform};
+use chip8::audio::wav::WavRenderer;
+use chip8::chip8::Chip8Rom;
+use chip8::chip8::quirks::{PROFILE_NAMES, QuirkConfig};
+use chip8::headless::{DEFAULT_INSTRUCTIONS_PER_FRAME, HeadlessRunner, parse_key_script, to_ascii, to_pbm, to_png};
+
+const DEFAULT_FRAMES: u32 = 600;
+const DEFAULT_SEED: u64 = 0;
+const PNG_SCALE: usize = 4;
+const WAV_SAMPLE_RATE: u32 = 44100;
+
+enum Format {
+    Ascii,
+    Pbm,
+    Png,
+}
+
+fn main() {
+    let mut rom_path = None;
+    let mut quirks = None;
+    let mut instructions_per_frame = DEFAULT_INSTRUCTIONS_PER_FRAME;
+    let mut frames = DEFAULT_FRAMES;
+    let mut seed = DEFAULT_SEED;
+    let mut key_script = None;
+    let mut output = None;
+    let mut format = None;
+    let mut wav_path = None;
+    let mut audio = AudioConfig::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+
+        match arg.as_str() {
+            "--profile" => quirks = Some(QuirkConfig::from_profile_name(&value()).unwrap_or_else(|| usage())),
+            "--ipf" => instructions_per_frame = value().parse().unwrap_or_else(|_| usage()),
+            "--frames" => frames = value().parse().unwrap_or_else(|_| usage()),
+            "--seed" => seed = value().parse().unwrap_or_else(|_| usage()),
+            "--keys" => key_script = Some(PathBuf::from(value())),
+            "--output" | "-o" => output = Some(PathBuf::from(value())),
+            "--format" => format = Some(parse_format(&value()).unwrap_or_else(|| usage())),
+            "--wav" => wav_path = Some(PathBuf::from(value())),
+            "--waveform" => audio.waveform = Waveform::from_name(&value()).unwrap_or_else(|| usage()),
+            "--frequency" => audio.frequency = value().parse().unwrap_or_else(|_| usage()),
+            "--volume" => audio.volume = value().parse().unwrap_or_else(|_| usage()),
+            "--help" | "-h" => usage(),
+            _ if rom_path.is_none() => rom_path = Some(PathBuf::from(arg)),
+            _ => usage(),
+        }
+    }
+
+    let Some(rom_path) = rom_path else {
+        usage();
+    };
+
+    let data = fs::read(&rom_path).unwrap_or_else(|error| fail(&rom_path, error));
+    let name = rom_path.file_stem().map_or("rom".to_string(), |stem| stem.to_string_lossy().to_string());
+    let mut chip8 = Chip8Rom { name: name.clone(), data, quirks }.to_device();
+    chip8.set_seed(seed);
+
+    let mut runner = HeadlessRunner::new(chip8, instructions_per_frame);
+
+    if let Some(path) = key_script {
+        let script = fs::read_to_string(&path).unwrap_or_else(|error| fail(&path, error));
+        let events = parse_key_script(&script).unwrap_or_else(|error| {
+            eprintln!("{}: {}", path.display(), error);
+            process::exit(1);
+        });
+        runner = runner.with_key_events(events);
+    }
+
+    let mut wav = wav_path.as_ref().map(|_| WavRenderer::new(audio, WAV_SAMPLE_RATE));
+    let mut result = Ok(());
+    for _ in 0..frames {
+        if runner.exited() {
+            break;
+        }
+        result = runner.run_frame();
+        if result.is_err() {
+            break;
+        }
+        if let Some(wav) = wav.as_mut() {
+            wav.frame(runner.chip8.sound_timer() > 0);
+        }
+    }
+
+    let format = format
+        .or_else(|| output.as_ref().and_then(|path| path.extension()).and_then(|extension| parse_format(&extension.to_string_lossy())))
+        .unwrap_or(Format::Ascii);
+
+    let image = match format {
+        Format::Ascii => to_ascii(&runner.chip8.display).into_bytes(),
+        Format::Pbm => to_pbm(&runner.chip8.display),
+        Format::Png => to_png(&runner.chip8.display, PNG_SCALE),
+    };
+
+    match output {
+        Some(path) => fs::write(&path, image).unwrap_or_else(|error| fail(&path, error)),
+        None => print!("{}", String::from_utf8_lossy(&image)),
+    }
+
+    if let (Some(path), Some(wav)) = (wav_path, wav) {
+        fs::write(&path, wav.to_wav()).unwrap_or_else(|error| fail(&path, error));
+    }
+
+    if let Err(error) = result {
+        eprintln!("{} halted after {} frames: {}", name, runner.frame(), error);
+        process::exit(2);
+    }
+}
+
+fn parse_format(name: &str) -> Option<Format> {
+    match name {
+        "ascii" | "txt" => Some(Format::Ascii),
+        "pbm" => Some(Format::Pbm),
+        "png" => Some(Format::Png),
+        _ => None,
+    }
+}
+
+fn fail(path: &Path, error: std::io::Error) -> ! {
+    eprintln!("{}: {}", path.display(), error);
+    process::exit(1);
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: chip8-headless <rom.ch8> [options]");
+    eprintln!("  --profile <name>    Quirk profile: {}, default from the ROM database", PROFILE_NAMES.join(", "));
+    eprintln!("  --ipf <n>           Instructions per frame, default {}", DEFAULT_INSTRUCTIONS_PER_FRAME);
+    eprintln!("  --frames <n>        Frames to run at 60 per second, default {}", DEFAULT_FRAMES);
+    eprintln!("  --seed <n>          Seed for the random number generator, default {}", DEFAULT_SEED);
+    eprintln!("  --keys <file>       Key script, one '<frame> <key> down|up' per line");
+    eprintln!("  --output <file>     Write the display here instead of to stdout");
+    eprintln!("  --format <format>   ascii, pbm or png, defaults to the output extension");
+    eprintln!("  --wav <file>        Render the beeper into a WAV file");
+    eprintln!("  --waveform <name>   square, triangle, sawtooth or sine, default square");
+    eprintln!("  --frequency <hz>    Beeper frequency, default 440");
+    eprintln!("  --volume <level>    Beeper volume from 0 to 1, default 0.25");
+    process::exit(1);
+}
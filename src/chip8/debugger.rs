@@ -0,0 +1,335 @@
+//! Breakpoints, watchpoints and stepping around [`Chip8::cycle`].
+//!
+//! The debugger checks every instruction before it runs: a breakpoint or watchpoint that matches
+//! pauses the machine with the program counter still on that instruction, so the state can be
+//! inspected before anything happens.
+
+use std::fmt;
+
+use crate::chip8::{Chip8, Chip8Error, CycleOutcome, Opcode};
+use crate::disassembler::{decode_at, Syntax};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// Compares a V register against a value
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RegisterCondition {
+    pub register: u8,
+    pub comparison: Comparison,
+    pub value: u8,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub address: u16,
+    pub condition: Option<RegisterCondition>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+/// Watches the memory from `start` up to and including `end`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub start: u16,
+    pub end: u16,
+    pub kind: WatchKind,
+}
+
+/// Memory the next instruction is going to touch, from `start` up to but not including `end`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub start: usize,
+    pub end: usize,
+    pub kind: AccessKind,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DebugEvent {
+    Breakpoint { address: u16 },
+    Watchpoint { address: u16, watchpoint: Watchpoint, access: MemoryAccess },
+    StepComplete { address: u16 },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DebugOutcome {
+    Cycle(CycleOutcome),
+    Paused,
+    Stopped(DebugEvent),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Step {
+    Into,
+    Over { depth: usize },
+    Out { depth: usize },
+}
+
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    paused: bool,
+    step: Option<Step>,
+    // Breakpoints on the instruction the machine resumes from are ignored once, or it could
+    // never move past them
+    resume_address: Option<u16>,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Debugger::new()
+    }
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Debugger {
+            breakpoints: vec![],
+            watchpoints: vec![],
+            paused: false,
+            step: None,
+            resume_address: None,
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.remove_breakpoint(breakpoint.address);
+        self.breakpoints.push(breakpoint);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) {
+        self.breakpoints.retain(|breakpoint| breakpoint.address != address);
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+    }
+
+    pub fn remove_watchpoint(&mut self, start: u16, end: u16) {
+        self.watchpoints.retain(|watchpoint| watchpoint.start != start || watchpoint.end != end);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.step = None;
+    }
+
+    pub fn resume(&mut self, chip8: &Chip8) {
+        self.run(chip8, None);
+    }
+
+    /// Runs a single instruction
+    pub fn step_into(&mut self, chip8: &Chip8) {
+        self.run(chip8, Some(Step::Into));
+    }
+
+    /// Runs a single instruction, a `2nnn` call runs until the subroutine has returned
+    pub fn step_over(&mut self, chip8: &Chip8) {
+        self.run(chip8, Some(Step::Over { depth: chip8.stack().len() }));
+    }
+
+    /// Runs until the current subroutine returns with `00EE`
+    pub fn step_out(&mut self, chip8: &Chip8) {
+        let depth = chip8.stack().len();
+
+        if depth == 0 {
+            log::warn!("Not inside a subroutine");
+            return;
+        }
+
+        self.run(chip8, Some(Step::Out { depth }));
+    }
+
+    fn run(&mut self, chip8: &Chip8, step: Option<Step>) {
+        self.paused = false;
+        self.step = step;
+        self.resume_address = Some(chip8.program_counter());
+    }
+
+    /// Runs one cycle of the machine, unless it is paused or a breakpoint or watchpoint stops it
+    pub fn cycle(&mut self, chip8: &mut Chip8) -> Result<DebugOutcome, Chip8Error> {
+        if self.paused {
+            return Ok(DebugOutcome::Paused);
+        }
+
+        let address = chip8.program_counter();
+
+        if self.resume_address != Some(address) {
+            if let Some(event) = self.check(chip8) {
+                self.pause();
+                return Ok(DebugOutcome::Stopped(event));
+            }
+        }
+
+        let outcome = chip8.cycle()?;
+
+        if outcome != CycleOutcome::Executed {
+            return Ok(DebugOutcome::Cycle(outcome));
+        }
+
+        self.resume_address = None;
+
+        let depth = chip8.stack().len();
+        let complete = match self.step {
+            None => false,
+            Some(Step::Into) => true,
+            Some(Step::Over { depth: start_depth }) => depth <= start_depth,
+            Some(Step::Out { depth: start_depth }) => depth < start_depth,
+        };
+
+        if complete {
+            self.pause();
+            return Ok(DebugOutcome::Stopped(DebugEvent::StepComplete { address: chip8.program_counter() }));
+        }
+
+        Ok(DebugOutcome::Cycle(outcome))
+    }
+
+    fn check(&self, chip8: &Chip8) -> Option<DebugEvent> {
+        let address = chip8.program_counter();
+
+        let breakpoint = self.breakpoints.iter().find(|breakpoint| {
+            breakpoint.address == address && match breakpoint.condition {
+                Some(condition) => condition.matches(chip8),
+                None => true,
+            }
+        });
+
+        if breakpoint.is_some() {
+            return Some(DebugEvent::Breakpoint { address });
+        }
+
+        let access = chip8.memory_access()?;
+
+        self.watchpoints.iter()
+            .find(|watchpoint| watchpoint.matches(&access))
+            .map(|&watchpoint| DebugEvent::Watchpoint { address, watchpoint, access })
+    }
+}
+
+impl Comparison {
+    /// Parses `==`, `!=`, `<`, `<=`, `>` or `>=`
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "==" => Some(Comparison::Equal),
+            "!=" => Some(Comparison::NotEqual),
+            "<" => Some(Comparison::Less),
+            "<=" => Some(Comparison::LessOrEqual),
+            ">" => Some(Comparison::Greater),
+            ">=" => Some(Comparison::GreaterOrEqual),
+            _ => None,
+        }
+    }
+}
+
+impl WatchKind {
+    /// Parses `r`, `w` or `rw`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "r" => Some(WatchKind::Read),
+            "w" => Some(WatchKind::Write),
+            "rw" => Some(WatchKind::ReadWrite),
+            _ => None,
+        }
+    }
+}
+
+impl RegisterCondition {
+    pub fn matches(&self, chip8: &Chip8) -> bool {
+        let register = chip8.registers()[self.register as usize & 0xF];
+
+        match self.comparison {
+            Comparison::Equal => register == self.value,
+            Comparison::NotEqual => register != self.value,
+            Comparison::Less => register < self.value,
+            Comparison::LessOrEqual => register <= self.value,
+            Comparison::Greater => register > self.value,
+            Comparison::GreaterOrEqual => register >= self.value,
+        }
+    }
+}
+
+impl Watchpoint {
+    pub fn matches(&self, access: &MemoryAccess) -> bool {
+        let kind = matches!(
+            (self.kind, access.kind),
+            (WatchKind::ReadWrite, _) | (WatchKind::Read, AccessKind::Read) | (WatchKind::Write, AccessKind::Write)
+        );
+
+        kind && access.start <= self.end as usize && access.end > self.start as usize
+    }
+}
+
+impl fmt::Display for DebugEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DebugEvent::Breakpoint { address } =>
+                write!(f, "Breakpoint at {:03X}", address),
+            DebugEvent::Watchpoint { address, access, .. } =>
+                write!(f, "Watchpoint at {:03X}: {:?} {:04X}-{:04X}", address, access.kind, access.start, access.end - 1),
+            DebugEvent::StepComplete { address } =>
+                write!(f, "Stepped to {:03X}", address),
+        }
+    }
+}
+
+impl Chip8 {
+    /// Memory that the instruction at the program counter reads or writes when it runs
+    pub fn memory_access(&self) -> Option<MemoryAccess> {
+        let opcode = Opcode::from_instruction(self.fetch_instruction().ok()?);
+        let start = self.index_register as usize;
+        let planes = self.planes.count_ones() as usize;
+
+        let (length, kind) = match opcode {
+            Opcode { opcode: 0xD, n: 0, .. } => (32 * planes, AccessKind::Read),
+            Opcode { opcode: 0xD, n, .. } => (n as usize * planes, AccessKind::Read),
+            Opcode { opcode: 0x5, n: 0x2, x, y, .. } => (x.abs_diff(y) as usize + 1, AccessKind::Write),
+            Opcode { opcode: 0x5, n: 0x3, x, y, .. } => (x.abs_diff(y) as usize + 1, AccessKind::Read),
+            Opcode { instruction: 0xF002, .. } => (16, AccessKind::Read),
+            Opcode { opcode: 0xF, nn: 0x33, .. } => (3, AccessKind::Write),
+            Opcode { opcode: 0xF, nn: 0x55, x, .. } => (x as usize + 1, AccessKind::Write),
+            Opcode { opcode: 0xF, nn: 0x65, x, .. } => (x as usize + 1, AccessKind::Read),
+            _ => return None,
+        };
+
+        Some(MemoryAccess { start, end: start + length, kind })
+    }
+
+    /// Registers, timers and the next instruction, formatted for the log
+    pub fn debug_summary(&self) -> String {
+        let registers: Vec<String> = self.registers.iter().enumerate()
+            .map(|(index, value)| format!("V{:X}={:02X}", index, value))
+            .collect();
+        let stack: Vec<String> = self.stack.iter().map(|address| format!("{:03X}", address)).collect();
+        let instruction = self.fetch_instruction().map_or("----".to_string(), |instruction| format!("{:04X}", instruction));
+        let source = decode_at(&self.memory, self.program_counter as usize, Syntax::Octo).unwrap_or_default();
+
+        format!(
+            "PC={:03X} [{} {}] I={:03X} DT={:02X} ST={:02X} {} stack=[{}]",
+            self.program_counter, instruction, source, self.index_register, self.delay_timer, self.sound_timer,
+            registers.join(" "), stack.join(" ")
+        )
+    }
+}
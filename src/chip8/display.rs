@@ -0,0 +1,113 @@
+pub const LORES_WIDTH: usize = 64;
+pub const LORES_HEIGHT: usize = 32;
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+pub const ALL_PLANES: u8 = 0b11;
+
+/// Framebuffer with one byte per pixel, every bit is one XO-CHIP drawing plane
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Display {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Display {
+    pub fn new(width: usize, height: usize) -> Self {
+        Display {
+            width,
+            height,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    /// Display from raw plane bits, None when the pixel count doesn't match the size
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<u8>) -> Option<Self> {
+        if pixels.len() != width * height {
+            return None;
+        }
+
+        Some(Display { width, height, pixels })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Plane bits of the pixel, 0 when the pixel is off on every plane
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Flips the pixel on the given planes when `value` is set, returns true if a lit pixel was turned off
+    pub fn toggle(&mut self, x: usize, y: usize, planes: u8, value: bool) -> bool {
+        if !value {
+            return false;
+        }
+
+        let pixel = &mut self.pixels[y * self.width + x];
+        let collision = *pixel & planes != 0;
+        *pixel ^= planes;
+        collision
+    }
+
+    pub fn clear(&mut self, planes: u8) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel &= !planes;
+        }
+    }
+
+    /// Changes the resolution, the contents of all planes are cleared
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.pixels = vec![0; width * height];
+    }
+
+    pub fn scroll_down(&mut self, planes: u8, rows: usize) {
+        self.scroll(planes, 0, rows as isize);
+    }
+
+    pub fn scroll_up(&mut self, planes: u8, rows: usize) {
+        self.scroll(planes, 0, -(rows as isize));
+    }
+
+    pub fn scroll_right(&mut self, planes: u8, columns: usize) {
+        self.scroll(planes, columns as isize, 0);
+    }
+
+    pub fn scroll_left(&mut self, planes: u8, columns: usize) {
+        self.scroll(planes, -(columns as isize), 0);
+    }
+
+    fn scroll(&mut self, planes: u8, dx: isize, dy: isize) {
+        let mut pixels = vec![0; self.pixels.len()];
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = y * self.width + x;
+                let source_x = x as isize - dx;
+                let source_y = y as isize - dy;
+
+                let moved = if source_x >= 0 && source_y >= 0
+                    && (source_x as usize) < self.width && (source_y as usize) < self.height {
+                    self.pixels[source_y as usize * self.width + source_x as usize]
+                } else {
+                    0
+                };
+
+                pixels[index] = (moved & planes) | (self.pixels[index] & !planes);
+            }
+        }
+
+        self.pixels = pixels;
+    }
+}
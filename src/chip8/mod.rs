@@ -0,0 +1,803 @@
+use std::fmt;
+
+use crate::chip8::display::{ALL_PLANES, Display, HIRES_HEIGHT, HIRES_WIDTH, LORES_HEIGHT, LORES_WIDTH};
+use crate::chip8::quirks::{DisplayWait, MemoryIncrement, QuirkConfig};
+use crate::chip8::rng::{Rng, XorShiftRng};
+use crate::romdb;
+use crate::romdb::RomInfo;
+
+pub mod debugger;
+pub mod display;
+pub mod quirks;
+pub mod rng;
+pub mod state;
+pub mod timing;
+
+const FONT_ADDRESS: u16 = 0x050;
+const BIG_FONT_ADDRESS: u16 = 0x0A0;
+const MEMORY_SIZE: usize = 0x1000;
+const XO_MEMORY_SIZE: usize = 0x10000;
+/// Deepest stack a save state can hold
+pub const MAX_STACK_DEPTH: usize = u16::MAX as usize;
+
+pub struct Chip8 {
+    memory: Vec<u8>,
+    registers: [u8; 16],
+    index_register: u16,
+    program_counter: u16,
+    stack: Vec<u16>,
+    pub display: Display,
+    input: [bool; 16],
+    delay_timer: u8,
+    sound_timer: u8,
+    quirk_config: QuirkConfig,
+    total_cycles: u32,
+    blocking_on_draw: bool,
+    blocking_input: Option<u8>,
+    rpl_flags: [u8; 16],
+    exited: bool,
+    planes: u8,
+    audio_pattern: [u8; 16],
+    pitch: u8,
+    rng: Box<dyn Rng>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CycleOutcome {
+    Executed,
+    BlockedOnDraw,
+    Exited,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Chip8Error {
+    UnknownInstruction { address: u16, instruction: u16 },
+    StackUnderflow { address: u16 },
+    StackOverflow { address: u16 },
+    MemoryOutOfBounds { address: u16, memory_address: usize },
+    ProgramCounterOutOfBounds { address: u16 },
+}
+
+pub struct Opcode {
+    pub instruction: u16,
+    pub opcode: u8,
+    pub x: u8,
+    pub y: u8,
+    pub n: u8,
+    pub nn: u8,
+    pub nnn: u16,
+}
+
+pub struct Chip8Rom {
+    pub name: String,
+    pub data: Vec<u8>,
+    /// Quirks picked for this ROM, when not set they come from the ROM database
+    pub quirks: Option<QuirkConfig>,
+}
+
+impl Chip8Rom {
+    pub fn new(name: &str, data: Vec<u8>) -> Self {
+        Chip8Rom {
+            name: name.to_string(),
+            data,
+            quirks: None,
+        }
+    }
+
+    pub fn new_quirks(name: &str, data: Vec<u8>, quirks: QuirkConfig) -> Self {
+        Chip8Rom {
+            name: name.to_string(),
+            data,
+            quirks: Some(quirks),
+        }
+    }
+
+    /// What the ROM database knows about this ROM
+    pub fn info(&self) -> Option<&'static RomInfo> {
+        romdb::lookup(&self.data)
+    }
+
+    /// The quirks the machine runs with: the picked ones, the database's or the defaults
+    pub fn effective_quirks(&self) -> QuirkConfig {
+        self.quirks
+            .or_else(|| self.info().and_then(|info| info.quirks))
+            .unwrap_or_default()
+    }
+
+    pub fn to_device(&self) -> Chip8 {
+        let mut chip8 = Chip8::with_quirks(self.effective_quirks());
+        chip8.set_rom(&self.data);
+        return chip8;
+    }
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Chip8::new()
+    }
+}
+
+impl Chip8 {
+    pub fn new() -> Self {
+        Chip8::with_quirks(QuirkConfig::new())
+    }
+
+    pub fn with_quirks(quirk_config: QuirkConfig) -> Self {
+        let memory_size = if quirk_config.extended_memory { XO_MEMORY_SIZE } else { MEMORY_SIZE };
+
+        Chip8 {
+            memory: vec![0; memory_size],
+            registers: [0; 16],
+            index_register: 0,
+            program_counter: 0x200,
+            stack: vec!(),
+            display: Display::new(LORES_WIDTH, LORES_HEIGHT),
+            input: [false; 16],
+            quirk_config,
+            total_cycles: 0,
+            delay_timer: 0,
+            sound_timer: 0,
+            blocking_on_draw: false,
+            blocking_input: None,
+            rpl_flags: [0; 16],
+            exited: false,
+            planes: 1,
+            audio_pattern: [0; 16],
+            pitch: 64,
+            rng: Box::new(XorShiftRng::from_entropy()),
+        }
+    }
+
+    pub fn cycle(&mut self) -> Result<CycleOutcome, Chip8Error> {
+        if self.exited {
+            return Ok(CycleOutcome::Exited);
+        }
+
+        if self.blocking_on_draw {
+            return Ok(CycleOutcome::BlockedOnDraw);
+        }
+
+        let address = self.program_counter;
+        let instruction = self.fetch_instruction()?;
+        let opcode = Opcode::from_instruction(instruction);
+
+        self.program_counter = self.program_counter.wrapping_add(2);
+
+        if let Err(error) = self.execute(opcode) {
+            self.program_counter = address;
+            return Err(error);
+        }
+
+        // Only instructions that ran are counted, movies and the debugger go by this count
+        self.total_cycles += 1;
+
+        if self.exited {
+            return Ok(CycleOutcome::Exited);
+        }
+
+        Ok(CycleOutcome::Executed)
+    }
+
+    fn execute(&mut self, opcode: Opcode) -> Result<(), Chip8Error> {
+        match opcode {
+            Opcode { opcode: 0x1, nnn, .. } => self.set_program_counter(nnn),
+            Opcode { opcode: 0x2, nnn, .. } => self.jump_sub(nnn)?,
+            Opcode { opcode: 0x3, x, nn, .. } => self.value_conditional_skip(x, nn, false),
+            Opcode { opcode: 0x4, x, nn, .. } => self.value_conditional_skip(x, nn, true),
+            Opcode { opcode: 0x5, n: 0x0, x, y, .. } => self.register_conditional_skip(x, y, false),
+            Opcode { opcode: 0x5, n: 0x2, x, y, .. } => self.register_range_to_memory(x, y)?,
+            Opcode { opcode: 0x5, n: 0x3, x, y, .. } => self.memory_to_register_range(x, y)?,
+            Opcode { opcode: 0x6, x, nn, .. } => self.set_v_register(x, nn),
+            Opcode { opcode: 0x7, x, nn, .. } => self.add_v_register(x, nn),
+            Opcode { opcode: 0x8, n: 0x0, x, y, .. } => self.register_copy(x, y),
+            Opcode { opcode: 0x8, n: 0x1, x, y, .. } => self.register_or(x, y),
+            Opcode { opcode: 0x8, n: 0x2, x, y, .. } => self.register_and(x, y),
+            Opcode { opcode: 0x8, n: 0x3, x, y, .. } => self.register_xor(x, y),
+            Opcode { opcode: 0x8, n: 0x4, x, y, .. } => self.register_add(x, y),
+            Opcode { opcode: 0x8, n: 0x5, x, y, .. } => self.register_sub(x, y, false),
+            Opcode { opcode: 0x8, n: 0x6, x, y, .. } => self.register_shift(x, y, false),
+            Opcode { opcode: 0x8, n: 0x7, x, y, .. } => self.register_sub(x, y, true),
+            Opcode { opcode: 0x8, n: 0xE, x, y, .. } => self.register_shift(x, y, true),
+            Opcode { opcode: 0x9, x, y, .. } => self.register_conditional_skip(x, y, true),
+            Opcode { opcode: 0xA, nnn, .. } => self.set_index_register(nnn),
+            Opcode { opcode: 0xB, nnn, .. } => self.jump_offset(nnn),
+            Opcode { opcode: 0xC, x, nn, .. } => self.set_register_random(x, nn),
+            Opcode { opcode: 0xD, x, y, n, .. } => self.draw_sprite(x, y, n)?,
+            Opcode { opcode: 0xE, nn: 0x9E, x, .. } => self.input_conditional_skip(x, false),
+            Opcode { opcode: 0xE, nn: 0xA1, x, .. } => self.input_conditional_skip(x, true),
+            Opcode { instruction: 0xF000, .. } => self.set_index_register_long()?,
+            Opcode { opcode: 0xF, nn: 0x01, x, .. } => self.planes = x & ALL_PLANES,
+            Opcode { instruction: 0xF002, .. } => self.load_audio_pattern()?,
+            Opcode { opcode: 0xF, nn: 0x07, x, .. } => self.get_delay_timer(x),
+            Opcode { opcode: 0xF, nn: 0x0A, x, .. } => self.wait_for_input(x),
+            Opcode { opcode: 0xF, nn: 0x15, x, .. } => self.set_delay_timer(x),
+            Opcode { opcode: 0xF, nn: 0x18, x, .. } => self.set_sound_timer(x),
+            Opcode { opcode: 0xF, nn: 0x1E, x, .. } => self.add_index_register(x),
+            Opcode { opcode: 0xF, nn: 0x29, x, .. } => self.index_to_font_char(x),
+            Opcode { opcode: 0xF, nn: 0x30, x, .. } => self.index_to_big_font_char(x),
+            Opcode { opcode: 0xF, nn: 0x33, x, .. } => self.convert_to_bcd(x)?,
+            Opcode { opcode: 0xF, nn: 0x3A, x, .. } => self.pitch = self.registers[x as usize],
+            Opcode { opcode: 0xF, nn: 0x55, x, .. } => self.register_to_memory(x)?,
+            Opcode { opcode: 0xF, nn: 0x65, x, .. } => self.memory_to_register(x)?,
+            Opcode { opcode: 0xF, nn: 0x75, x, .. } => self.register_to_flags(x),
+            Opcode { opcode: 0xF, nn: 0x85, x, .. } => self.flags_to_register(x),
+            Opcode { opcode: 0x0, x: 0x0, y: 0xC, n, .. } => self.display.scroll_down(self.planes, n as usize),
+            Opcode { opcode: 0x0, x: 0x0, y: 0xD, n, .. } => self.display.scroll_up(self.planes, n as usize),
+            Opcode { instruction: 0x00E0, .. } => self.clear_screen(),
+            Opcode { instruction: 0x00EE, .. } => self.return_sub()?,
+            Opcode { instruction: 0x00FB, .. } => self.display.scroll_right(self.planes, 4),
+            Opcode { instruction: 0x00FC, .. } => self.display.scroll_left(self.planes, 4),
+            Opcode { instruction: 0x00FD, .. } => self.exited = true,
+            Opcode { instruction: 0x00FE, .. } => self.display.resize(LORES_WIDTH, LORES_HEIGHT),
+            Opcode { instruction: 0x00FF, .. } => self.display.resize(HIRES_WIDTH, HIRES_HEIGHT),
+            Opcode { instruction, .. } => {
+                return Err(Chip8Error::UnknownInstruction { address: self.instruction_address(), instruction });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn update(&mut self) {
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
+
+        self.blocking_on_draw = false;
+    }
+
+    fn set_delay_timer(&mut self, source_register: u8) {
+        self.delay_timer = self.registers[source_register as usize];
+    }
+
+    fn set_sound_timer(&mut self, source_register: u8) {
+        self.sound_timer = self.registers[source_register as usize];
+    }
+
+    fn get_delay_timer(&mut self, target_register: u8) {
+        self.registers[target_register as usize] = self.delay_timer;
+    }
+
+    fn add_index_register(&mut self, target_register: u8) {
+        self.index_register = self.index_register.wrapping_add(self.registers[target_register as usize].into());
+    }
+
+    fn wait_for_input(&mut self, target_register: u8) {
+        match self.blocking_input {
+            None => {
+                let first_input = self.input.iter().enumerate().find(|&(_, &value)| value);
+
+                match first_input {
+                    Some((index, _)) => self.blocking_input = Some(index as u8),
+                    None => self.program_counter = self.program_counter.wrapping_sub(2),
+                }
+            }
+            Some(input) => {
+                if self.input[input as usize] == false {
+                    self.registers[target_register as usize] = input;
+                    self.blocking_input = None;
+                } else {
+                    self.program_counter = self.program_counter.wrapping_sub(2);
+                }
+            }
+        }
+    }
+
+    fn set_register_random(&mut self, target_register: u8, mod_and: u8) {
+        self.registers[target_register as usize] = self.rng.next_byte() & mod_and;
+    }
+
+    fn input_conditional_skip(&mut self, source_register: u8, inverse: bool) {
+        let input = self.registers[source_register as usize] & 0xF;
+        if self.input[input as usize] ^ inverse {
+            self.skip_instruction();
+        }
+    }
+
+    fn register_to_memory(&mut self, target_register: u8) -> Result<(), Chip8Error> {
+        self.check_memory_range(self.index_register as usize, target_register as usize + 1)?;
+
+        for i in 0..=target_register {
+            self.memory[(self.index_register + i as u16) as usize] = self.registers[i as usize];
+        }
+        self.increment_index_register(target_register);
+        Ok(())
+    }
+
+    fn memory_to_register(&mut self, target_register: u8) -> Result<(), Chip8Error> {
+        self.check_memory_range(self.index_register as usize, target_register as usize + 1)?;
+
+        for i in 0..=target_register {
+            self.registers[i as usize] = self.memory[(self.index_register + i as u16) as usize];
+        }
+
+        self.increment_index_register(target_register);
+        Ok(())
+    }
+
+    fn increment_index_register(&mut self, target_register: u8) {
+        match self.quirk_config.memory_increment {
+            MemoryIncrement::None => {}
+            MemoryIncrement::X => self.index_register = self.index_register.wrapping_add(target_register as u16),
+            MemoryIncrement::XPlusOne => self.index_register = self.index_register.wrapping_add(target_register as u16 + 1),
+        }
+    }
+
+    pub fn set_input(&mut self, input: u8, pressed: bool) {
+        self.input[input as usize] = pressed;
+    }
+
+    pub fn is_pressed(&self, input: u8) -> bool {
+        self.input[input as usize]
+    }
+
+    /// Seed the random number generator was last started from
+    pub fn seed(&self) -> u64 {
+        self.rng.seed()
+    }
+
+    /// Restarts the random number generator, the same seed and input always give the same run
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng.reseed(seed);
+    }
+
+    /// Replaces the random number generator used by `Cxnn`
+    pub fn set_rng(&mut self, rng: Box<dyn Rng>) {
+        self.rng = rng;
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    pub fn index_register(&self) -> u16 {
+        self.index_register
+    }
+
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.registers
+    }
+
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    pub fn quirks(&self) -> QuirkConfig {
+        self.quirk_config
+    }
+
+    /// The beeper sounds while this is above zero
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    /// Instructions executed since the machine started
+    pub fn total_cycles(&self) -> u32 {
+        self.total_cycles
+    }
+
+    fn convert_to_bcd(&mut self, target_register: u8) -> Result<(), Chip8Error> {
+        self.check_memory_range(self.index_register as usize, 3)?;
+
+        let value = self.registers[target_register as usize];
+
+        let hundreds = value / 100;
+        let tens = (value / 10) % 10;
+        let ones = value % 10;
+
+        self.memory[self.index_register as usize] = hundreds;
+        self.memory[self.index_register as usize + 1] = tens;
+        self.memory[self.index_register as usize + 2] = ones;
+        Ok(())
+    }
+
+    fn register_copy(&mut self, target_register: u8, source_register: u8) {
+        self.registers[target_register as usize] = self.registers[source_register as usize];
+    }
+
+    fn register_or(&mut self, target_register: u8, source_register: u8) {
+        let value = self.registers[source_register as usize];
+        if self.quirk_config.vf_reset {
+            self.registers[0xF] = 0;
+        }
+        self.registers[target_register as usize] = self.registers[target_register as usize] | value;
+    }
+
+    fn register_xor(&mut self, target_register: u8, source_register: u8) {
+        let value = self.registers[source_register as usize];
+        if self.quirk_config.vf_reset {
+            self.registers[0xF] = 0;
+        }
+        self.registers[target_register as usize] = self.registers[target_register as usize] ^ value;
+    }
+
+    fn register_and(&mut self, target_register: u8, source_register: u8) {
+        let value = self.registers[source_register as usize];
+        if self.quirk_config.vf_reset {
+            self.registers[0xF] = 0;
+        }
+        self.registers[target_register as usize] = self.registers[target_register as usize] & value;
+    }
+
+    fn register_shift(&mut self, target_register: u8, source_register: u8, inverse: bool) {
+        let bit_out: u8;
+
+        if self.quirk_config.source_vy_bitshift {
+            self.registers[target_register as usize] = self.registers[source_register as usize];
+        }
+
+        if inverse {
+            bit_out = (self.registers[target_register as usize] >> 7) & 1;
+            self.registers[target_register as usize] <<= 1;
+        } else {
+            bit_out = self.registers[target_register as usize] & 1;
+            self.registers[target_register as usize] >>= 1;
+        }
+        self.registers[0xF] = bit_out;
+    }
+
+    fn register_add(&mut self, target_register: u8, source_register: u8) {
+        let x = self.registers[target_register as usize];
+        let y = self.registers[source_register as usize];
+
+        self.registers[target_register as usize] = x.wrapping_add(y);
+
+        if y > (255 - x) {
+            self.registers[0xF] = 1;
+        } else {
+            self.registers[0xF] = 0;
+        }
+    }
+
+    fn register_sub(&mut self, target_register: u8, source_register: u8, swap: bool) {
+        let mut x = self.registers[target_register as usize];
+        let mut y = self.registers[source_register as usize];
+
+        if swap {
+            std::mem::swap(&mut x, &mut y);
+        }
+
+        self.registers[target_register as usize] = x.wrapping_sub(y);
+
+        if x >= y {
+            self.registers[0xF] = 1;
+        } else {
+            self.registers[0xF] = 0;
+        }
+    }
+
+
+    fn return_sub(&mut self) -> Result<(), Chip8Error> {
+        match self.stack.pop() {
+            Some(position) => {
+                self.set_program_counter(position);
+                Ok(())
+            }
+            None => Err(Chip8Error::StackUnderflow { address: self.instruction_address() }),
+        }
+    }
+
+    fn jump_sub(&mut self, position: u16) -> Result<(), Chip8Error> {
+        if self.stack.len() >= MAX_STACK_DEPTH {
+            return Err(Chip8Error::StackOverflow { address: self.instruction_address() });
+        }
+
+        self.stack.push(self.program_counter);
+        self.set_program_counter(position);
+        Ok(())
+    }
+
+    fn jump_offset(&mut self, position: u16) {
+        let register = if self.quirk_config.jump_vx_offset { (position >> 8) & 0xF } else { 0 };
+        self.set_program_counter(position + self.registers[register as usize] as u16);
+    }
+
+    fn register_conditional_skip(&mut self, register_a: u8, register_b: u8, inverse: bool) {
+        let vx = self.registers[register_a as usize];
+        let vy = self.registers[register_b as usize];
+
+        if (vx == vy) ^ inverse {
+            self.skip_instruction();
+        }
+    }
+
+    fn value_conditional_skip(&mut self, register: u8, value: u8, inverse: bool) {
+        let vx = self.registers[register as usize];
+
+        if (vx == value) ^ inverse {
+            self.skip_instruction();
+        }
+    }
+
+    fn draw_sprite(&mut self, x: u8, y: u8, height: u8) -> Result<(), Chip8Error> {
+        // Dxy0 draws a 16x16 sprite, stored as two bytes per row
+        let (width, height) = match height {
+            0 => (16, 16),
+            n => (8, n as usize),
+        };
+        let row_bytes = width / 8;
+        let sprite_size = height * row_bytes;
+
+        // Every selected plane gets its own copy of the sprite data, stored one after the other
+        let planes: Vec<u8> = (0..2).map(|plane| 1 << plane).filter(|plane| self.planes & plane != 0).collect();
+        self.check_memory_range(self.index_register as usize, sprite_size * planes.len())?;
+
+        let display_width = self.display.width();
+        let display_height = self.display.height();
+        let x = self.registers[x as usize] as usize % display_width;
+        let y = self.registers[y as usize] as usize % display_height;
+        self.registers[0xF] = 0;
+
+        for (plane_index, &plane) in planes.iter().enumerate() {
+            let sprite_address = self.index_register as usize + plane_index * sprite_size;
+
+            for row in 0..height {
+                let mut py = y + row;
+
+                if py >= display_height {
+                    if self.quirk_config.sprite_clipping {
+                        break;
+                    }
+                    py %= display_height;
+                }
+
+                let address = sprite_address + row * row_bytes;
+                let sprite = self.memory[address..address + row_bytes]
+                    .iter()
+                    .fold(0u16, |bits, &byte| (bits << 8) | byte as u16);
+
+                for column in 0..width {
+                    let mut px = x + column;
+
+                    if px >= display_width {
+                        if self.quirk_config.sprite_clipping {
+                            break;
+                        }
+                        px %= display_width;
+                    }
+
+                    let bit = (sprite >> (width - 1 - column)) & 1 != 0;
+
+                    if self.display.toggle(px, py, plane, bit) {
+                        self.registers[0xF] = 1;
+                    }
+                }
+            }
+        }
+        self.blocking_on_draw = match self.quirk_config.display_wait {
+            DisplayWait::Never => false,
+            DisplayWait::Always => true,
+            DisplayWait::LoresOnly => display_width == LORES_WIDTH,
+        };
+        Ok(())
+    }
+
+    // The XO-CHIP long index load is four bytes long, skipping it has to skip all of them
+    fn skip_instruction(&mut self) {
+        let pc = self.program_counter as usize;
+        let long = pc + 1 < self.memory.len() && self.memory[pc] == 0xF0 && self.memory[pc + 1] == 0x00;
+
+        self.program_counter = self.program_counter.wrapping_add(if long { 4 } else { 2 });
+    }
+
+    fn set_program_counter(&mut self, value: u16) {
+        self.program_counter = value;
+    }
+
+    fn set_v_register(&mut self, register: u8, value: u8) {
+        if register > 0xF {
+            println!("Invalid V register: {:01X}", register);
+            return;
+        }
+
+        self.registers[register as usize] = value;
+    }
+
+    fn add_v_register(&mut self, register: u8, value: u8) {
+        if register > 0xF {
+            println!("Invalid V register: {:01X}", register);
+            return;
+        }
+
+        self.registers[register as usize] = self.registers[register as usize].wrapping_add(value);
+    }
+
+    fn set_index_register(&mut self, value: u16) {
+        self.index_register = value;
+    }
+
+    fn set_index_register_long(&mut self) -> Result<(), Chip8Error> {
+        let address = self.instruction_address();
+        self.index_register = self.fetch_instruction().map_err(|_| Chip8Error::ProgramCounterOutOfBounds { address })?;
+        self.program_counter = self.program_counter.wrapping_add(2);
+        Ok(())
+    }
+
+    fn register_range_to_memory(&mut self, first_register: u8, last_register: u8) -> Result<(), Chip8Error> {
+        let range = register_range(first_register, last_register);
+        self.check_memory_range(self.index_register as usize, range.len())?;
+
+        for (offset, &register) in range.iter().enumerate() {
+            self.memory[self.index_register as usize + offset] = self.registers[register];
+        }
+        Ok(())
+    }
+
+    fn memory_to_register_range(&mut self, first_register: u8, last_register: u8) -> Result<(), Chip8Error> {
+        let range = register_range(first_register, last_register);
+        self.check_memory_range(self.index_register as usize, range.len())?;
+
+        for (offset, &register) in range.iter().enumerate() {
+            self.registers[register] = self.memory[self.index_register as usize + offset];
+        }
+        Ok(())
+    }
+
+    fn load_audio_pattern(&mut self) -> Result<(), Chip8Error> {
+        let start = self.index_register as usize;
+        self.check_memory_range(start, self.audio_pattern.len())?;
+
+        self.audio_pattern.copy_from_slice(&self.memory[start..start + 16]);
+        Ok(())
+    }
+
+    fn clear_screen(&mut self) {
+        self.display.clear(self.planes);
+    }
+
+    fn fetch_instruction(&self) -> Result<u16, Chip8Error> {
+        let pc = self.program_counter as usize;
+
+        if pc + 1 >= self.memory.len() {
+            return Err(Chip8Error::ProgramCounterOutOfBounds { address: self.program_counter });
+        }
+
+        let high_byte = self.memory[pc] as u16;
+        let low_byte = self.memory[pc + 1] as u16;
+        Ok((high_byte << 8) | low_byte)
+    }
+
+    fn check_memory_range(&self, start: usize, length: usize) -> Result<(), Chip8Error> {
+        if start + length > self.memory.len() {
+            return Err(Chip8Error::MemoryOutOfBounds {
+                address: self.instruction_address(),
+                memory_address: start + length - 1,
+            });
+        }
+        Ok(())
+    }
+
+    // Only valid while executing, the program counter has already moved past the current instruction
+    fn instruction_address(&self) -> u16 {
+        self.program_counter.wrapping_sub(2)
+    }
+
+    pub fn set_rom(&mut self, rom: &Vec<u8>) {
+        let end = std::cmp::min(rom.len(), self.memory.len() - 512);
+
+        for (index, &byte) in rom.iter().enumerate().take(end) {
+            self.memory[512 + index] = byte;
+        }
+
+        let font = get_font_chars();
+
+        for (index, &byte) in font.iter().enumerate() {
+            self.memory[FONT_ADDRESS as usize + index] = byte;
+        }
+
+        let big_font = get_big_font_chars();
+
+        for (index, &byte) in big_font.iter().enumerate() {
+            self.memory[BIG_FONT_ADDRESS as usize + index] = byte;
+        }
+    }
+
+    fn index_to_font_char(&mut self, target_register: u8) {
+        let char = self.registers[target_register as usize] & 0xF;
+        self.index_register = FONT_ADDRESS + (char as u16 * 5);
+    }
+
+    fn index_to_big_font_char(&mut self, target_register: u8) {
+        let char = self.registers[target_register as usize] & 0xF;
+        self.index_register = BIG_FONT_ADDRESS + (char as u16 * 10);
+    }
+
+    fn register_to_flags(&mut self, target_register: u8) {
+        for i in 0..=target_register as usize {
+            self.rpl_flags[i] = self.registers[i];
+        }
+    }
+
+    fn flags_to_register(&mut self, target_register: u8) {
+        for i in 0..=target_register as usize {
+            self.registers[i] = self.rpl_flags[i];
+        }
+    }
+}
+
+/// Registers from `first` to `last` in memory order, which may run backwards
+fn register_range(first: u8, last: u8) -> Vec<usize> {
+    if first <= last {
+        (first as usize..=last as usize).collect()
+    } else {
+        (last as usize..=first as usize).rev().collect()
+    }
+}
+
+impl Opcode {
+    pub fn from_instruction(instruction: u16) -> Self {
+        let opcode = ((instruction >> 12) & 0xF) as u8;
+        let x = ((instruction >> 8) & 0xF) as u8;
+        let y = ((instruction >> 4) & 0xF) as u8;
+        let n = (instruction & 0xF) as u8;
+        let nn = (instruction & 0xFF) as u8;
+        let nnn = instruction & 0xFFF;
+
+        Opcode { instruction, opcode, x, y, n, nn, nnn }
+    }
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::UnknownInstruction { address, instruction } =>
+                write!(f, "Instruction not supported: {:04X} at {:03X}", instruction, address),
+            Chip8Error::StackUnderflow { address } =>
+                write!(f, "Return with empty stack at {:03X}", address),
+            Chip8Error::StackOverflow { address } =>
+                write!(f, "Call with full stack at {:03X}", address),
+            Chip8Error::MemoryOutOfBounds { address, memory_address } =>
+                write!(f, "Memory access out of bounds: {:04X} at {:03X}", memory_address, address),
+            Chip8Error::ProgramCounterOutOfBounds { address } =>
+                write!(f, "Program counter out of bounds: {:04X}", address),
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {}
+
+pub fn get_font_chars() -> Vec<u8> {
+    vec![
+        0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+        0x20, 0x60, 0x20, 0x20, 0x70, // 1
+        0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+        0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+        0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+        0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+        0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+        0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+        0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+        0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+        0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+        0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+        0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+        0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+        0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+        0xF0, 0x80, 0xF0, 0x80, 0x80,  // F
+    ]
+}
+
+pub fn get_big_font_chars() -> Vec<u8> {
+    vec![
+        0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+        0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+        0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+        0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+        0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+        0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+        0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+        0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+        0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+        0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+        0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+        0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+        0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+        0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+        0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+        0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+    ]
+}
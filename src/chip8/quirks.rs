@@ -0,0 +1,131 @@
+/// How `Fx55` and `Fx65` leave the index register behind
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MemoryIncrement {
+    /// The index register is left untouched (SUPER-CHIP)
+    None,
+    /// The index register is increased by X (CHIP-48)
+    X,
+    /// The index register is increased by X + 1 (COSMAC VIP, XO-CHIP)
+    XPlusOne,
+}
+
+/// When `Dxyn` waits for the next vertical blank before the program continues
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DisplayWait {
+    /// Sprites are drawn right away (CHIP-48, modern interpreters)
+    Never,
+    /// Every sprite waits (COSMAC VIP)
+    Always,
+    /// Only sprites drawn in low resolution wait (SUPER-CHIP 1.1)
+    LoresOnly,
+}
+
+/// Behaviour that differs between the CHIP-8 platforms. Every field is a separate switch, the
+/// named profiles set them the way the original platforms did.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct QuirkConfig {
+    /// `8xy1`, `8xy2` and `8xy3` reset VF to 0
+    pub vf_reset: bool,
+    /// Index register change after `Fx55` and `Fx65`
+    pub memory_increment: MemoryIncrement,
+    /// When `Dxyn` waits for the next vertical blank
+    pub display_wait: DisplayWait,
+    /// Sprites are clipped at the edges of the screen instead of wrapping around
+    pub sprite_clipping: bool,
+    /// `8xy6` and `8xyE` shift VY into VX instead of shifting VX in place
+    pub source_vy_bitshift: bool,
+    /// `Bnnn` jumps to XNN + VX instead of NNN + V0
+    pub jump_vx_offset: bool,
+    /// 64 KiB of memory instead of 4 KiB (XO-CHIP)
+    pub extended_memory: bool,
+}
+
+/// Names accepted by [`QuirkConfig::from_profile_name`]
+pub const PROFILE_NAMES: [&str; 5] = ["cosmac-vip", "chip-48", "schip-legacy", "schip-modern", "xo-chip"];
+
+impl Default for QuirkConfig {
+    fn default() -> Self {
+        QuirkConfig::new()
+    }
+}
+
+impl QuirkConfig {
+    pub fn new() -> Self {
+        QuirkConfig::cosmac_vip()
+    }
+
+    pub fn from_profile_name(name: &str) -> Option<Self> {
+        match name {
+            "cosmac-vip" => Some(QuirkConfig::cosmac_vip()),
+            "chip-48" => Some(QuirkConfig::chip48()),
+            "schip-legacy" => Some(QuirkConfig::schip_legacy()),
+            "schip-modern" => Some(QuirkConfig::schip_modern()),
+            "xo-chip" => Some(QuirkConfig::xo_chip()),
+            _ => None,
+        }
+    }
+
+    /// The original CHIP-8 interpreter on the COSMAC VIP
+    pub fn cosmac_vip() -> Self {
+        QuirkConfig {
+            vf_reset: true,
+            memory_increment: MemoryIncrement::XPlusOne,
+            display_wait: DisplayWait::Always,
+            sprite_clipping: true,
+            source_vy_bitshift: true,
+            jump_vx_offset: false,
+            extended_memory: false,
+        }
+    }
+
+    /// CHIP-48 on the HP-48 calculators
+    pub fn chip48() -> Self {
+        QuirkConfig {
+            vf_reset: false,
+            memory_increment: MemoryIncrement::X,
+            display_wait: DisplayWait::Never,
+            sprite_clipping: true,
+            source_vy_bitshift: false,
+            jump_vx_offset: true,
+            extended_memory: false,
+        }
+    }
+
+    /// SUPER-CHIP 1.1 on the HP-48, which only waited for the display in low resolution mode
+    pub fn schip_legacy() -> Self {
+        QuirkConfig {
+            vf_reset: false,
+            memory_increment: MemoryIncrement::None,
+            display_wait: DisplayWait::LoresOnly,
+            sprite_clipping: true,
+            source_vy_bitshift: false,
+            jump_vx_offset: true,
+            extended_memory: false,
+        }
+    }
+
+    /// SUPER-CHIP as implemented by modern interpreters like Octo
+    pub fn schip_modern() -> Self {
+        QuirkConfig {
+            vf_reset: false,
+            memory_increment: MemoryIncrement::None,
+            display_wait: DisplayWait::Never,
+            sprite_clipping: true,
+            source_vy_bitshift: false,
+            jump_vx_offset: true,
+            extended_memory: false,
+        }
+    }
+
+    pub fn xo_chip() -> Self {
+        QuirkConfig {
+            vf_reset: false,
+            memory_increment: MemoryIncrement::XPlusOne,
+            display_wait: DisplayWait::Never,
+            sprite_clipping: false,
+            source_vy_bitshift: true,
+            jump_vx_offset: false,
+            extended_memory: true,
+        }
+    }
+}
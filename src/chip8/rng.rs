@@ -0,0 +1,102 @@
+//! Random numbers for `Cxnn`.
+//!
+//! Every [`Chip8`](crate::chip8::Chip8) owns a generator that starts from a seed, so a run can be
+//! repeated exactly by starting it with the same seed and input. The generator state is part of
+//! the save states.
+
+use getrandom::getrandom;
+
+/// Source of the random bytes used by `Cxnn`
+pub trait Rng {
+    fn next_byte(&mut self) -> u8;
+
+    /// Seed the generator was last started from
+    fn seed(&self) -> u64;
+
+    /// Starts the sequence over from a new seed
+    fn reseed(&mut self, seed: u64);
+
+    /// Current position in the sequence, as stored in save states
+    fn state(&self) -> Vec<u8>;
+
+    /// Continues from a position returned by [`Rng::state`]. Returns false and leaves the
+    /// generator untouched when the state isn't valid.
+    fn restore(&mut self, state: &[u8]) -> bool;
+}
+
+/// Default generator: xorshift64* seeded through SplitMix64, fast and good enough for games
+pub struct XorShiftRng {
+    seed: u64,
+    state: u64,
+}
+
+impl XorShiftRng {
+    pub fn new(seed: u64) -> Self {
+        XorShiftRng {
+            seed,
+            state: initial_state(seed),
+        }
+    }
+
+    /// Seeded from the operating system, or the browser on the web
+    pub fn from_entropy() -> Self {
+        XorShiftRng::new(random_seed())
+    }
+}
+
+impl Rng for XorShiftRng {
+    fn next_byte(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545F4914F6CDD1D) >> 56) as u8
+    }
+
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        *self = XorShiftRng::new(seed);
+    }
+
+    fn state(&self) -> Vec<u8> {
+        let mut state = self.seed.to_le_bytes().to_vec();
+        state.extend_from_slice(&self.state.to_le_bytes());
+        state
+    }
+
+    fn restore(&mut self, state: &[u8]) -> bool {
+        if state.len() != 16 {
+            return false;
+        }
+
+        let seed = u64::from_le_bytes(state[..8].try_into().unwrap());
+        let position = u64::from_le_bytes(state[8..].try_into().unwrap());
+
+        // xorshift can never reach zero
+        if position == 0 {
+            return false;
+        }
+
+        self.seed = seed;
+        self.state = position;
+        true
+    }
+}
+
+pub fn random_seed() -> u64 {
+    let mut buf = [0u8; 8];
+    getrandom(&mut buf).expect("Random number");
+    u64::from_le_bytes(buf)
+}
+
+/// SplitMix64 step, spreads similar seeds far apart and never gives xorshift a zero state
+fn initial_state(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^= z >> 31;
+
+    if z == 0 { 0x9E3779B97F4A7C15 } else { z }
+}
@@ -0,0 +1,309 @@
+//! Save states for a running [`Chip8`].
+//!
+//! A state is a single little-endian binary blob:
+//!
+//! | Offset | Size | Content                                  |
+//! |--------|------|------------------------------------------|
+//! | 0      | 4    | Magic bytes `C8ST`                       |
+//! | 4      | 2    | Format version, currently 3              |
+//! | 6      | 4    | Payload length in bytes                  |
+//! | 10     | n    | Payload                                  |
+//! | 10 + n | 4    | CRC-32 of the payload                    |
+//!
+//! The payload holds the machine in this order:
+//!
+//! - quirks: 7 bytes, `vf_reset`, `memory_increment` (0 none, 1 X, 2 X + 1), `display_wait`
+//!   (0 never, 1 always, 2 lores only), `sprite_clipping`, `source_vy_bitshift`, `jump_vx_offset` and `extended_memory`
+//! - memory: u32 length followed by the memory contents
+//! - registers V0 to VF: 16 bytes
+//! - index register and program counter: u16 each
+//! - stack: u16 depth (u8 before version 3) followed by a u16 per entry
+//! - display: u16 width, u16 height and one byte of plane bits per pixel
+//! - input: 16 bytes, one per key
+//! - delay timer and sound timer: u8 each
+//! - total cycles: u32
+//! - blocking on draw: u8, blocking input: u8 key or 0xFF when not waiting
+//! - RPL flags: 16 bytes
+//! - exited: u8
+//! - selected planes: u8
+//! - audio pattern: 16 bytes
+//! - pitch: u8
+//! - random number generator: u16 length followed by the generator state (since version 2)
+//!
+//! Booleans are stored as a single byte, 0 or 1. Older states can still be loaded, version 1
+//! states keep the random number generator as it is.
+
+use std::fmt;
+
+use crate::chip8::Chip8;
+use crate::chip8::display::{Display, HIRES_HEIGHT, HIRES_WIDTH, LORES_HEIGHT, LORES_WIDTH};
+use crate::chip8::quirks::{DisplayWait, MemoryIncrement, QuirkConfig};
+use crate::hash::crc32;
+
+const MAGIC: &[u8; 4] = b"C8ST";
+const VERSION: u16 = 3;
+const MIN_VERSION: u16 = 1;
+const HEADER_SIZE: usize = 10;
+const CHECKSUM_SIZE: usize = 4;
+const NO_INPUT: u8 = 0xFF;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StateError {
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    ChecksumMismatch,
+    Truncated,
+    InvalidValue(&'static str),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::InvalidMagic => write!(f, "Not a save state"),
+            StateError::UnsupportedVersion(version) => write!(f, "Unsupported save state version {}", version),
+            StateError::ChecksumMismatch => write!(f, "Save state checksum mismatch"),
+            StateError::Truncated => write!(f, "Save state is truncated"),
+            StateError::InvalidValue(field) => write!(f, "Invalid {} in save state", field),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+impl Chip8 {
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(self.memory.len() + self.display.pixels().len() + 128);
+
+        write_quirks(&mut payload, &self.quirk_config);
+        payload.extend_from_slice(&(self.memory.len() as u32).to_le_bytes());
+        payload.extend_from_slice(&self.memory);
+        payload.extend_from_slice(&self.registers);
+        payload.extend_from_slice(&self.index_register.to_le_bytes());
+        payload.extend_from_slice(&self.program_counter.to_le_bytes());
+        payload.extend_from_slice(&(self.stack.len() as u16).to_le_bytes());
+        for entry in &self.stack {
+            payload.extend_from_slice(&entry.to_le_bytes());
+        }
+        payload.extend_from_slice(&(self.display.width() as u16).to_le_bytes());
+        payload.extend_from_slice(&(self.display.height() as u16).to_le_bytes());
+        payload.extend_from_slice(self.display.pixels());
+        payload.extend(self.input.iter().map(|&pressed| pressed as u8));
+        payload.push(self.delay_timer);
+        payload.push(self.sound_timer);
+        payload.extend_from_slice(&self.total_cycles.to_le_bytes());
+        payload.push(self.blocking_on_draw as u8);
+        payload.push(self.blocking_input.unwrap_or(NO_INPUT));
+        payload.extend_from_slice(&self.rpl_flags);
+        payload.push(self.exited as u8);
+        payload.push(self.planes);
+        payload.extend_from_slice(&self.audio_pattern);
+        payload.push(self.pitch);
+        let rng_state = self.rng.state();
+        payload.extend_from_slice(&(rng_state.len() as u16).to_le_bytes());
+        payload.extend_from_slice(&rng_state);
+
+        let mut state = Vec::with_capacity(HEADER_SIZE + payload.len() + CHECKSUM_SIZE);
+        state.extend_from_slice(MAGIC);
+        state.extend_from_slice(&VERSION.to_le_bytes());
+        state.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        state.extend_from_slice(&payload);
+        state.extend_from_slice(&crc32(&payload).to_le_bytes());
+        state
+    }
+
+    /// Restores a state created by [`Chip8::save_state`]. The machine is left untouched when the
+    /// state can't be read.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let (version, payload) = read_payload(state)?;
+        let mut reader = Reader::new(payload);
+
+        let quirk_config = read_quirks(&mut reader)?;
+        let mut chip8 = Chip8::with_quirks(quirk_config);
+
+        let memory_size = reader.u32()? as usize;
+        if memory_size != chip8.memory.len() {
+            return Err(StateError::InvalidValue("memory size"));
+        }
+        chip8.memory.copy_from_slice(reader.bytes(memory_size)?);
+        chip8.registers.copy_from_slice(reader.bytes(16)?);
+        chip8.index_register = reader.u16()?;
+        chip8.program_counter = reader.u16()?;
+
+        let depth = match version {
+            1 | 2 => reader.u8()? as u16,
+            _ => reader.u16()?,
+        };
+        for _ in 0..depth {
+            chip8.stack.push(reader.u16()?);
+        }
+
+        let width = reader.u16()? as usize;
+        let height = reader.u16()? as usize;
+        if (width, height) != (LORES_WIDTH, LORES_HEIGHT) && (width, height) != (HIRES_WIDTH, HIRES_HEIGHT) {
+            return Err(StateError::InvalidValue("display size"));
+        }
+        chip8.display = Display::from_pixels(width, height, reader.bytes(width * height)?.to_vec())
+            .ok_or(StateError::InvalidValue("display size"))?;
+
+        for (key, &pressed) in chip8.input.iter_mut().zip(reader.bytes(16)?) {
+            *key = read_bool(pressed)?;
+        }
+        chip8.delay_timer = reader.u8()?;
+        chip8.sound_timer = reader.u8()?;
+        chip8.total_cycles = reader.u32()?;
+        chip8.blocking_on_draw = read_bool(reader.u8()?)?;
+        chip8.blocking_input = match reader.u8()? {
+            NO_INPUT => None,
+            key if key < 16 => Some(key),
+            _ => return Err(StateError::InvalidValue("blocking input")),
+        };
+        chip8.rpl_flags.copy_from_slice(reader.bytes(16)?);
+        chip8.exited = read_bool(reader.u8()?)?;
+        chip8.planes = reader.u8()?;
+        chip8.audio_pattern.copy_from_slice(reader.bytes(16)?);
+        chip8.pitch = reader.u8()?;
+
+        let rng_state = match version {
+            1 => None,
+            _ => {
+                let length = reader.u16()? as usize;
+                Some(reader.bytes(length)?)
+            }
+        };
+
+        if !reader.is_empty() {
+            return Err(StateError::InvalidValue("payload length"));
+        }
+
+        if let Some(rng_state) = rng_state {
+            if !self.rng.restore(rng_state) {
+                return Err(StateError::InvalidValue("random number generator state"));
+            }
+        }
+
+        // Keep using the same generator, only its position comes from the state
+        std::mem::swap(&mut chip8.rng, &mut self.rng);
+        *self = chip8;
+        Ok(())
+    }
+}
+
+fn read_payload(state: &[u8]) -> Result<(u16, &[u8]), StateError> {
+    if state.len() < HEADER_SIZE + CHECKSUM_SIZE {
+        return Err(StateError::Truncated);
+    }
+
+    let mut header = Reader::new(&state[..HEADER_SIZE]);
+    if header.bytes(4)? != MAGIC {
+        return Err(StateError::InvalidMagic);
+    }
+
+    let version = header.u16()?;
+    if !(MIN_VERSION..=VERSION).contains(&version) {
+        return Err(StateError::UnsupportedVersion(version));
+    }
+
+    let length = header.u32()? as usize;
+    if state.len() != HEADER_SIZE + length + CHECKSUM_SIZE {
+        return Err(StateError::Truncated);
+    }
+
+    let payload = &state[HEADER_SIZE..HEADER_SIZE + length];
+    let checksum = Reader::new(&state[HEADER_SIZE + length..]).u32()?;
+    if crc32(payload) != checksum {
+        return Err(StateError::ChecksumMismatch);
+    }
+
+    Ok((version, payload))
+}
+
+fn write_quirks(payload: &mut Vec<u8>, quirks: &QuirkConfig) {
+    let memory_increment = match quirks.memory_increment {
+        MemoryIncrement::None => 0,
+        MemoryIncrement::X => 1,
+        MemoryIncrement::XPlusOne => 2,
+    };
+
+    let display_wait = match quirks.display_wait {
+        DisplayWait::Never => 0,
+        DisplayWait::Always => 1,
+        DisplayWait::LoresOnly => 2,
+    };
+
+    payload.extend_from_slice(&[
+        quirks.vf_reset as u8,
+        memory_increment,
+        display_wait,
+        quirks.sprite_clipping as u8,
+        quirks.source_vy_bitshift as u8,
+        quirks.jump_vx_offset as u8,
+        quirks.extended_memory as u8,
+    ]);
+}
+
+fn read_quirks(reader: &mut Reader) -> Result<QuirkConfig, StateError> {
+    Ok(QuirkConfig {
+        vf_reset: read_bool(reader.u8()?)?,
+        memory_increment: match reader.u8()? {
+            0 => MemoryIncrement::None,
+            1 => MemoryIncrement::X,
+            2 => MemoryIncrement::XPlusOne,
+            _ => return Err(StateError::InvalidValue("memory increment quirk")),
+        },
+        display_wait: match reader.u8()? {
+            0 => DisplayWait::Never,
+            1 => DisplayWait::Always,
+            2 => DisplayWait::LoresOnly,
+            _ => return Err(StateError::InvalidValue("display wait quirk")),
+        },
+        sprite_clipping: read_bool(reader.u8()?)?,
+        source_vy_bitshift: read_bool(reader.u8()?)?,
+        jump_vx_offset: read_bool(reader.u8()?)?,
+        extended_memory: read_bool(reader.u8()?)?,
+    })
+}
+
+fn read_bool(value: u8) -> Result<bool, StateError> {
+    match value {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(StateError::InvalidValue("boolean")),
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data }
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], StateError> {
+        if self.data.len() < length {
+            return Err(StateError::Truncated);
+        }
+
+        let (bytes, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes([self.u8()?, self.u8()?]))
+    }
+
+    fn u32(&mut self) -> Result<u32, StateError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
@@ -0,0 +1,100 @@
+//! What instructions cost on a COSMAC VIP.
+//!
+//! The VIP runs the CHIP-8 interpreter on a CDP1802 clocked at 1.76 MHz, where a machine cycle
+//! takes 8 clock periods. Every CHIP-8 instruction costs the fetch and decode of the interpreter
+//! plus the routine for the opcode. The routine costs are the published timings of the VIP
+//! interpreter from Jackson Sommerich's "Chip-8 Instruction Scheduling and Frequency" (2019),
+//! converted from microseconds to machine cycles of 4.544 µs. Routines that loop cost a pass of
+//! their loop for every register, digit or key they go through.
+//!
+//! On every frame the display steals 1024 machine cycles to read out the 128 rows of the screen,
+//! and the interrupt routine that starts it also counts down the timers. `Dxyn` waits for that
+//! interrupt before it draws, so a game draws at most one sprite per frame.
+
+use crate::chip8::Chip8;
+
+/// Clock of the VIP's CDP1802, half of its 3.52128 MHz crystal
+pub const CLOCK_FREQUENCY: f64 = 1_760_640.0;
+
+pub const CLOCKS_PER_MACHINE_CYCLE: f64 = 8.0;
+
+pub const MACHINE_CYCLES_PER_SECOND: f64 = CLOCK_FREQUENCY / CLOCKS_PER_MACHINE_CYCLE;
+
+/// Machine cycles the display and its interrupt routine take at the start of every frame
+pub const INTERRUPT_CYCLES: u32 = 1024 + 30;
+
+/// Fetch and decode, paid by every instruction before it runs
+pub const FETCH_CYCLES: u32 = 40;
+
+/// One look at every key of the hex keypad, 10 machine cycles per key
+pub const KEY_SCAN_CYCLES: u32 = 16 * 10;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct InstructionTiming {
+    /// Machine cycles the instruction takes after its fetch
+    pub cycles: u32,
+    /// The instruction is fetched, then waits for the next display interrupt before it runs
+    pub waits_for_interrupt: bool,
+}
+
+impl Chip8 {
+    /// What the instruction at the program counter will cost on a VIP, from the current state
+    pub fn vip_timing(&self) -> InstructionTiming {
+        let pc = self.program_counter as usize;
+        let Some(bytes) = self.memory.get(pc..pc + 2) else {
+            return InstructionTiming { cycles: 0, waits_for_interrupt: false };
+        };
+
+        let instruction = ((bytes[0] as u16) << 8) | bytes[1] as u16;
+        let x = (instruction >> 8 & 0xF) as u32;
+
+        let cycles = match instruction >> 12 {
+            0x0 if instruction == 0x00E0 => 24,
+            0x0 if instruction == 0x00EE => 23,
+            0x1 | 0x2 | 0xB => 23,
+            0x3 | 0x4 | 0xA => 12,
+            0x5 | 0x9 | 0xE => 16,
+            0x6 => 6,
+            0x7 => 10,
+            0x8 => 44,
+            0xC => 36,
+            0xD => return self.sprite_timing(instruction),
+            0xF => match instruction & 0xFF {
+                0x07 | 0x15 | 0x18 => 10,
+                0x1E => 19,
+                0x29 => 20,
+                0x33 => bcd_cycles(self.registers[x as usize]),
+                // A pass of 14 machine cycles for each register from V0 to Vx
+                0x55 | 0x65 => 14 + 14 * (x + 1),
+                // Fx0A runs again until a key is pressed and released, every pass scans the keypad
+                0x0A => KEY_SCAN_CYCLES,
+                _ => 0,
+            },
+            // Machine code routines and the instructions the VIP doesn't have only cost the fetch
+            _ => 0,
+        };
+
+        InstructionTiming { cycles, waits_for_interrupt: false }
+    }
+
+    /// The published timing of `Dxyn` includes the wait for the interrupt, so the drawing itself
+    /// follows the sprite routine: sprites are shifted into place byte by byte, a sprite that isn't
+    /// on a byte boundary costs more for every row
+    fn sprite_timing(&self, instruction: u16) -> InstructionTiming {
+        let x = self.registers[(instruction >> 8 & 0xF) as usize];
+        let rows = (instruction & 0xF) as u32;
+        let row_cycles = if x.is_multiple_of(8) { 22 } else { 34 };
+
+        InstructionTiming {
+            cycles: 26 + rows * row_cycles,
+            waits_for_interrupt: true,
+        }
+    }
+}
+
+/// `Fx33` subtracts 100 from the value until it would go below zero to count the hundreds, then 10
+/// for the tens, the ones are what is left. Every subtraction that fits costs another pass.
+fn bcd_cycles(value: u8) -> u32 {
+    let (hundreds, tens) = (value as u32 / 100, value as u32 / 10 % 10);
+    84 + 16 * (hundreds + tens)
+}
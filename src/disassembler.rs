@@ -0,0 +1,449 @@
+//! Turns CHIP-8, SUPER-CHIP and XO-CHIP machine code back into readable source.
+//!
+//! Code and data are told apart by following the program from 0x200: jumps, calls and both
+//! paths of every skip are traced, anything that is never reached is listed as data bytes. Jump
+//! and call targets get a label, so the Octo syntax output can be assembled again.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::chip8::Opcode;
+
+const PROGRAM_START: u16 = 0x200;
+const DATA_BYTES_PER_LINE: usize = 8;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Syntax {
+    /// Octo assembly language, can be assembled again
+    Octo,
+    /// The classic mnemonics from Cowgod's CHIP-8 technical reference, a listing for reading that
+    /// can't be assembled again
+    Cowgod,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Operation {
+    ScrollDown(u8),
+    ScrollUp(u8),
+    Clear,
+    Return,
+    ScrollRight,
+    ScrollLeft,
+    Exit,
+    Lores,
+    Hires,
+    Jump(u16),
+    Call(u16),
+    SkipEqual(u8, u8),
+    SkipNotEqual(u8, u8),
+    SkipRegisterEqual(u8, u8),
+    SaveRange(u8, u8),
+    LoadRange(u8, u8),
+    Set(u8, u8),
+    Add(u8, u8),
+    Copy(u8, u8),
+    Or(u8, u8),
+    And(u8, u8),
+    Xor(u8, u8),
+    AddRegister(u8, u8),
+    Sub(u8, u8),
+    ShiftRight(u8, u8),
+    SubReverse(u8, u8),
+    ShiftLeft(u8, u8),
+    SkipRegisterNotEqual(u8, u8),
+    SetIndex(u16),
+    JumpOffset(u16),
+    Random(u8, u8),
+    Draw(u8, u8, u8),
+    SkipKey(u8),
+    SkipNotKey(u8),
+    LongIndex(u16),
+    Plane(u8),
+    Audio,
+    GetDelay(u8),
+    WaitKey(u8),
+    SetDelay(u8),
+    SetSound(u8),
+    AddIndex(u8),
+    Font(u8),
+    BigFont(u8),
+    Bcd(u8),
+    Pitch(u8),
+    Store(u8),
+    Load(u8),
+    StoreFlags(u8),
+    LoadFlags(u8),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum LabelKind {
+    Jump,
+    Subroutine,
+}
+
+/// Source for the instruction at `address`, None when the bytes aren't a known instruction.
+/// Addresses are written as numbers.
+pub fn decode_at(memory: &[u8], address: usize, syntax: Syntax) -> Option<String> {
+    let (operation, _) = decode(memory, address)?;
+    Some(format_operation(operation, syntax, &|address| format!("0x{:03X}", address)))
+}
+
+/// Full listing of a ROM that is loaded at 0x200
+pub fn disassemble(rom: &[u8], syntax: Syntax) -> String {
+    let targets = trace(rom);
+    let lines = split_lines(rom, &targets.code);
+
+    // Labels can only go where a line starts, anything else is referenced by its address
+    let labels: BTreeMap<u16, String> = targets.labels.iter()
+        .filter(|(address, _)| lines.iter().any(|line| line.address == **address))
+        .map(|(&address, kind)| (address, label_name(address, *kind)))
+        .collect();
+
+    let label = |address: u16| labels.get(&address).cloned().unwrap_or_else(|| format!("0x{:03X}", address));
+
+    let mut output = String::new();
+
+    for line in &lines {
+        if let Some(name) = labels.get(&line.address) {
+            match syntax {
+                Syntax::Octo => output.push_str(&format!(": {}\n", name)),
+                Syntax::Cowgod => output.push_str(&format!("{}:\n", name)),
+            }
+        }
+
+        let bytes = &rom[(line.address - PROGRAM_START) as usize..][..line.length];
+        let hex: String = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+
+        let text = match line.operation {
+            Some(operation) => format_operation(operation, syntax, &label),
+            None => format_data(bytes, syntax),
+        };
+
+        match syntax {
+            Syntax::Octo => output.push_str(&format!("\t{:<40}# {:03X}: {}\n", text, line.address, hex)),
+            Syntax::Cowgod => output.push_str(&format!("{:03X}  {:<16}  {}\n", line.address, hex, text)),
+        }
+    }
+
+    output
+}
+
+struct Targets {
+    code: BTreeSet<u16>,
+    labels: BTreeMap<u16, LabelKind>,
+}
+
+struct Line {
+    address: u16,
+    length: usize,
+    operation: Option<Operation>,
+}
+
+/// Follows every path through the program from the start address
+fn trace(rom: &[u8]) -> Targets {
+    let memory = rom_memory(rom);
+    let mut targets = Targets { code: BTreeSet::new(), labels: BTreeMap::new() };
+    let mut pending = vec![PROGRAM_START];
+    add_label(&mut targets, PROGRAM_START, LabelKind::Jump);
+
+    while let Some(mut address) = pending.pop() {
+        loop {
+            if targets.code.contains(&address) {
+                break;
+            }
+
+            let Some((operation, length)) = decode(&memory, address as usize) else {
+                break;
+            };
+
+            targets.code.insert(address);
+
+            // Nothing follows an instruction in the last word of 64 KiB memory
+            let Some(next) = address.checked_add(length as u16) else {
+                break;
+            };
+
+            match operation {
+                Operation::Jump(target) => {
+                    add_label(&mut targets, target, LabelKind::Jump);
+                    pending.push(target);
+                    break;
+                }
+                Operation::JumpOffset(target) => {
+                    add_label(&mut targets, target, LabelKind::Jump);
+                    pending.push(target);
+                    break;
+                }
+                Operation::Call(target) => {
+                    add_label(&mut targets, target, LabelKind::Subroutine);
+                    pending.push(target);
+                }
+                Operation::Return | Operation::Exit => break,
+                operation if is_skip(operation) => {
+                    let skipped = decode(&memory, next as usize).map_or(2, |(_, length)| length);
+                    pending.extend(next.checked_add(skipped as u16));
+                }
+                _ => {}
+            }
+
+            address = next;
+        }
+    }
+
+    targets
+}
+
+/// Walks the ROM in order, every traced instruction that doesn't overlap the previous line
+/// becomes a line of code, everything else is grouped into lines of data
+fn split_lines(rom: &[u8], code: &BTreeSet<u16>) -> Vec<Line> {
+    let memory = rom_memory(rom);
+    let end = PROGRAM_START as usize + rom.len();
+    let mut lines: Vec<Line> = vec![];
+    let mut address = PROGRAM_START as usize;
+
+    while address < end {
+        let instruction = if code.contains(&(address as u16)) { decode(&memory, address) } else { None };
+
+        match instruction {
+            Some((operation, length)) if address + length <= end => {
+                lines.push(Line { address: address as u16, length, operation: Some(operation) });
+                address += length;
+            }
+            _ => {
+                let data_line = lines.last_mut()
+                    .filter(|line| line.operation.is_none() && line.length < DATA_BYTES_PER_LINE)
+                    .filter(|line| line.address as usize + line.length == address && !code.contains(&(address as u16)));
+
+                match data_line {
+                    Some(line) => line.length += 1,
+                    None => lines.push(Line { address: address as u16, length: 1, operation: None }),
+                }
+                address += 1;
+            }
+        }
+    }
+
+    lines
+}
+
+/// A subroutine label wins over a jump label for the same address
+fn add_label(targets: &mut Targets, address: u16, kind: LabelKind) {
+    let entry = targets.labels.entry(address).or_insert(kind);
+    *entry = (*entry).max(kind);
+}
+
+fn rom_memory(rom: &[u8]) -> Vec<u8> {
+    let mut memory = vec![0; PROGRAM_START as usize];
+    memory.extend_from_slice(rom);
+    memory
+}
+
+fn label_name(address: u16, kind: LabelKind) -> String {
+    match (address, kind) {
+        (PROGRAM_START, _) => "main".to_string(),
+        (_, LabelKind::Jump) => format!("label_{:03x}", address),
+        (_, LabelKind::Subroutine) => format!("sub_{:03x}", address),
+    }
+}
+
+fn is_skip(operation: Operation) -> bool {
+    matches!(
+        operation,
+        Operation::SkipEqual(..) | Operation::SkipNotEqual(..) | Operation::SkipRegisterEqual(..)
+            | Operation::SkipRegisterNotEqual(..) | Operation::SkipKey(_) | Operation::SkipNotKey(_)
+    )
+}
+
+/// Decodes the instruction at `address` together with its length in bytes
+fn decode(memory: &[u8], address: usize) -> Option<(Operation, usize)> {
+    let word = |address: usize| -> Option<u16> {
+        Some(((*memory.get(address)? as u16) << 8) | *memory.get(address + 1)? as u16)
+    };
+
+    let opcode = Opcode::from_instruction(word(address)?);
+
+    let operation = match opcode {
+        Opcode { opcode: 0x0, x: 0x0, y: 0xC, n, .. } => Operation::ScrollDown(n),
+        Opcode { opcode: 0x0, x: 0x0, y: 0xD, n, .. } => Operation::ScrollUp(n),
+        Opcode { instruction: 0x00E0, .. } => Operation::Clear,
+        Opcode { instruction: 0x00EE, .. } => Operation::Return,
+        Opcode { instruction: 0x00FB, .. } => Operation::ScrollRight,
+        Opcode { instruction: 0x00FC, .. } => Operation::ScrollLeft,
+        Opcode { instruction: 0x00FD, .. } => Operation::Exit,
+        Opcode { instruction: 0x00FE, .. } => Operation::Lores,
+        Opcode { instruction: 0x00FF, .. } => Operation::Hires,
+        Opcode { opcode: 0x1, nnn, .. } => Operation::Jump(nnn),
+        Opcode { opcode: 0x2, nnn, .. } => Operation::Call(nnn),
+        Opcode { opcode: 0x3, x, nn, .. } => Operation::SkipEqual(x, nn),
+        Opcode { opcode: 0x4, x, nn, .. } => Operation::SkipNotEqual(x, nn),
+        Opcode { opcode: 0x5, n: 0x0, x, y, .. } => Operation::SkipRegisterEqual(x, y),
+        Opcode { opcode: 0x5, n: 0x2, x, y, .. } => Operation::SaveRange(x, y),
+        Opcode { opcode: 0x5, n: 0x3, x, y, .. } => Operation::LoadRange(x, y),
+        Opcode { opcode: 0x6, x, nn, .. } => Operation::Set(x, nn),
+        Opcode { opcode: 0x7, x, nn, .. } => Operation::Add(x, nn),
+        Opcode { opcode: 0x8, n: 0x0, x, y, .. } => Operation::Copy(x, y),
+        Opcode { opcode: 0x8, n: 0x1, x, y, .. } => Operation::Or(x, y),
+        Opcode { opcode: 0x8, n: 0x2, x, y, .. } => Operation::And(x, y),
+        Opcode { opcode: 0x8, n: 0x3, x, y, .. } => Operation::Xor(x, y),
+        Opcode { opcode: 0x8, n: 0x4, x, y, .. } => Operation::AddRegister(x, y),
+        Opcode { opcode: 0x8, n: 0x5, x, y, .. } => Operation::Sub(x, y),
+        Opcode { opcode: 0x8, n: 0x6, x, y, .. } => Operation::ShiftRight(x, y),
+        Opcode { opcode: 0x8, n: 0x7, x, y, .. } => Operation::SubReverse(x, y),
+        Opcode { opcode: 0x8, n: 0xE, x, y, .. } => Operation::ShiftLeft(x, y),
+        Opcode { opcode: 0x9, n: 0x0, x, y, .. } => Operation::SkipRegisterNotEqual(x, y),
+        Opcode { opcode: 0xA, nnn, .. } => Operation::SetIndex(nnn),
+        Opcode { opcode: 0xB, nnn, .. } => Operation::JumpOffset(nnn),
+        Opcode { opcode: 0xC, x, nn, .. } => Operation::Random(x, nn),
+        Opcode { opcode: 0xD, x, y, n, .. } => Operation::Draw(x, y, n),
+        Opcode { opcode: 0xE, nn: 0x9E, x, .. } => Operation::SkipKey(x),
+        Opcode { opcode: 0xE, nn: 0xA1, x, .. } => Operation::SkipNotKey(x),
+        Opcode { instruction: 0xF000, .. } => return Some((Operation::LongIndex(word(address + 2)?), 4)),
+        Opcode { opcode: 0xF, nn: 0x01, x, .. } => Operation::Plane(x),
+        Opcode { instruction: 0xF002, .. } => Operation::Audio,
+        Opcode { opcode: 0xF, nn: 0x07, x, .. } => Operation::GetDelay(x),
+        Opcode { opcode: 0xF, nn: 0x0A, x, .. } => Operation::WaitKey(x),
+        Opcode { opcode: 0xF, nn: 0x15, x, .. } => Operation::SetDelay(x),
+        Opcode { opcode: 0xF, nn: 0x18, x, .. } => Operation::SetSound(x),
+        Opcode { opcode: 0xF, nn: 0x1E, x, .. } => Operation::AddIndex(x),
+        Opcode { opcode: 0xF, nn: 0x29, x, .. } => Operation::Font(x),
+        Opcode { opcode: 0xF, nn: 0x30, x, .. } => Operation::BigFont(x),
+        Opcode { opcode: 0xF, nn: 0x33, x, .. } => Operation::Bcd(x),
+        Opcode { opcode: 0xF, nn: 0x3A, x, .. } => Operation::Pitch(x),
+        Opcode { opcode: 0xF, nn: 0x55, x, .. } => Operation::Store(x),
+        Opcode { opcode: 0xF, nn: 0x65, x, .. } => Operation::Load(x),
+        Opcode { opcode: 0xF, nn: 0x75, x, .. } => Operation::StoreFlags(x),
+        Opcode { opcode: 0xF, nn: 0x85, x, .. } => Operation::LoadFlags(x),
+        _ => return None,
+    };
+
+    Some((operation, 2))
+}
+
+fn format_operation(operation: Operation, syntax: Syntax, label: &dyn Fn(u16) -> String) -> String {
+    match syntax {
+        Syntax::Octo => format_octo(operation, label),
+        Syntax::Cowgod => format_cowgod(operation, label),
+    }
+}
+
+fn format_octo(operation: Operation, label: &dyn Fn(u16) -> String) -> String {
+    match operation {
+        Operation::ScrollDown(n) => format!("scroll-down {}", n),
+        Operation::ScrollUp(n) => format!("scroll-up {}", n),
+        Operation::Clear => "clear".to_string(),
+        Operation::Return => "return".to_string(),
+        Operation::ScrollRight => "scroll-right".to_string(),
+        Operation::ScrollLeft => "scroll-left".to_string(),
+        Operation::Exit => "exit".to_string(),
+        Operation::Lores => "lores".to_string(),
+        Operation::Hires => "hires".to_string(),
+        Operation::Jump(nnn) => format!("jump {}", label(nnn)),
+        Operation::Call(nnn) => match label(nnn) {
+            name if name.starts_with("0x") => format!(":call {}", name),
+            name => name,
+        },
+        // Octo runs the next instruction when the condition holds, the skips test the opposite
+        Operation::SkipEqual(x, nn) => format!("if v{:x} != 0x{:02X} then", x, nn),
+        Operation::SkipNotEqual(x, nn) => format!("if v{:x} == 0x{:02X} then", x, nn),
+        Operation::SkipRegisterEqual(x, y) => format!("if v{:x} != v{:x} then", x, y),
+        Operation::SkipRegisterNotEqual(x, y) => format!("if v{:x} == v{:x} then", x, y),
+        Operation::SkipKey(x) => format!("if v{:x} -key then", x),
+        Operation::SkipNotKey(x) => format!("if v{:x} key then", x),
+        Operation::SaveRange(x, y) => format!("save v{:x} - v{:x}", x, y),
+        Operation::LoadRange(x, y) => format!("load v{:x} - v{:x}", x, y),
+        Operation::Set(x, nn) => format!("v{:x} := 0x{:02X}", x, nn),
+        Operation::Add(x, nn) => format!("v{:x} += 0x{:02X}", x, nn),
+        Operation::Copy(x, y) => format!("v{:x} := v{:x}", x, y),
+        Operation::Or(x, y) => format!("v{:x} |= v{:x}", x, y),
+        Operation::And(x, y) => format!("v{:x} &= v{:x}", x, y),
+        Operation::Xor(x, y) => format!("v{:x} ^= v{:x}", x, y),
+        Operation::AddRegister(x, y) => format!("v{:x} += v{:x}", x, y),
+        Operation::Sub(x, y) => format!("v{:x} -= v{:x}", x, y),
+        Operation::ShiftRight(x, y) => format!("v{:x} >>= v{:x}", x, y),
+        Operation::SubReverse(x, y) => format!("v{:x} =- v{:x}", x, y),
+        Operation::ShiftLeft(x, y) => format!("v{:x} <<= v{:x}", x, y),
+        Operation::SetIndex(nnn) => format!("i := 0x{:03X}", nnn),
+        Operation::JumpOffset(nnn) => format!("jump0 {}", label(nnn)),
+        Operation::Random(x, nn) => format!("v{:x} := random 0x{:02X}", x, nn),
+        Operation::Draw(x, y, n) => format!("sprite v{:x} v{:x} {}", x, y, n),
+        Operation::LongIndex(nnnn) => format!("i := long 0x{:04X}", nnnn),
+        Operation::Plane(n) => format!("plane {}", n),
+        Operation::Audio => "audio".to_string(),
+        Operation::GetDelay(x) => format!("v{:x} := delay", x),
+        Operation::WaitKey(x) => format!("v{:x} := key", x),
+        Operation::SetDelay(x) => format!("delay := v{:x}", x),
+        Operation::SetSound(x) => format!("buzzer := v{:x}", x),
+        Operation::AddIndex(x) => format!("i += v{:x}", x),
+        Operation::Font(x) => format!("i := hex v{:x}", x),
+        Operation::BigFont(x) => format!("i := bighex v{:x}", x),
+        Operation::Bcd(x) => format!("bcd v{:x}", x),
+        Operation::Pitch(x) => format!("pitch := v{:x}", x),
+        Operation::Store(x) => format!("save v{:x}", x),
+        Operation::Load(x) => format!("load v{:x}", x),
+        Operation::StoreFlags(x) => format!("saveflags v{:x}", x),
+        Operation::LoadFlags(x) => format!("loadflags v{:x}", x),
+    }
+}
+
+fn format_cowgod(operation: Operation, label: &dyn Fn(u16) -> String) -> String {
+    match operation {
+        Operation::ScrollDown(n) => format!("SCD {}", n),
+        Operation::ScrollUp(n) => format!("SCU {}", n),
+        Operation::Clear => "CLS".to_string(),
+        Operation::Return => "RET".to_string(),
+        Operation::ScrollRight => "SCR".to_string(),
+        Operation::ScrollLeft => "SCL".to_string(),
+        Operation::Exit => "EXIT".to_string(),
+        Operation::Lores => "LOW".to_string(),
+        Operation::Hires => "HIGH".to_string(),
+        Operation::Jump(nnn) => format!("JP {}", label(nnn)),
+        Operation::Call(nnn) => format!("CALL {}", label(nnn)),
+        Operation::SkipEqual(x, nn) => format!("SE V{:X}, 0x{:02X}", x, nn),
+        Operation::SkipNotEqual(x, nn) => format!("SNE V{:X}, 0x{:02X}", x, nn),
+        Operation::SkipRegisterEqual(x, y) => format!("SE V{:X}, V{:X}", x, y),
+        Operation::SkipRegisterNotEqual(x, y) => format!("SNE V{:X}, V{:X}", x, y),
+        Operation::SkipKey(x) => format!("SKP V{:X}", x),
+        Operation::SkipNotKey(x) => format!("SKNP V{:X}", x),
+        Operation::SaveRange(x, y) => format!("SAVE V{:X}, V{:X}", x, y),
+        Operation::LoadRange(x, y) => format!("LOAD V{:X}, V{:X}", x, y),
+        Operation::Set(x, nn) => format!("LD V{:X}, 0x{:02X}", x, nn),
+        Operation::Add(x, nn) => format!("ADD V{:X}, 0x{:02X}", x, nn),
+        Operation::Copy(x, y) => format!("LD V{:X}, V{:X}", x, y),
+        Operation::Or(x, y) => format!("OR V{:X}, V{:X}", x, y),
+        Operation::And(x, y) => format!("AND V{:X}, V{:X}", x, y),
+        Operation::Xor(x, y) => format!("XOR V{:X}, V{:X}", x, y),
+        Operation::AddRegister(x, y) => format!("ADD V{:X}, V{:X}", x, y),
+        Operation::Sub(x, y) => format!("SUB V{:X}, V{:X}", x, y),
+        Operation::ShiftRight(x, y) => format!("SHR V{:X}, V{:X}", x, y),
+        Operation::SubReverse(x, y) => format!("SUBN V{:X}, V{:X}", x, y),
+        Operation::ShiftLeft(x, y) => format!("SHL V{:X}, V{:X}", x, y),
+        Operation::SetIndex(nnn) => format!("LD I, 0x{:03X}", nnn),
+        Operation::JumpOffset(nnn) => format!("JP V0, {}", label(nnn)),
+        Operation::Random(x, nn) => format!("RND V{:X}, 0x{:02X}", x, nn),
+        Operation::Draw(x, y, n) => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        Operation::LongIndex(nnnn) => format!("LD I, LONG 0x{:04X}", nnnn),
+        Operation::Plane(n) => format!("PLANE {}", n),
+        Operation::Audio => "AUDIO".to_string(),
+        Operation::GetDelay(x) => format!("LD V{:X}, DT", x),
+        Operation::WaitKey(x) => format!("LD V{:X}, K", x),
+        Operation::SetDelay(x) => format!("LD DT, V{:X}", x),
+        Operation::SetSound(x) => format!("LD ST, V{:X}", x),
+        Operation::AddIndex(x) => format!("ADD I, V{:X}", x),
+        Operation::Font(x) => format!("LD F, V{:X}", x),
+        Operation::BigFont(x) => format!("LD HF, V{:X}", x),
+        Operation::Bcd(x) => format!("LD B, V{:X}", x),
+        Operation::Pitch(x) => format!("PITCH V{:X}", x),
+        Operation::Store(x) => format!("LD [I], V{:X}", x),
+        Operation::Load(x) => format!("LD V{:X}, [I]", x),
+        Operation::StoreFlags(x) => format!("LD R, V{:X}", x),
+        Operation::LoadFlags(x) => format!("LD V{:X}, R", x),
+    }
+}
+
+fn format_data(bytes: &[u8], syntax: Syntax) -> String {
+    let values: Vec<String> = bytes.iter().map(|byte| format!("0x{:02X}", byte)).collect();
+
+    match syntax {
+        Syntax::Octo => values.join(" "),
+        Syntax::Cowgod => format!("DB {}", values.join(", ")),
+    }
+}
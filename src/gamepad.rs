@@ -0,0 +1,332 @@
+//! Gamepad buttons, read with gilrs on the desktop and the Gamepad API in the browser.
+//!
+//! Sources are polled once per frame and report button changes. The left stick acts as a second
+//! directional pad, so pads without a good one still work.
+
+use std::collections::HashMap;
+
+#[cfg(any(feature = "gamepad", target_arch = "wasm32"))]
+/// How far the stick has to move before it counts as a direction
+const STICK_THRESHOLD: f64 = 0.5;
+
+/// Pads that get bindings, for games with up to four players
+pub const MAX_GAMEPADS: usize = 4;
+
+/// Buttons by their position on the pad, `South` is A on an Xbox pad and cross on a PlayStation pad
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftShoulder,
+    RightShoulder,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// Buttons in the order of the Gamepad API's standard mapping
+pub const STANDARD_BUTTONS: [GamepadButton; 16] = [
+    GamepadButton::South,
+    GamepadButton::East,
+    GamepadButton::West,
+    GamepadButton::North,
+    GamepadButton::LeftShoulder,
+    GamepadButton::RightShoulder,
+    GamepadButton::LeftTrigger,
+    GamepadButton::RightTrigger,
+    GamepadButton::Select,
+    GamepadButton::Start,
+    GamepadButton::LeftStick,
+    GamepadButton::RightStick,
+    GamepadButton::DPadUp,
+    GamepadButton::DPadDown,
+    GamepadButton::DPadLeft,
+    GamepadButton::DPadRight,
+];
+
+/// A button of one of the pads, by the index of the pad
+pub type PadButton = (usize, GamepadButton);
+
+/// Name in key binding files, the pad number followed by the button like `Gamepad1South`
+pub fn button_name((gamepad, button): PadButton) -> String {
+    format!("Gamepad{}{:?}", gamepad + 1, button)
+}
+
+pub fn button_from_name(name: &str) -> Option<PadButton> {
+    let name = name.strip_prefix("Gamepad")?;
+    let digits = name.find(|character: char| !character.is_ascii_digit())?;
+    let number = name[..digits].parse::<usize>().ok().filter(|number| (1..=MAX_GAMEPADS).contains(number))?;
+
+    let button = STANDARD_BUTTONS.iter().copied().find(|button| format!("{:?}", button) == name[digits..])?;
+    Some((number - 1, button))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GamepadEvent {
+    /// Index of the pad, in the order they were connected
+    pub gamepad: usize,
+    pub button: GamepadButton,
+    pub pressed: bool,
+}
+
+pub trait GamepadSource {
+    /// Button changes since the last poll
+    fn poll(&mut self) -> Vec<GamepadEvent>;
+}
+
+/// Source for builds without gamepad support
+pub struct NoGamepads;
+
+impl GamepadSource for NoGamepads {
+    fn poll(&mut self) -> Vec<GamepadEvent> {
+        vec![]
+    }
+}
+
+/// The gamepad source for this platform, without gamepads when there is no support
+pub fn create_source() -> Box<dyn GamepadSource> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        Box::new(WebGamepads::new())
+    }
+    #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+    {
+        match gilrs::Gilrs::new() {
+            Ok(gilrs) => Box::new(NativeGamepads::new(gilrs)),
+            Err(error) => {
+                log::warn!("Gamepads are disabled: {}", error);
+                Box::new(NoGamepads)
+            }
+        }
+    }
+    #[cfg(all(not(feature = "gamepad"), not(target_arch = "wasm32")))]
+    {
+        Box::new(NoGamepads)
+    }
+}
+
+/// The same buttons on every pad: directions on 2, 8, 4 and 6 and the main button on 5, the keys
+/// most games use
+pub fn default_layout() -> HashMap<PadButton, u8> {
+    let buttons = [
+        (GamepadButton::DPadUp, 0x2),
+        (GamepadButton::DPadDown, 0x8),
+        (GamepadButton::DPadLeft, 0x4),
+        (GamepadButton::DPadRight, 0x6),
+        (GamepadButton::South, 0x5),
+        (GamepadButton::East, 0xA),
+        (GamepadButton::West, 0xB),
+        (GamepadButton::North, 0x1),
+        (GamepadButton::Start, 0xF),
+        (GamepadButton::Select, 0x0),
+    ];
+
+    (0..MAX_GAMEPADS)
+        .flat_map(|gamepad| buttons.iter().map(move |&(button, key)| ((gamepad, button), key)))
+        .collect()
+}
+
+#[cfg(any(feature = "gamepad", target_arch = "wasm32"))]
+#[derive(Default)]
+struct PadState {
+    buttons: HashMap<GamepadButton, bool>,
+    /// Left stick position, `y` points down like in the Gamepad API
+    stick: (f64, f64),
+    reported: HashMap<GamepadButton, bool>,
+}
+
+#[cfg(any(feature = "gamepad", target_arch = "wasm32"))]
+impl PadState {
+    fn is_pressed(&self, button: GamepadButton) -> bool {
+        let (x, y) = self.stick;
+        let stick = match button {
+            GamepadButton::DPadLeft => x < -STICK_THRESHOLD,
+            GamepadButton::DPadRight => x > STICK_THRESHOLD,
+            GamepadButton::DPadUp => y < -STICK_THRESHOLD,
+            GamepadButton::DPadDown => y > STICK_THRESHOLD,
+            _ => false,
+        };
+
+        stick || self.buttons.get(&button).copied().unwrap_or(false)
+    }
+}
+
+#[cfg(any(feature = "gamepad", target_arch = "wasm32"))]
+/// Tracks the buttons and sticks of the pads and reports what changed
+#[derive(Default)]
+struct ButtonStates {
+    pads: Vec<PadState>,
+}
+
+#[cfg(any(feature = "gamepad", target_arch = "wasm32"))]
+impl ButtonStates {
+    fn pad(&mut self, gamepad: usize) -> &mut PadState {
+        if self.pads.len() <= gamepad {
+            self.pads.resize_with(gamepad + 1, PadState::default);
+        }
+        &mut self.pads[gamepad]
+    }
+
+    fn set_button(&mut self, events: &mut Vec<GamepadEvent>, gamepad: usize, button: GamepadButton, pressed: bool) {
+        self.pad(gamepad).buttons.insert(button, pressed);
+        self.report(events, gamepad, button);
+    }
+
+    fn set_stick(&mut self, events: &mut Vec<GamepadEvent>, gamepad: usize, x: f64, y: f64) {
+        self.pad(gamepad).stick = (x, y);
+        for button in [GamepadButton::DPadUp, GamepadButton::DPadDown, GamepadButton::DPadLeft, GamepadButton::DPadRight] {
+            self.report(events, gamepad, button);
+        }
+    }
+
+    fn report(&mut self, events: &mut Vec<GamepadEvent>, gamepad: usize, button: GamepadButton) {
+        let pad = self.pad(gamepad);
+        let pressed = pad.is_pressed(button);
+
+        let reported = pad.reported.entry(button).or_insert(false);
+        if *reported != pressed {
+            *reported = pressed;
+            events.push(GamepadEvent { gamepad, button, pressed });
+        }
+    }
+}
+
+#[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+pub struct NativeGamepads {
+    gilrs: gilrs::Gilrs,
+    /// Connected pads in order, so the first pad is player one
+    ids: Vec<gilrs::GamepadId>,
+    states: ButtonStates,
+}
+
+#[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+impl NativeGamepads {
+    pub fn new(gilrs: gilrs::Gilrs) -> Self {
+        let ids = gilrs.gamepads().map(|(id, _)| id).collect();
+
+        NativeGamepads {
+            gilrs,
+            ids,
+            states: ButtonStates::default(),
+        }
+    }
+
+    fn index(&mut self, id: gilrs::GamepadId) -> usize {
+        match self.ids.iter().position(|known| *known == id) {
+            Some(index) => index,
+            None => {
+                self.ids.push(id);
+                self.ids.len() - 1
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+impl GamepadSource for NativeGamepads {
+    fn poll(&mut self) -> Vec<GamepadEvent> {
+        use gilrs::{Axis, Button, EventType};
+
+        let mut events = vec![];
+
+        while let Some(gilrs::Event { id, event, .. }) = self.gilrs.next_event() {
+            let gamepad = self.index(id);
+
+            let (button, pressed) = match event {
+                EventType::ButtonPressed(button, _) => (button, true),
+                EventType::ButtonReleased(button, _) => (button, false),
+                EventType::AxisChanged(axis @ (Axis::LeftStickX | Axis::LeftStickY), value, _) => {
+                    let (mut x, mut y) = self.states.pad(gamepad).stick;
+                    match axis {
+                        // gilrs points the Y axis up
+                        Axis::LeftStickX => x = value as f64,
+                        _ => y = -value as f64,
+                    }
+                    self.states.set_stick(&mut events, gamepad, x, y);
+                    continue;
+                }
+                _ => continue,
+            };
+
+            let button = match button {
+                Button::South => GamepadButton::South,
+                Button::East => GamepadButton::East,
+                Button::West => GamepadButton::West,
+                Button::North => GamepadButton::North,
+                Button::LeftTrigger => GamepadButton::LeftShoulder,
+                Button::RightTrigger => GamepadButton::RightShoulder,
+                Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+                Button::RightTrigger2 => GamepadButton::RightTrigger,
+                Button::Select => GamepadButton::Select,
+                Button::Start => GamepadButton::Start,
+                Button::LeftThumb => GamepadButton::LeftStick,
+                Button::RightThumb => GamepadButton::RightStick,
+                Button::DPadUp => GamepadButton::DPadUp,
+                Button::DPadDown => GamepadButton::DPadDown,
+                Button::DPadLeft => GamepadButton::DPadLeft,
+                Button::DPadRight => GamepadButton::DPadRight,
+                _ => continue,
+            };
+            self.states.set_button(&mut events, gamepad, button, pressed);
+        }
+
+        events
+    }
+}
+
+/// Polls `navigator.getGamepads()`, which only reports the current state of the buttons
+#[cfg(target_arch = "wasm32")]
+pub struct WebGamepads {
+    states: ButtonStates,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl WebGamepads {
+    pub fn new() -> Self {
+        WebGamepads { states: ButtonStates::default() }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl GamepadSource for WebGamepads {
+    fn poll(&mut self) -> Vec<GamepadEvent> {
+        use wasm_bindgen::JsCast;
+
+        let mut events = vec![];
+        let Some(gamepads) = web_sys::window().and_then(|window| window.navigator().get_gamepads().ok()) else {
+            return events;
+        };
+
+        // Disconnected pads leave a null in the list, the index of a pad doesn't change
+        for (gamepad, pad) in gamepads.iter().enumerate() {
+            let Ok(pad) = pad.dyn_into::<web_sys::Gamepad>() else {
+                continue;
+            };
+            if pad.mapping() != web_sys::GamepadMappingType::Standard {
+                continue;
+            }
+
+            for (index, button) in pad.buttons().iter().enumerate().take(STANDARD_BUTTONS.len()) {
+                if let Ok(button) = button.dyn_into::<web_sys::GamepadButton>() {
+                    self.states.set_button(&mut events, gamepad, STANDARD_BUTTONS[index], button.pressed());
+                }
+            }
+
+            let axes = pad.axes();
+            if let (Some(x), Some(y)) = (axes.get(0).as_f64(), axes.get(1).as_f64()) {
+                self.states.set_stick(&mut events, gamepad, x, y);
+            }
+        }
+
+        events
+    }
+}
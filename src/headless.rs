@@ -0,0 +1,280 @@
+//! Runs a [`Chip8`] without a window or GPU, for scripted runs and tests.
+//!
+//! Time is counted in frames: every frame the timers tick once and the CPU runs a fixed number of
+//! instructions, like the windowed runtime does at 60 Hz. Key input comes from a script of
+//! frame numbers and key changes, and the display can be written as ASCII art, PBM or PNG.
+
+use std::fmt;
+
+use crate::chip8::{Chip8, Chip8Error, CycleOutcome};
+use crate::chip8::display::Display;
+use crate::hash::crc32;
+
+pub const DEFAULT_INSTRUCTIONS_PER_FRAME: u32 = 12;
+
+/// Display colors for PNG output, the same black, purple, orange and white the window uses
+const PNG_PALETTE: [[u8; 3]; 4] = [[0x00, 0x00, 0x00], [0x6D, 0x2D, 0xF7], [0xF7, 0x6D, 0x2D], [0xFF, 0xFF, 0xFF]];
+const ASCII_PIXELS: [char; 4] = ['.', '#', '+', '@'];
+const ADLER32_MODULO: u32 = 65521;
+const MAX_STORED_BLOCK: usize = 0xFFFF;
+
+/// A key change that happens at the start of a frame
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub frame: u32,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub text: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid key event on line {}: '{}'", self.line, self.text)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// Parses a key script, one `<frame> <key> down|up` event per line, e.g. `30 A down`. Keys are a
+/// single hex digit, `#` starts a comment.
+pub fn parse_key_script(script: &str) -> Result<Vec<KeyEvent>, ScriptError> {
+    let mut events = vec![];
+
+    for (index, line) in script.lines().enumerate() {
+        let code = line.split('#').next().unwrap_or("").trim();
+        if code.is_empty() {
+            continue;
+        }
+
+        let error = || ScriptError { line: index + 1, text: code.to_string() };
+        let parts: Vec<&str> = code.split_whitespace().collect();
+
+        let [frame, key, state] = parts[..] else {
+            return Err(error());
+        };
+
+        let frame = frame.parse::<u32>().map_err(|_| error())?;
+        let key = u8::from_str_radix(key, 16).ok().filter(|key| *key < 16).ok_or_else(error)?;
+        let pressed = match state {
+            "down" => true,
+            "up" => false,
+            _ => return Err(error()),
+        };
+
+        events.push(KeyEvent { frame, key, pressed });
+    }
+
+    events.sort_by_key(|event| event.frame);
+    Ok(events)
+}
+
+pub struct HeadlessRunner {
+    pub chip8: Chip8,
+    instructions_per_frame: u32,
+    events: Vec<KeyEvent>,
+    next_event: usize,
+    frame: u32,
+    exited: bool,
+}
+
+impl HeadlessRunner {
+    pub fn new(chip8: Chip8, instructions_per_frame: u32) -> Self {
+        HeadlessRunner {
+            chip8,
+            instructions_per_frame,
+            events: vec![],
+            next_event: 0,
+            frame: 0,
+            exited: false,
+        }
+    }
+
+    pub fn with_key_events(mut self, mut events: Vec<KeyEvent>) -> Self {
+        events.sort_by_key(|event| event.frame);
+        self.events = events;
+        self.next_event = 0;
+        self
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Runs one frame: applies the key events for this frame, ticks the timers and runs the
+    /// instructions until the frame is used up, the program waits for the display or exits
+    pub fn run_frame(&mut self) -> Result<(), Chip8Error> {
+        while let Some(event) = self.events.get(self.next_event).filter(|event| event.frame <= self.frame) {
+            self.chip8.set_input(event.key, event.pressed);
+            self.next_event += 1;
+        }
+
+        self.frame += 1;
+
+        if self.exited {
+            return Ok(());
+        }
+
+        self.chip8.update();
+        for _ in 0..self.instructions_per_frame {
+            match self.chip8.cycle()? {
+                CycleOutcome::Executed => {}
+                CycleOutcome::BlockedOnDraw => break,
+                CycleOutcome::Exited => {
+                    self.exited = true;
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs the given number of frames, stops early when the program exits
+    pub fn run_frames(&mut self, frames: u32) -> Result<(), Chip8Error> {
+        for _ in 0..frames {
+            if self.exited {
+                break;
+            }
+            self.run_frame()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// One character per pixel: `.` off, `#` plane 1, `+` plane 2 and `@` both planes
+pub fn to_ascii(display: &Display) -> String {
+    let mut output = String::with_capacity((display.width() + 1) * display.height());
+
+    for y in 0..display.height() {
+        for x in 0..display.width() {
+            output.push(ASCII_PIXELS[(display.get(x, y) & 0b11) as usize]);
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Reads a display back from [`to_ascii`] output
+pub fn from_ascii(text: &str) -> Option<Display> {
+    let rows: Vec<&str> = text.lines().filter(|line| !line.is_empty()).collect();
+    let width = rows.first()?.chars().count();
+    let mut pixels = Vec::with_capacity(width * rows.len());
+
+    for row in &rows {
+        if row.chars().count() != width {
+            return None;
+        }
+        for character in row.chars() {
+            pixels.push(ASCII_PIXELS.iter().position(|pixel| *pixel == character)? as u8);
+        }
+    }
+
+    Display::from_pixels(width, rows.len(), pixels)
+}
+
+/// Binary PBM (P4), a pixel is black when it is lit on any plane
+pub fn to_pbm(display: &Display) -> Vec<u8> {
+    let mut output = format!("P4\n{} {}\n", display.width(), display.height()).into_bytes();
+
+    for y in 0..display.height() {
+        for row_byte in 0..display.width().div_ceil(8) {
+            let mut byte = 0u8;
+            for bit in 0..8 {
+                let x = row_byte * 8 + bit;
+                if x < display.width() && display.get(x, y) != 0 {
+                    byte |= 0x80 >> bit;
+                }
+            }
+            output.push(byte);
+        }
+    }
+
+    output
+}
+
+/// Indexed color PNG with one byte per pixel, `scale` pixels wide per CHIP-8 pixel
+pub fn to_png(display: &Display, scale: usize) -> Vec<u8> {
+    let scale = scale.max(1);
+    let width = display.width() * scale;
+    let height = display.height() * scale;
+
+    let mut image = Vec::with_capacity((width + 1) * height);
+    for y in 0..height {
+        // Filter type 0, no filtering
+        image.push(0);
+        for x in 0..width {
+            image.push(display.get(x / scale, y / scale) & 0b11);
+        }
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // Bit depth 8, color type 3 (indexed), default compression, filter and no interlacing
+    header.extend_from_slice(&[8, 3, 0, 0, 0]);
+
+    let mut output = b"\x89PNG\r\n\x1a\n".to_vec();
+    write_png_chunk(&mut output, b"IHDR", &header);
+    write_png_chunk(&mut output, b"PLTE", PNG_PALETTE.concat().as_slice());
+    write_png_chunk(&mut output, b"IDAT", &zlib_stored(&image));
+    write_png_chunk(&mut output, b"IEND", &[]);
+    output
+}
+
+fn write_png_chunk(output: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+
+    let start = output.len();
+    output.extend_from_slice(kind);
+    output.extend_from_slice(data);
+
+    let checksum = crc32(&output[start..]);
+    output.extend_from_slice(&checksum.to_be_bytes());
+}
+
+/// Wraps the data in a zlib stream of uncompressed deflate blocks, framebuffers are small enough
+/// that compressing them isn't worth a dependency
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut output = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+
+    if blocks.peek().is_none() {
+        output.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let length = block.len() as u16;
+
+        output.push(last as u8);
+        output.extend_from_slice(&length.to_le_bytes());
+        output.extend_from_slice(&(!length).to_le_bytes());
+        output.extend_from_slice(block);
+    }
+
+    output.extend_from_slice(&adler32(data).to_be_bytes());
+    output
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let mut a = 1u32;
+    let mut b = 0u32;
+
+    for &byte in data {
+        a = (a + byte as u32) % ADLER32_MODULO;
+        b = (b + a) % ADLER32_MODULO;
+    }
+
+    (b << 16) | a
+}
@@ -0,0 +1,345 @@
+//! Keyboard and gamepad bindings for the 16 keypad keys.
+//!
+//! The bindings are stored as text, with a default layout that replaces the built-in one and
+//! overrides for single ROMs, found by their hash:
+//!
+//! ```text
+//! chip8-keys 1
+//! [default]
+//! KeyX 0
+//! Digit1 1
+//! [rom 5f518084744bf3cb8733f6e5454dfd1634320563]
+//! ArrowLeft 5
+//! Gamepad2DPadUp C
+//! ```
+//!
+//! Keys are named after their physical position on a US keyboard, the way winit names them.
+//! Gamepad buttons are named after the pad and the button's position, see [`button_name`].
+
+use std::collections::HashMap;
+use std::fmt;
+
+use winit::keyboard::KeyCode;
+
+use crate::gamepad::{button_from_name, button_name, PadButton};
+use crate::gamepad;
+use crate::hash::{parse_sha1, to_hex};
+
+pub const KEY_FILE: &str = "keys.cfg";
+
+const HEADER: &str = "chip8-keys";
+const VERSION: u32 = 1;
+
+/// Order the rebinding flow asks for the keys, row by row as they sit on the keypad
+pub const KEYPAD_ORDER: [u8; 16] = [0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF];
+
+/// Keys that can be bound and stored by name
+const BINDABLE_KEYS: [KeyCode; 79] = [
+    KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+    KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+    KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE, KeyCode::KeyF,
+    KeyCode::KeyG, KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ, KeyCode::KeyK, KeyCode::KeyL,
+    KeyCode::KeyM, KeyCode::KeyN, KeyCode::KeyO, KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR,
+    KeyCode::KeyS, KeyCode::KeyT, KeyCode::KeyU, KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX,
+    KeyCode::KeyY, KeyCode::KeyZ,
+    KeyCode::Numpad0, KeyCode::Numpad1, KeyCode::Numpad2, KeyCode::Numpad3, KeyCode::Numpad4,
+    KeyCode::Numpad5, KeyCode::Numpad6, KeyCode::Numpad7, KeyCode::Numpad8, KeyCode::Numpad9,
+    KeyCode::NumpadAdd, KeyCode::NumpadSubtract, KeyCode::NumpadMultiply, KeyCode::NumpadDivide,
+    KeyCode::NumpadDecimal, KeyCode::NumpadEnter,
+    KeyCode::ArrowUp, KeyCode::ArrowDown, KeyCode::ArrowLeft, KeyCode::ArrowRight,
+    KeyCode::Space, KeyCode::Enter, KeyCode::Tab,
+    KeyCode::ShiftLeft, KeyCode::ShiftRight, KeyCode::ControlLeft, KeyCode::ControlRight,
+    KeyCode::AltLeft, KeyCode::AltRight,
+    KeyCode::Backquote, KeyCode::Minus, KeyCode::Equal, KeyCode::BracketLeft, KeyCode::BracketRight,
+    KeyCode::Backslash, KeyCode::Semicolon, KeyCode::Quote, KeyCode::Comma, KeyCode::Period,
+    KeyCode::Slash, KeyCode::IntlBackslash,
+    KeyCode::End, KeyCode::Delete,
+];
+
+/// The QWERTY layout: 1234, QWER, ASDF and ZXCV on the keypad rows
+pub fn default_layout() -> HashMap<KeyCode, u8> {
+    let mut key_map: HashMap<KeyCode, u8> = HashMap::new();
+
+    key_map.insert(KeyCode::Digit1, 0x1);
+    key_map.insert(KeyCode::Digit2, 0x2);
+    key_map.insert(KeyCode::Digit3, 0x3);
+    key_map.insert(KeyCode::Digit4, 0xC);
+
+    key_map.insert(KeyCode::KeyQ, 0x4);
+    key_map.insert(KeyCode::KeyW, 0x5);
+    key_map.insert(KeyCode::KeyE, 0x6);
+    key_map.insert(KeyCode::KeyR, 0xD);
+
+    key_map.insert(KeyCode::KeyA, 0x7);
+    key_map.insert(KeyCode::KeyS, 0x8);
+    key_map.insert(KeyCode::KeyD, 0x9);
+    key_map.insert(KeyCode::KeyF, 0xE);
+
+    key_map.insert(KeyCode::KeyZ, 0xA);
+    key_map.insert(KeyCode::KeyX, 0x0);
+    key_map.insert(KeyCode::KeyC, 0xB);
+    key_map.insert(KeyCode::KeyV, 0xF);
+    key_map
+}
+
+pub fn key_name(keycode: KeyCode) -> String {
+    format!("{:?}", keycode)
+}
+
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS.iter().copied().find(|keycode| key_name(*keycode) == name)
+}
+
+pub fn is_bindable(keycode: KeyCode) -> bool {
+    BINDABLE_KEYS.contains(&keycode)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyConfigError {
+    InvalidHeader,
+    UnsupportedVersion(u32),
+    InvalidLine { line: usize, text: String },
+}
+
+impl fmt::Display for KeyConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyConfigError::InvalidHeader => write!(f, "Not a key binding file"),
+            KeyConfigError::UnsupportedVersion(version) => write!(f, "Unsupported key binding version {}", version),
+            KeyConfigError::InvalidLine { line, text } => write!(f, "Invalid key binding line {}: '{}'", line, text),
+        }
+    }
+}
+
+impl std::error::Error for KeyConfigError {}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyConfig {
+    /// Replaces the built-in layout when set
+    pub default: Option<HashMap<KeyCode, u8>>,
+    /// Bindings added on top of the default layout for a ROM, by the ROM's hash
+    pub roms: HashMap<[u8; 20], HashMap<KeyCode, u8>>,
+    /// Replaces the built-in gamepad layout when set
+    pub gamepad_default: Option<HashMap<PadButton, u8>>,
+    /// Gamepad bindings added on top of the default gamepad layout for a ROM
+    pub gamepad_roms: HashMap<[u8; 20], HashMap<PadButton, u8>>,
+}
+
+impl KeyConfig {
+    pub fn new() -> Self {
+        KeyConfig {
+            default: None,
+            roms: HashMap::new(),
+            gamepad_default: None,
+            gamepad_roms: HashMap::new(),
+        }
+    }
+
+    /// The stored bindings, an empty config when there are none or they can't be read
+    pub fn load() -> Self {
+        let Some(text) = crate::storage::read_config(KEY_FILE) else {
+            return KeyConfig::new();
+        };
+
+        KeyConfig::from_text(&text).unwrap_or_else(|error| {
+            log::error!("Ignoring {}: {}", KEY_FILE, error);
+            KeyConfig::new()
+        })
+    }
+
+    pub fn save(&self) {
+        match crate::storage::write_config(KEY_FILE, &self.to_text()) {
+            Ok(()) => log::info!("Saved key bindings"),
+            Err(error) => log::error!("Failed to save key bindings: {}", error),
+        }
+    }
+
+    /// Binds the keys for one ROM or, without a hash, in the default layout. The keys that were
+    /// bound to the same keypad keys before lose their binding.
+    pub fn bind(&mut self, rom_hash: Option<[u8; 20]>, bindings: &[(KeyCode, u8)]) {
+        let key_map = match rom_hash {
+            Some(hash) => self.roms.entry(hash).or_default(),
+            None => self.default.get_or_insert_with(default_layout),
+        };
+
+        key_map.retain(|_, key| !bindings.iter().any(|(_, bound)| bound == key));
+        key_map.extend(bindings.iter().copied());
+    }
+
+    /// Binds gamepad buttons like [`KeyConfig::bind`] binds keys, a button only takes the keypad
+    /// key from the buttons of its own pad
+    pub fn bind_buttons(&mut self, rom_hash: Option<[u8; 20]>, bindings: &[(PadButton, u8)]) {
+        let button_map = match rom_hash {
+            Some(hash) => self.gamepad_roms.entry(hash).or_default(),
+            None => self.gamepad_default.get_or_insert_with(gamepad::default_layout),
+        };
+
+        button_map.retain(|(gamepad, _), key| !bindings.iter().any(|((bound_gamepad, _), bound)| bound_gamepad == gamepad && bound == key));
+        button_map.extend(bindings.iter().copied());
+    }
+
+    /// Removes the overrides of one ROM or, without a hash, goes back to the built-in layouts
+    pub fn reset(&mut self, rom_hash: Option<[u8; 20]>) {
+        match rom_hash {
+            Some(hash) => {
+                self.roms.remove(&hash);
+                self.gamepad_roms.remove(&hash);
+            }
+            None => {
+                self.default = None;
+                self.gamepad_default = None;
+            }
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{} {}\n", HEADER, VERSION);
+
+        if self.default.is_some() || self.gamepad_default.is_some() {
+            text.push_str("[default]\n");
+            write_bindings(&mut text, self.default.as_ref(), self.gamepad_default.as_ref());
+        }
+
+        let mut roms: Vec<_> = self.roms.keys().chain(self.gamepad_roms.keys()).collect();
+        roms.sort();
+        roms.dedup();
+        for hash in roms {
+            text.push_str(&format!("[rom {}]\n", to_hex(hash)));
+            write_bindings(&mut text, self.roms.get(hash), self.gamepad_roms.get(hash));
+        }
+
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, KeyConfigError> {
+        let mut lines = text.lines().enumerate()
+            .map(|(index, line)| (index, line.split('#').next().unwrap_or("").trim()))
+            .filter(|(_, line)| !line.is_empty());
+
+        let (_, header) = lines.next().ok_or(KeyConfigError::InvalidHeader)?;
+        let version = match header.split_whitespace().collect::<Vec<_>>()[..] {
+            [HEADER, version] => version.parse::<u32>().map_err(|_| KeyConfigError::InvalidHeader)?,
+            _ => return Err(KeyConfigError::InvalidHeader),
+        };
+        if version != VERSION {
+            return Err(KeyConfigError::UnsupportedVersion(version));
+        }
+
+        let mut config = KeyConfig::new();
+        // The section as the ROM hash, `None` in the default section
+        let mut section: Option<Option<[u8; 20]>> = None;
+
+        for (index, line) in lines {
+            let invalid = || KeyConfigError::InvalidLine { line: index + 1, text: line.to_string() };
+
+            if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                section = match name.split_whitespace().collect::<Vec<_>>()[..] {
+                    ["default"] => Some(None),
+                    ["rom", hash] => Some(Some(parse_sha1(hash).ok_or_else(invalid)?)),
+                    _ => return Err(invalid()),
+                };
+                continue;
+            }
+
+            let [name, key] = line.split_whitespace().collect::<Vec<_>>()[..] else {
+                return Err(invalid());
+            };
+            let key = u8::from_str_radix(key, 16).ok().filter(|key| *key < 16).ok_or_else(invalid)?;
+            let rom_hash = section.ok_or_else(invalid)?;
+
+            if let Some(button) = button_from_name(name) {
+                let button_map = match rom_hash {
+                    Some(hash) => config.gamepad_roms.entry(hash).or_default(),
+                    None => config.gamepad_default.get_or_insert_with(HashMap::new),
+                };
+                button_map.insert(button, key);
+                continue;
+            }
+
+            let keycode = key_from_name(name).ok_or_else(invalid)?;
+            let key_map = match rom_hash {
+                Some(hash) => config.roms.entry(hash).or_default(),
+                None => config.default.get_or_insert_with(HashMap::new),
+            };
+            key_map.insert(keycode, key);
+        }
+
+        Ok(config)
+    }
+}
+
+/// One binding per line, keys before buttons and in keypad order so the file reads like the keypad
+fn write_bindings(text: &mut String, key_map: Option<&HashMap<KeyCode, u8>>, button_map: Option<&HashMap<PadButton, u8>>) {
+    let keypad_order = |(name, key): &(String, u8)| (KEYPAD_ORDER.iter().position(|ordered| ordered == key), name.clone());
+
+    let mut keys: Vec<(String, u8)> = key_map.into_iter().flatten().map(|(keycode, key)| (key_name(*keycode), *key)).collect();
+    keys.sort_by_key(keypad_order);
+
+    let mut buttons: Vec<(String, u8)> = button_map.into_iter().flatten().map(|(button, key)| (button_name(*button), *key)).collect();
+    buttons.sort_by_key(keypad_order);
+
+    for (name, key) in keys.into_iter().chain(buttons) {
+        text.push_str(&format!("{} {:X}\n", name, key));
+    }
+}
+
+/// Asks for a key for every keypad key in turn
+pub struct Rebinding {
+    /// The ROM the bindings are for, the default layout when not set
+    pub rom_hash: Option<[u8; 20]>,
+    position: usize,
+    bindings: Vec<(KeyCode, u8)>,
+    button_bindings: Vec<(PadButton, u8)>,
+}
+
+impl Rebinding {
+    pub fn new(rom_hash: Option<[u8; 20]>) -> Self {
+        Rebinding {
+            rom_hash,
+            position: 0,
+            bindings: vec![],
+            button_bindings: vec![],
+        }
+    }
+
+    /// The keypad key waiting for a binding, `None` once every key had its turn
+    pub fn current(&self) -> Option<u8> {
+        KEYPAD_ORDER.get(self.position).copied()
+    }
+
+    /// Binds the key to the current keypad key, a key that was already used moves to this one
+    pub fn bind(&mut self, keycode: KeyCode) {
+        let Some(key) = self.current() else {
+            return;
+        };
+
+        self.bindings.retain(|(bound, _)| *bound != keycode);
+        self.bindings.push((keycode, key));
+        self.position += 1;
+    }
+
+    /// Binds a gamepad button to the current keypad key instead of a key
+    pub fn bind_button(&mut self, button: PadButton) {
+        let Some(key) = self.current() else {
+            return;
+        };
+
+        self.button_bindings.retain(|(bound, _)| *bound != button);
+        self.button_bindings.push((button, key));
+        self.position += 1;
+    }
+
+    /// Keeps the current binding of the current keypad key
+    pub fn skip(&mut self) {
+        self.position += 1;
+    }
+
+    pub fn finish(self, config: &mut KeyConfig) {
+        if !self.bindings.is_empty() || self.button_bindings.is_empty() {
+            config.bind(self.rom_hash, &self.bindings);
+        }
+        if !self.button_bindings.is_empty() {
+            config.bind_buttons(self.rom_hash, &self.button_bindings);
+        }
+    }
+}
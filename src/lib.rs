@@ -1,15 +1,39 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::mpsc::Sender;
 
 use wasm_bindgen::prelude::*;
 
-use crate::application::{AppCommand, RuntimeData, start_application};
-use crate::application::AppCommand::{LOAD_ROM, RESET};
+use crate::application::{AppCommand, LaunchOptions, RuntimeData, start_application};
+use crate::application::AppCommand::{ADD_BREAKPOINT, ADD_WATCHPOINT, ADVANCE_FRAME, LOAD_ROM, LOAD_ROM_BYTES, LOAD_STATE, PAUSE, PLAY_MOVIE, REMOVE_BREAKPOINT, REMOVE_WATCHPOINT, RESET, RESET_KEY_BINDINGS, RESUME, SAVE_STATE, SET_AUDIO, SET_EFFECT, SET_EFFECT_PARAM, SET_INSTRUCTIONS_PER_FRAME, SET_PALETTE, SET_PERSISTENCE, SET_SPEED, SET_TIMING, START_REBINDING, START_RECORDING, STEP_INTO, STEP_OUT, STEP_OVER, STOP_PLAYBACK, STOP_RECORDING};
+use crate::audio::{AudioConfig, Waveform};
+use crate::chip8::quirks::{PROFILE_NAMES, QuirkConfig};
+use crate::chip8::debugger::{Breakpoint, Comparison, RegisterCondition, WatchKind, Watchpoint};
+use crate::palette::{parse_palette, THEMES};
+use crate::persistence::{Persistence, PERSISTENCE_NAMES};
+use crate::scheduler::{Speed, Timing};
 use crate::wgpu_runtime::WgpuRuntime;
 
 mod utils;
-mod chip8;
+pub mod chip8;
+pub mod audio;
 mod wgpu_runtime;
-mod application;
+pub mod application;
+pub mod hash;
+pub mod rewind;
+pub mod session;
+pub mod movie;
+mod storage;
+pub mod palette;
+pub mod persistence;
+pub mod postprocess;
+pub mod romdb;
+pub mod keymap;
+pub mod gamepad;
+pub mod disassembler;
+pub mod assembler;
+pub mod headless;
+pub mod scheduler;
 
 #[wasm_bindgen]
 pub struct WasmRuntime {
@@ -19,6 +43,7 @@ pub struct WasmRuntime {
 #[wasm_bindgen]
 pub struct CallBack {
     sender: Sender<AppCommand>,
+    instruction_rate: Arc<AtomicU32>,
 }
 
 #[wasm_bindgen]
@@ -30,13 +55,199 @@ impl CallBack {
     pub fn load_rom(&mut self, id: u8) {
         self.sender.send(LOAD_ROM(id)).unwrap();
     }
+
+    /// Loads a ROM from a `Uint8Array`, `profile` is one of the quirk profile names. Without a
+    /// profile the quirks come from the ROM database.
+    pub fn load_rom_bytes(&mut self, name: String, data: Vec<u8>, profile: Option<String>) {
+        let quirks = match profile {
+            Some(profile) => match QuirkConfig::from_profile_name(&profile) {
+                Some(quirks) => Some(quirks),
+                None => {
+                    log::error!("Unknown quirk profile {}, expected one of {}", profile, PROFILE_NAMES.join(", "));
+                    return;
+                }
+            },
+            None => None,
+        };
+
+        self.sender.send(LOAD_ROM_BYTES { name, data, quirks }).unwrap();
+    }
+
+    pub fn save_state(&mut self, slot: u8) {
+        self.sender.send(SAVE_STATE(slot)).unwrap();
+    }
+
+    pub fn load_state(&mut self, slot: u8) {
+        self.sender.send(LOAD_STATE(slot)).unwrap();
+    }
+
+    pub fn pause(&mut self) {
+        self.sender.send(PAUSE).unwrap();
+    }
+
+    pub fn resume(&mut self) {
+        self.sender.send(RESUME).unwrap();
+    }
+
+    /// Runs a single 60 Hz frame while paused, pauses the machine when it is running
+    pub fn advance_frame(&mut self) {
+        self.sender.send(ADVANCE_FRAME).unwrap();
+    }
+
+    /// Runs at `speed` times the normal speed, 0 runs as fast as possible
+    pub fn set_speed(&mut self, speed: f32) {
+        let speed = if speed == 0.0 { Speed::Uncapped } else { Speed::Scaled(speed) };
+        self.sender.send(SET_SPEED(speed)).unwrap();
+    }
+
+    pub fn set_instructions_per_frame(&mut self, instructions: u32) {
+        self.sender.send(SET_INSTRUCTIONS_PER_FRAME(instructions)).unwrap();
+    }
+
+    /// Picks a theme by name or `RRGGBB` colours like on the command line, for every ROM or only the
+    /// current one. An empty text goes back to the colours from before.
+    pub fn set_palette(&mut self, palette: String, rom_only: bool) {
+        let palette = match palette.trim() {
+            "" => None,
+            text => match parse_palette(text) {
+                Some(palette) => Some(palette),
+                None => {
+                    let themes: Vec<&str> = THEMES.iter().map(|(name, _)| *name).collect();
+                    log::error!("Invalid palette {}, expected colours or one of {}", text, themes.join(", "));
+                    return;
+                }
+            },
+        };
+
+        self.sender.send(SET_PALETTE { palette, rom_only }).unwrap();
+    }
+
+    /// Picks how long pixels stay lit like on the command line, for every ROM or only the current
+    /// one. An empty text goes back to the mode from before.
+    pub fn set_persistence(&mut self, persistence: String, rom_only: bool) {
+        let persistence = match persistence.trim() {
+            "" => None,
+            text => match Persistence::parse(text) {
+                Some(persistence) => Some(persistence),
+                None => {
+                    log::error!("Invalid persistence {}, expected one of {}", text, PERSISTENCE_NAMES.join(", "));
+                    return;
+                }
+            },
+        };
+
+        self.sender.send(SET_PERSISTENCE { persistence, rom_only }).unwrap();
+    }
+
+    /// Turns a post-processing effect like `scanlines` or `bloom` on or off
+    pub fn set_effect(&mut self, effect: String, enabled: bool) {
+        self.sender.send(SET_EFFECT { effect, enabled }).unwrap();
+    }
+
+    /// Changes a parameter of a post-processing effect, like the `intensity` of the `scanlines`
+    pub fn set_effect_param(&mut self, effect: String, param: String, value: f32) {
+        self.sender.send(SET_EFFECT_PARAM { effect, param, value }).unwrap();
+    }
+
+    /// Runs every instruction for as long as it took on a COSMAC VIP instead of at a fixed rate
+    pub fn set_vip_timing(&mut self, enabled: bool) {
+        let timing = if enabled { Timing::CosmacVip } else { Timing::Fixed };
+        self.sender.send(SET_TIMING(timing)).unwrap();
+    }
+
+    /// Instructions that ran in the last second, measured once a second
+    pub fn instructions_per_second(&self) -> u32 {
+        self.instruction_rate.load(Ordering::Relaxed)
+    }
+
+    pub fn step_into(&mut self) {
+        self.sender.send(STEP_INTO).unwrap();
+    }
+
+    pub fn step_over(&mut self) {
+        self.sender.send(STEP_OVER).unwrap();
+    }
+
+    pub fn step_out(&mut self) {
+        self.sender.send(STEP_OUT).unwrap();
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.sender.send(ADD_BREAKPOINT(Breakpoint { address, condition: None })).unwrap();
+    }
+
+    /// Breaks at the address only when `V[register] <comparison> value` holds, for example `"=="`
+    pub fn add_conditional_breakpoint(&mut self, address: u16, register: u8, comparison: &str, value: u8) {
+        let Some(comparison) = Comparison::from_symbol(comparison) else {
+            log::error!("Unknown comparison {}", comparison);
+            return;
+        };
+
+        let condition = RegisterCondition { register, comparison, value };
+        self.sender.send(ADD_BREAKPOINT(Breakpoint { address, condition: Some(condition) })).unwrap();
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) {
+        self.sender.send(REMOVE_BREAKPOINT(address)).unwrap();
+    }
+
+    /// Watches `start` to `end` inclusive, `kind` is `"r"`, `"w"` or `"rw"`
+    pub fn add_watchpoint(&mut self, start: u16, end: u16, kind: &str) {
+        let Some(kind) = WatchKind::from_name(kind) else {
+            log::error!("Unknown watchpoint kind {}", kind);
+            return;
+        };
+
+        self.sender.send(ADD_WATCHPOINT(Watchpoint { start, end, kind })).unwrap();
+    }
+
+    pub fn remove_watchpoint(&mut self, start: u16, end: u16) {
+        self.sender.send(REMOVE_WATCHPOINT(start, end)).unwrap();
+    }
+
+    /// Restarts the current ROM and records the keypad, stopping saves the movie to local storage
+    pub fn start_recording(&mut self) {
+        self.sender.send(START_RECORDING).unwrap();
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.sender.send(STOP_RECORDING).unwrap();
+    }
+
+    /// Plays the contents of a movie file, the ROM it was recorded with is loaded automatically
+    pub fn play_movie(&mut self, movie: String) {
+        self.sender.send(PLAY_MOVIE(movie)).unwrap();
+    }
+
+    pub fn stop_playback(&mut self) {
+        self.sender.send(STOP_PLAYBACK).unwrap();
+    }
+
+    /// `waveform` is `"square"`, `"triangle"`, `"sawtooth"` or `"sine"`, `volume` goes from 0 to 1
+    pub fn set_audio(&mut self, waveform: &str, frequency: f32, volume: f32) {
+        let Some(waveform) = Waveform::from_name(waveform) else {
+            log::error!("Unknown waveform {}", waveform);
+            return;
+        };
+
+        self.sender.send(SET_AUDIO(AudioConfig { waveform, frequency, volume })).unwrap();
+    }
+
+    /// Asks for a key for every keypad key in turn, for the current ROM only or for all ROMs
+    pub fn start_rebinding(&mut self, rom_only: bool) {
+        self.sender.send(START_REBINDING { rom_only }).unwrap();
+    }
+
+    pub fn reset_key_bindings(&mut self, rom_only: bool) {
+        self.sender.send(RESET_KEY_BINDINGS { rom_only }).unwrap();
+    }
 }
 
 #[wasm_bindgen]
 impl WasmRuntime {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
-        let runtime = start_application();
+        let runtime = start_application(LaunchOptions::new());
 
         return WasmRuntime {
             runtime,
@@ -54,8 +265,12 @@ impl WasmRuntime {
     }
 
     pub fn get_sender(&mut self) -> CallBack {
+        let instruction_rate = self.runtime.data.as_ref()
+            .map_or_else(|| Arc::new(AtomicU32::new(0)), |data| data.instruction_rate());
+
         CallBack {
-            sender: self.runtime.get_command_sender()
+            sender: self.runtime.get_command_sender(),
+            instruction_rate,
         }
     }
 
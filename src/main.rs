@@ -1,15 +1,101 @@
-#[macro_use]
-extern crate log;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::{env, fs, process};
 
-use wasm_bindgen::prelude::*;
+use chip8::application::{create_rom_list, DEFAULT_WINDOW_SCALE, LaunchOptions, start_application};
+use chip8::chip8::quirks::{PROFILE_NAMES, QuirkConfig};
+use chip8::palette::{parse_palette, THEMES};
+use chip8::persistence::{Persistence, PERSISTENCE_NAMES};
+use chip8::postprocess::EFFECTS;
+use chip8::scheduler::Timing;
+use chip8::session::DEFAULT_INSTRUCTIONS_PER_SECOND;
 
-use crate::application::start_application;
+fn main() {
+    let mut options = LaunchOptions::new();
+    let mut rom_path = None;
 
-mod utils;
-mod chip8;
-mod wgpu_runtime;
-mod application;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
 
-fn main() {
-    start_application().start();
+        match arg.as_str() {
+            "--rom" => options.rom = find_bundled_rom(&value()).unwrap_or_else(|| usage()),
+            "--list-roms" => {
+                for (id, rom) in create_rom_list().iter().enumerate() {
+                    println!("{:>3}  {}", id, rom.name);
+                }
+                return;
+            }
+            "--profile" => options.quirks = Some(QuirkConfig::from_profile_name(&value()).unwrap_or_else(|| usage())),
+            "--ips" => options.instructions_per_second = Some(value().parse().ok().filter(|ips: &f32| *ips > 0.0).unwrap_or_else(|| usage())),
+            "--vip-timing" => options.timing = Timing::CosmacVip,
+            "--scale" => options.scale = value().parse().ok().filter(|scale: &u32| *scale > 0).unwrap_or_else(|| usage()),
+            "--palette" => options.palette = Some(parse_palette(&value()).unwrap_or_else(|| usage())),
+            "--persistence" => options.persistence = Some(Persistence::parse(&value()).unwrap_or_else(|| usage())),
+            "--effects" => {
+                let text = value();
+                if let Err(error) = options.effects.apply(&text) {
+                    eprintln!("{}", error);
+                    usage();
+                }
+            }
+            "--fullscreen" => options.fullscreen = true,
+            "--paused" => options.paused = true,
+            "--help" | "-h" => help(),
+            _ if rom_path.is_none() && !arg.starts_with('-') => rom_path = Some(PathBuf::from(arg)),
+            _ => usage(),
+        }
+    }
+
+    if let Some(path) = rom_path {
+        let data = fs::read(&path).unwrap_or_else(|error| {
+            eprintln!("{}: {}", path.display(), error);
+            process::exit(1);
+        });
+        let name = path.file_stem().map_or("ROM".to_string(), |stem| stem.to_string_lossy().to_string());
+        options.rom_file = Some((name, data));
+    }
+
+    start_application(options).start();
+}
+
+/// Finds a bundled ROM by its id or its name, ignoring case
+fn find_bundled_rom(text: &str) -> Option<u8> {
+    let roms = create_rom_list();
+
+    if let Ok(id) = text.parse::<u8>() {
+        return Some(id).filter(|id| (*id as usize) < roms.len());
+    }
+
+    roms.iter().position(|rom| rom.name.eq_ignore_ascii_case(text)).map(|id| id as u8)
+}
+
+/// Writes the options, to stdout for `--help` and to stderr after a bad argument
+fn write_usage(out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "Usage: chip8 [rom.ch8] [options]")?;
+    writeln!(out, "  --rom <id|name>     Start a bundled ROM, see --list-roms")?;
+    writeln!(out, "  --list-roms         List the bundled ROMs and exit")?;
+    writeln!(out, "  --profile <name>    Quirk profile: {}, default from the ROM database", PROFILE_NAMES.join(", "))?;
+    writeln!(out, "  --ips <n>           Instructions per second, default from the ROM database or {}", DEFAULT_INSTRUCTIONS_PER_SECOND)?;
+    writeln!(out, "  --vip-timing        Run every instruction for as long as it took on a COSMAC VIP")?;
+    writeln!(out, "  --scale <n>         Window pixels per CHIP-8 pixel, default {}", DEFAULT_WINDOW_SCALE)?;
+    writeln!(out, "  --palette <colors>  A theme: {}", THEMES.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", "))?;
+    writeln!(out, "                      or comma separated RRGGBB colours, background and foreground or all four planes")?;
+    writeln!(out, "  --persistence <m>   How long pixels stay lit: {}, default last-frame", PERSISTENCE_NAMES.join(", "))?;
+    writeln!(out, "                      decay:<ms> sets the half-life, majority:<n> the number of frames")?;
+    writeln!(out, "  --effects <list>    Post-processing effects: crt for all of them or a comma separated list of")?;
+    writeln!(out, "                      {} with optional settings like scanlines:intensity=0.5", EFFECTS.iter().map(|effect| effect.name).collect::<Vec<_>>().join(", "))?;
+    writeln!(out, "  --fullscreen        Start in borderless fullscreen")?;
+    writeln!(out, "  --paused            Start with the debugger paused")?;
+    Ok(())
+}
+
+fn help() -> ! {
+    write_usage(&mut io::stdout()).ok();
+    process::exit(0);
+}
+
+fn usage() -> ! {
+    write_usage(&mut io::stderr()).ok();
+    process::exit(1);
 }
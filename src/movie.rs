@@ -0,0 +1,303 @@
+//! Input recordings that replay a run exactly.
+//!
+//! A movie starts from a freshly reset machine and stores every key change with the number of
+//! instructions executed before it, together with everything else that decides how the run goes:
+//! the ROM, the quirks, the random seed and the speed, which decides between which instructions
+//! the timers tick. Movies are plain text so they can be shared:
+//!
+//! ```text
+//! chip8-movie 2
+//! rom 5f518084744bf3cb8733f6e5454dfd1634320563
+//! quirks 1 2 1 1 1 0 0
+//! seed 000000000000002a
+//! ips 700
+//! timing fixed
+//! input 1520 5 down
+//! input 1711 5 up
+//! end 9004
+//! ```
+//!
+//! The quirks are `vf_reset`, `memory_increment` (0 none, 1 X, 2 X + 1), `display_wait` (0 never,
+//! 1 always, 2 lores only), `sprite_clipping`, `source_vy_bitshift`, `jump_vx_offset` and
+//! `extended_memory`. The timing is `fixed` at the instructions per second or `vip` for the COSMAC
+//! VIP timing. Version 1 movies have no speed and play at the default 700 instructions per second.
+
+use std::fmt;
+
+use crate::chip8::Chip8;
+use crate::chip8::quirks::{DisplayWait, MemoryIncrement, QuirkConfig};
+use crate::hash::{parse_sha1, sha1, to_hex};
+use crate::scheduler::{Scheduler, Timing};
+use crate::session::DEFAULT_INSTRUCTIONS_PER_SECOND;
+
+const HEADER: &str = "chip8-movie";
+const VERSION: u32 = 2;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct InputEvent {
+    /// Instructions executed when the key changed
+    pub cycle: u32,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Movie {
+    pub rom_hash: [u8; 20],
+    pub quirks: QuirkConfig,
+    pub seed: u64,
+    pub instructions_per_second: f64,
+    pub timing: Timing,
+    pub events: Vec<InputEvent>,
+    /// Instructions executed when the recording stopped
+    pub end_cycle: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MovieError {
+    InvalidHeader,
+    UnsupportedVersion(u32),
+    InvalidLine { line: usize, text: String },
+    Missing(&'static str),
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovieError::InvalidHeader => write!(f, "Not a movie file"),
+            MovieError::UnsupportedVersion(version) => write!(f, "Unsupported movie version {}", version),
+            MovieError::InvalidLine { line, text } => write!(f, "Invalid movie line {}: '{}'", line, text),
+            MovieError::Missing(field) => write!(f, "Movie has no {}", field),
+        }
+    }
+}
+
+impl std::error::Error for MovieError {}
+
+impl Movie {
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{} {}\n", HEADER, VERSION);
+        text.push_str(&format!("rom {}\n", to_hex(&self.rom_hash)));
+        text.push_str(&format!("quirks {}\n", write_quirks(&self.quirks)));
+        text.push_str(&format!("seed {:016x}\n", self.seed));
+        text.push_str(&format!("ips {}\n", self.instructions_per_second));
+        text.push_str(&format!("timing {}\n", write_timing(self.timing)));
+
+        for event in &self.events {
+            let state = if event.pressed { "down" } else { "up" };
+            text.push_str(&format!("input {} {:X} {}\n", event.cycle, event.key, state));
+        }
+
+        text.push_str(&format!("end {}\n", self.end_cycle));
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Movie, MovieError> {
+        let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+
+        let (_, header) = lines.next().ok_or(MovieError::InvalidHeader)?;
+        let version = match header.split_whitespace().collect::<Vec<_>>()[..] {
+            [HEADER, version] => version.parse::<u32>().map_err(|_| MovieError::InvalidHeader)?,
+            _ => return Err(MovieError::InvalidHeader),
+        };
+        if version == 0 || version > VERSION {
+            return Err(MovieError::UnsupportedVersion(version));
+        }
+
+        let mut rom_hash = None;
+        let mut quirks = None;
+        let mut seed = None;
+        let mut instructions_per_second = (version == 1).then_some(DEFAULT_INSTRUCTIONS_PER_SECOND as f64);
+        let mut timing = (version == 1).then_some(Timing::Fixed);
+        let mut end_cycle = None;
+        let mut events = vec![];
+
+        for (index, line) in lines {
+            let invalid = || MovieError::InvalidLine { line: index + 1, text: line.to_string() };
+            let parts: Vec<&str> = line.split_whitespace().collect();
+
+            match parts[..] {
+                ["rom", hash] => rom_hash = Some(parse_sha1(hash).ok_or_else(invalid)?),
+                ["quirks", ..] => quirks = Some(read_quirks(&parts[1..]).ok_or_else(invalid)?),
+                ["seed", value] => seed = Some(u64::from_str_radix(value, 16).map_err(|_| invalid())?),
+                ["ips", value] => {
+                    let value = value.parse::<f64>().ok().filter(|value| value.is_finite() && *value > 0.0);
+                    instructions_per_second = Some(value.ok_or_else(invalid)?);
+                }
+                ["timing", name] => timing = Some(read_timing(name).ok_or_else(invalid)?),
+                ["input", cycle, key, state] => {
+                    let cycle = cycle.parse::<u32>().map_err(|_| invalid())?;
+                    let key = u8::from_str_radix(key, 16).ok().filter(|key| *key < 16).ok_or_else(invalid)?;
+                    let pressed = match state {
+                        "down" => true,
+                        "up" => false,
+                        _ => return Err(invalid()),
+                    };
+
+                    if events.last().is_some_and(|last: &InputEvent| last.cycle > cycle) {
+                        return Err(invalid());
+                    }
+                    events.push(InputEvent { cycle, key, pressed });
+                }
+                ["end", cycle] => end_cycle = Some(cycle.parse::<u32>().map_err(|_| invalid())?),
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(Movie {
+            rom_hash: rom_hash.ok_or(MovieError::Missing("ROM hash"))?,
+            quirks: quirks.ok_or(MovieError::Missing("quirks"))?,
+            seed: seed.ok_or(MovieError::Missing("seed"))?,
+            instructions_per_second: instructions_per_second.ok_or(MovieError::Missing("instructions per second"))?,
+            timing: timing.ok_or(MovieError::Missing("timing"))?,
+            events,
+            end_cycle: end_cycle.ok_or(MovieError::Missing("end"))?,
+        })
+    }
+}
+
+/// Records the key changes of a machine that was just reset, at the speed of the scheduler
+pub struct MovieRecorder {
+    rom_hash: [u8; 20],
+    quirks: QuirkConfig,
+    seed: u64,
+    instructions_per_second: f64,
+    timing: Timing,
+    events: Vec<InputEvent>,
+}
+
+impl MovieRecorder {
+    pub fn new(rom: &[u8], chip8: &Chip8, scheduler: &Scheduler) -> Self {
+        MovieRecorder {
+            rom_hash: sha1(rom),
+            quirks: chip8.quirks(),
+            seed: chip8.seed(),
+            instructions_per_second: scheduler.instructions_per_second(),
+            timing: scheduler.timing(),
+            events: vec![],
+        }
+    }
+
+    /// Passes the key change to the machine and records it
+    pub fn set_input(&mut self, chip8: &mut Chip8, key: u8, pressed: bool) {
+        self.events.push(InputEvent { cycle: chip8.total_cycles(), key, pressed });
+        chip8.set_input(key, pressed);
+    }
+
+    pub fn finish(self, chip8: &Chip8) -> Movie {
+        Movie {
+            rom_hash: self.rom_hash,
+            quirks: self.quirks,
+            seed: self.seed,
+            instructions_per_second: self.instructions_per_second,
+            timing: self.timing,
+            events: self.events,
+            end_cycle: chip8.total_cycles(),
+        }
+    }
+}
+
+/// Feeds the recorded key changes back into a machine
+pub struct MoviePlayer {
+    movie: Movie,
+    next_event: usize,
+}
+
+impl MoviePlayer {
+    /// Resets the machine and the scheduler to how the recording started, fails when the ROM isn't
+    /// the recorded one
+    pub fn start(movie: Movie, rom: &[u8], chip8: &mut Chip8, scheduler: &mut Scheduler) -> Option<Self> {
+        if sha1(rom) != movie.rom_hash {
+            return None;
+        }
+
+        *chip8 = Chip8::with_quirks(movie.quirks);
+        chip8.set_rom(&rom.to_vec());
+        chip8.set_seed(movie.seed);
+
+        scheduler.set_instructions_per_second(movie.instructions_per_second);
+        scheduler.set_timing(movie.timing);
+        scheduler.reset();
+
+        Some(MoviePlayer { movie, next_event: 0 })
+    }
+
+    /// Applies the key changes that happened before the next instruction, call before every cycle
+    pub fn apply(&mut self, chip8: &mut Chip8) {
+        while let Some(event) = self.movie.events.get(self.next_event).filter(|event| event.cycle <= chip8.total_cycles()) {
+            chip8.set_input(event.key, event.pressed);
+            self.next_event += 1;
+        }
+    }
+
+    /// True once every key change is applied and the machine got to where the recording stopped
+    pub fn finished(&self, chip8: &Chip8) -> bool {
+        self.next_event >= self.movie.events.len() && chip8.total_cycles() >= self.movie.end_cycle
+    }
+}
+
+fn write_timing(timing: Timing) -> &'static str {
+    match timing {
+        Timing::Fixed => "fixed",
+        Timing::CosmacVip => "vip",
+    }
+}
+
+fn read_timing(name: &str) -> Option<Timing> {
+    match name {
+        "fixed" => Some(Timing::Fixed),
+        "vip" => Some(Timing::CosmacVip),
+        _ => None,
+    }
+}
+
+fn write_quirks(quirks: &QuirkConfig) -> String {
+    let memory_increment = match quirks.memory_increment {
+        MemoryIncrement::None => 0,
+        MemoryIncrement::X => 1,
+        MemoryIncrement::XPlusOne => 2,
+    };
+    let display_wait = match quirks.display_wait {
+        DisplayWait::Never => 0,
+        DisplayWait::Always => 1,
+        DisplayWait::LoresOnly => 2,
+    };
+
+    format!(
+        "{} {} {} {} {} {} {}",
+        quirks.vf_reset as u8, memory_increment, display_wait, quirks.sprite_clipping as u8,
+        quirks.source_vy_bitshift as u8, quirks.jump_vx_offset as u8, quirks.extended_memory as u8
+    )
+}
+
+fn read_quirks(values: &[&str]) -> Option<QuirkConfig> {
+    let flag = |value: &str| match value {
+        "0" => Some(false),
+        "1" => Some(true),
+        _ => None,
+    };
+
+    let [vf_reset, memory_increment, display_wait, sprite_clipping, source_vy_bitshift, jump_vx_offset, extended_memory] = values else {
+        return None;
+    };
+
+    Some(QuirkConfig {
+        vf_reset: flag(vf_reset)?,
+        memory_increment: match *memory_increment {
+            "0" => MemoryIncrement::None,
+            "1" => MemoryIncrement::X,
+            "2" => MemoryIncrement::XPlusOne,
+            _ => return None,
+        },
+        display_wait: match *display_wait {
+            "0" => DisplayWait::Never,
+            "1" => DisplayWait::Always,
+            "2" => DisplayWait::LoresOnly,
+            _ => return None,
+        },
+        sprite_clipping: flag(sprite_clipping)?,
+        source_vy_bitshift: flag(source_vy_bitshift)?,
+        jump_vx_offset: flag(jump_vx_offset)?,
+        extended_memory: flag(extended_memory)?,
+    })
+}
@@ -0,0 +1,247 @@
+//! Display colours for every combination of the two XO-CHIP planes: none, first, second and both.
+//!
+//! Besides the colours of the ROM database there are named themes, and the user's choice of theme
+//! or colours is stored for all ROMs and for single ROMs, found by their hash:
+//!
+//! ```text
+//! chip8-palettes 1
+//! default amber
+//! rom 5f518084744bf3cb8733f6e5454dfd1634320563 102030,F0E0D0
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::hash::{parse_sha1, to_hex};
+
+pub type Palette = [[f32; 4]; 4];
+
+pub const PALETTE_FILE: &str = "palettes.cfg";
+
+const HEADER: &str = "chip8-palettes";
+const VERSION: u32 = 1;
+
+pub const DEFAULT_PALETTE: Palette = [
+    [0.0, 0.0, 0.0, 1.0],
+    [0.427, 0.176, 0.969, 1.0],
+    [0.969, 0.427, 0.176, 1.0],
+    [1.0, 1.0, 1.0, 1.0],
+];
+
+/// Green on black like the phosphor of an old monitor
+pub const GREEN_PHOSPHOR: Palette = [
+    [0.039, 0.078, 0.039, 1.0],
+    [0.2, 1.0, 0.4, 1.0],
+    [0.102, 0.502, 0.2, 1.0],
+    [0.702, 1.0, 0.776, 1.0],
+];
+
+pub const AMBER: Palette = [
+    [0.078, 0.039, 0.0, 1.0],
+    [1.0, 0.69, 0.0, 1.0],
+    [0.502, 0.251, 0.0, 1.0],
+    [1.0, 0.878, 0.627, 1.0],
+];
+
+/// Dark pixels on the grey of a handheld's LCD
+pub const LCD: Palette = [
+    [0.722, 0.753, 0.69, 1.0],
+    [0.188, 0.22, 0.188, 1.0],
+    [0.439, 0.471, 0.439, 1.0],
+    [0.063, 0.078, 0.063, 1.0],
+];
+
+/// Full brightness colours that are far apart, also for the XO-CHIP planes
+pub const HIGH_CONTRAST: Palette = [
+    [0.0, 0.0, 0.0, 1.0],
+    [1.0, 1.0, 1.0, 1.0],
+    [1.0, 1.0, 0.0, 1.0],
+    [0.0, 1.0, 1.0, 1.0],
+];
+
+/// Themes by the name they are picked with, the project's purple first
+pub const THEMES: [(&str, Palette); 5] = [
+    ("purple", DEFAULT_PALETTE),
+    ("green", GREEN_PHOSPHOR),
+    ("amber", AMBER),
+    ("lcd", LCD),
+    ("high-contrast", HIGH_CONTRAST),
+];
+
+/// Finds a theme by its name, ignoring case
+pub fn theme(name: &str) -> Option<Palette> {
+    THEMES.iter().find(|(theme, _)| theme.eq_ignore_ascii_case(name.trim())).map(|(_, palette)| *palette)
+}
+
+/// The name of the theme with exactly these colours
+pub fn theme_name(palette: &Palette) -> Option<&'static str> {
+    THEMES.iter().find(|(_, theme)| theme == palette).map(|(name, _)| *name)
+}
+
+/// Parses an `RRGGBB` colour, with or without a leading `#`
+pub fn parse_color(text: &str) -> Option<[f32; 4]> {
+    let hex = text.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|character| character.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    let channel = |shift: u32| ((value >> shift) & 0xFF) as f32 / 255.0;
+    Some([channel(16), channel(8), channel(0), 1.0])
+}
+
+/// Formats a colour as `RRGGBB`, the way [`parse_color`] reads it
+pub fn color_to_hex(color: [f32; 4]) -> String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("{:02X}{:02X}{:02X}", channel(color[0]), channel(color[1]), channel(color[2]))
+}
+
+/// Either all four plane colours or a background and a foreground colour
+pub fn from_colors(colors: &[[f32; 4]]) -> Option<Palette> {
+    match *colors {
+        [background, foreground] => Some([background, foreground, foreground, foreground]),
+        [none, first, second, both] => Some([none, first, second, both]),
+        _ => None,
+    }
+}
+
+/// Parses a theme name or a comma separated list of colours, see [`from_colors`]
+pub fn parse_palette(text: &str) -> Option<Palette> {
+    if let Some(palette) = theme(text) {
+        return Some(palette);
+    }
+
+    let colors = text.split(',').map(parse_color).collect::<Option<Vec<_>>>()?;
+    from_colors(&colors)
+}
+
+/// The theme name or the colours, in a form [`parse_palette`] reads back
+pub fn palette_to_text(palette: &Palette) -> String {
+    if let Some(name) = theme_name(palette) {
+        return name.to_string();
+    }
+
+    let [background, foreground, ..] = *palette;
+    if *palette == [background, foreground, foreground, foreground] {
+        return format!("{},{}", color_to_hex(background), color_to_hex(foreground));
+    }
+
+    palette.iter().map(|color| color_to_hex(*color)).collect::<Vec<_>>().join(",")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteConfigError {
+    InvalidHeader,
+    UnsupportedVersion(u32),
+    InvalidLine { line: usize, text: String },
+}
+
+impl fmt::Display for PaletteConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaletteConfigError::InvalidHeader => write!(f, "Not a palette file"),
+            PaletteConfigError::UnsupportedVersion(version) => write!(f, "Unsupported palette version {}", version),
+            PaletteConfigError::InvalidLine { line, text } => write!(f, "Invalid palette line {}: '{}'", line, text),
+        }
+    }
+}
+
+impl std::error::Error for PaletteConfigError {}
+
+/// The colours the user picked, which go over the ones of the ROM database
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PaletteConfig {
+    /// Replaces the built-in colours of ROMs the database has no colours for
+    pub default: Option<Palette>,
+    /// Colours for a ROM, by the ROM's hash
+    pub roms: HashMap<[u8; 20], Palette>,
+}
+
+impl PaletteConfig {
+    pub fn new() -> Self {
+        PaletteConfig {
+            default: None,
+            roms: HashMap::new(),
+        }
+    }
+
+    /// The stored colours, an empty config when there are none or they can't be read
+    pub fn load() -> Self {
+        let Some(text) = crate::storage::read_config(PALETTE_FILE) else {
+            return PaletteConfig::new();
+        };
+
+        PaletteConfig::from_text(&text).unwrap_or_else(|error| {
+            log::error!("Ignoring {}: {}", PALETTE_FILE, error);
+            PaletteConfig::new()
+        })
+    }
+
+    pub fn save(&self) {
+        match crate::storage::write_config(PALETTE_FILE, &self.to_text()) {
+            Ok(()) => log::info!("Saved palettes"),
+            Err(error) => log::error!("Failed to save palettes: {}", error),
+        }
+    }
+
+    /// Sets the colours of one ROM or, without a hash, the default. No palette removes the choice.
+    pub fn set(&mut self, rom_hash: Option<[u8; 20]>, palette: Option<Palette>) {
+        match (rom_hash, palette) {
+            (Some(hash), Some(palette)) => {
+                self.roms.insert(hash, palette);
+            }
+            (Some(hash), None) => {
+                self.roms.remove(&hash);
+            }
+            (None, palette) => self.default = palette,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{} {}\n", HEADER, VERSION);
+
+        if let Some(palette) = &self.default {
+            text.push_str(&format!("default {}\n", palette_to_text(palette)));
+        }
+
+        let mut roms: Vec<_> = self.roms.iter().collect();
+        roms.sort_by_key(|(hash, _)| **hash);
+        for (hash, palette) in roms {
+            text.push_str(&format!("rom {} {}\n", to_hex(hash), palette_to_text(palette)));
+        }
+
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, PaletteConfigError> {
+        let mut lines = text.lines().enumerate()
+            .map(|(index, line)| (index, line.split('#').next().unwrap_or("").trim()))
+            .filter(|(_, line)| !line.is_empty());
+
+        let (_, header) = lines.next().ok_or(PaletteConfigError::InvalidHeader)?;
+        let version = match header.split_whitespace().collect::<Vec<_>>()[..] {
+            [HEADER, version] => version.parse::<u32>().map_err(|_| PaletteConfigError::InvalidHeader)?,
+            _ => return Err(PaletteConfigError::InvalidHeader),
+        };
+        if version != VERSION {
+            return Err(PaletteConfigError::UnsupportedVersion(version));
+        }
+
+        let mut config = PaletteConfig::new();
+
+        for (index, line) in lines {
+            let invalid = || PaletteConfigError::InvalidLine { line: index + 1, text: line.to_string() };
+
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["default", palette] => config.default = Some(parse_palette(palette).ok_or_else(invalid)?),
+                ["rom", hash, palette] => {
+                    let hash = parse_sha1(hash).ok_or_else(invalid)?;
+                    config.roms.insert(hash, parse_palette(palette).ok_or_else(invalid)?);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(config)
+    }
+}
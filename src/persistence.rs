@@ -0,0 +1,212 @@
+//! How long a pixel stays lit after the game turns it off.
+//!
+//! CHIP-8 games erase and redraw their sprites every frame, which flickers on a display that
+//! shows each frame as it is. Every pixel keeps the frames it was lit in and an intensity that
+//! fades, the mode picks how the display turns those into brightness. The mode is stored for all
+//! ROMs and for single ROMs, found by their hash:
+//!
+//! ```text
+//! chip8-persistence 1
+//! default decay:50
+//! rom 5f518084744bf3cb8733f6e5454dfd1634320563 majority:3
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::hash::{parse_sha1, to_hex};
+
+pub const PERSISTENCE_FILE: &str = "persistence.cfg";
+
+const HEADER: &str = "chip8-persistence";
+const VERSION: u32 = 1;
+
+pub const DEFAULT_HALF_LIFE: f32 = 50.0;
+pub const DEFAULT_MAJORITY_FRAMES: u32 = 3;
+
+/// The lit frames of a pixel are kept in the bits of a 32 bit number, with the current frame
+pub const MAX_MAJORITY_FRAMES: u32 = 31;
+
+/// Names of the modes, as they are written without their settings
+pub const PERSISTENCE_NAMES: [&str; 4] = ["off", "last-frame", "decay", "majority"];
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum Persistence {
+    /// Every frame is shown as it is
+    Off,
+    /// A pixel that was lit in the last frame stays lit for one more frame
+    #[default]
+    LastFrame,
+    /// A pixel lights up fully and fades like phosphor, halving its brightness every half-life
+    Decay { half_life: f32 },
+    /// A pixel is lit when it was lit in most of the last frames
+    Majority { frames: u32 },
+}
+
+impl Persistence {
+    /// Reads a mode name with an optional setting after a colon: the half-life in milliseconds or
+    /// the number of frames, like `decay:80` or `majority:5`
+    pub fn parse(text: &str) -> Option<Self> {
+        let (name, setting) = match text.trim().split_once(':') {
+            Some((name, setting)) => (name, Some(setting)),
+            None => (text.trim(), None),
+        };
+
+        match (name.to_ascii_lowercase().as_str(), setting) {
+            ("off", None) => Some(Persistence::Off),
+            ("last-frame", None) => Some(Persistence::LastFrame),
+            ("decay", None) => Some(Persistence::Decay { half_life: DEFAULT_HALF_LIFE }),
+            ("decay", Some(half_life)) => {
+                let half_life = half_life.parse::<f32>().ok().filter(|half_life| half_life.is_finite() && *half_life > 0.0)?;
+                Some(Persistence::Decay { half_life })
+            }
+            ("majority", None) => Some(Persistence::Majority { frames: DEFAULT_MAJORITY_FRAMES }),
+            ("majority", Some(frames)) => {
+                let frames = frames.parse::<u32>().ok().filter(|frames| (1..=MAX_MAJORITY_FRAMES).contains(frames))?;
+                Some(Persistence::Majority { frames })
+            }
+            _ => None,
+        }
+    }
+
+    /// How much of a pixel's intensity is left after `elapsed` milliseconds
+    pub fn decay(&self, elapsed: f32) -> f32 {
+        match self {
+            Persistence::Decay { half_life } => 0.5f32.powf(elapsed / half_life),
+            _ => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for Persistence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Persistence::Off => write!(f, "off"),
+            Persistence::LastFrame => write!(f, "last-frame"),
+            Persistence::Decay { half_life } => write!(f, "decay:{}", half_life),
+            Persistence::Majority { frames } => write!(f, "majority:{}", frames),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PersistenceConfigError {
+    InvalidHeader,
+    UnsupportedVersion(u32),
+    InvalidLine { line: usize, text: String },
+}
+
+impl fmt::Display for PersistenceConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PersistenceConfigError::InvalidHeader => write!(f, "Not a persistence file"),
+            PersistenceConfigError::UnsupportedVersion(version) => write!(f, "Unsupported persistence version {}", version),
+            PersistenceConfigError::InvalidLine { line, text } => write!(f, "Invalid persistence line {}: '{}'", line, text),
+        }
+    }
+}
+
+impl std::error::Error for PersistenceConfigError {}
+
+/// The modes the user picked
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PersistenceConfig {
+    /// Replaces the built-in mode for every ROM
+    pub default: Option<Persistence>,
+    /// The mode for a ROM, by the ROM's hash
+    pub roms: HashMap<[u8; 20], Persistence>,
+}
+
+impl PersistenceConfig {
+    pub fn new() -> Self {
+        PersistenceConfig {
+            default: None,
+            roms: HashMap::new(),
+        }
+    }
+
+    /// The stored modes, an empty config when there are none or they can't be read
+    pub fn load() -> Self {
+        let Some(text) = crate::storage::read_config(PERSISTENCE_FILE) else {
+            return PersistenceConfig::new();
+        };
+
+        PersistenceConfig::from_text(&text).unwrap_or_else(|error| {
+            log::error!("Ignoring {}: {}", PERSISTENCE_FILE, error);
+            PersistenceConfig::new()
+        })
+    }
+
+    pub fn save(&self) {
+        match crate::storage::write_config(PERSISTENCE_FILE, &self.to_text()) {
+            Ok(()) => log::info!("Saved persistence"),
+            Err(error) => log::error!("Failed to save persistence: {}", error),
+        }
+    }
+
+    /// The mode picked for the ROM, the one picked for every ROM or the built-in one
+    pub fn get(&self, rom_hash: &[u8; 20]) -> Persistence {
+        self.roms.get(rom_hash).copied().or(self.default).unwrap_or_default()
+    }
+
+    /// Sets the mode of one ROM or, without a hash, the default. No mode removes the choice.
+    pub fn set(&mut self, rom_hash: Option<[u8; 20]>, persistence: Option<Persistence>) {
+        match (rom_hash, persistence) {
+            (Some(hash), Some(persistence)) => {
+                self.roms.insert(hash, persistence);
+            }
+            (Some(hash), None) => {
+                self.roms.remove(&hash);
+            }
+            (None, persistence) => self.default = persistence,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{} {}\n", HEADER, VERSION);
+
+        if let Some(persistence) = &self.default {
+            text.push_str(&format!("default {}\n", persistence));
+        }
+
+        let mut roms: Vec<_> = self.roms.iter().collect();
+        roms.sort_by_key(|(hash, _)| **hash);
+        for (hash, persistence) in roms {
+            text.push_str(&format!("rom {} {}\n", to_hex(hash), persistence));
+        }
+
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, PersistenceConfigError> {
+        let mut lines = text.lines().enumerate()
+            .map(|(index, line)| (index, line.split('#').next().unwrap_or("").trim()))
+            .filter(|(_, line)| !line.is_empty());
+
+        let (_, header) = lines.next().ok_or(PersistenceConfigError::InvalidHeader)?;
+        let version = match header.split_whitespace().collect::<Vec<_>>()[..] {
+            [HEADER, version] => version.parse::<u32>().map_err(|_| PersistenceConfigError::InvalidHeader)?,
+            _ => return Err(PersistenceConfigError::InvalidHeader),
+        };
+        if version != VERSION {
+            return Err(PersistenceConfigError::UnsupportedVersion(version));
+        }
+
+        let mut config = PersistenceConfig::new();
+
+        for (index, line) in lines {
+            let invalid = || PersistenceConfigError::InvalidLine { line: index + 1, text: line.to_string() };
+
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["default", persistence] => config.default = Some(Persistence::parse(persistence).ok_or_else(invalid)?),
+                ["rom", hash, persistence] => {
+                    let hash = parse_sha1(hash).ok_or_else(invalid)?;
+                    config.roms.insert(hash, Persistence::parse(persistence).ok_or_else(invalid)?);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(config)
+    }
+}
@@ -0,0 +1,196 @@
+//! Post-processing effects that make the display look like a CRT.
+//!
+//! Every effect is a WGSL pass in `src/shaders`, appended to `prelude.wgsl`, which reads the
+//! picture of the pass before it. The passes run in the order of [`EFFECTS`], skipping the ones
+//! that are turned off. A new effect only needs its shader and an entry in that list.
+//!
+//! Effects are picked with a comma separated list of names, each with optional parameters:
+//!
+//! ```text
+//! scanlines:intensity=0.5,curvature,vignette:intensity=0.6:size=0.3
+//! ```
+//!
+//! `crt` turns on every effect and `none` turns them all off.
+
+use std::fmt;
+
+/// Parameters that fit the uniform of a pass
+pub const MAX_PARAMS: usize = 8;
+
+/// The code every pass starts with: the bindings, the vertex shader and helpers
+pub const PRELUDE: &str = include_str!("shaders/prelude.wgsl");
+
+pub struct Param {
+    pub name: &'static str,
+    pub default: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+pub struct Effect {
+    pub name: &'static str,
+    /// Fragment shader with an `fs_effect` entry point
+    pub source: &'static str,
+    /// Passed to the shader in this order, see `param` in the prelude
+    pub params: &'static [Param],
+}
+
+/// Every effect in the order the passes run. The glow spreads before the picture is darkened
+/// and the curvature bends the scanlines and the mask with it.
+pub const EFFECTS: [Effect; 5] = [
+    Effect {
+        name: "bloom",
+        source: include_str!("shaders/bloom.wgsl"),
+        params: &[
+            Param { name: "strength", default: 0.4, min: 0.0, max: 2.0 },
+            Param { name: "radius", default: 3.0, min: 0.0, max: 16.0 },
+        ],
+    },
+    Effect {
+        name: "scanlines",
+        source: include_str!("shaders/scanlines.wgsl"),
+        params: &[
+            Param { name: "intensity", default: 0.35, min: 0.0, max: 1.0 },
+            Param { name: "lines", default: 1.0, min: 1.0, max: 4.0 },
+        ],
+    },
+    Effect {
+        name: "mask",
+        source: include_str!("shaders/mask.wgsl"),
+        params: &[
+            Param { name: "intensity", default: 0.2, min: 0.0, max: 1.0 },
+            Param { name: "width", default: 1.0, min: 1.0, max: 8.0 },
+        ],
+    },
+    Effect {
+        name: "curvature",
+        source: include_str!("shaders/curvature.wgsl"),
+        params: &[
+            Param { name: "amount", default: 0.08, min: 0.0, max: 0.5 },
+        ],
+    },
+    Effect {
+        name: "vignette",
+        source: include_str!("shaders/vignette.wgsl"),
+        params: &[
+            Param { name: "intensity", default: 0.4, min: 0.0, max: 1.0 },
+            Param { name: "size", default: 0.4, min: 0.0, max: 1.0 },
+        ],
+    },
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PostProcessError {
+    UnknownEffect(String),
+    UnknownParam { effect: String, param: String },
+    InvalidValue { effect: String, param: String, value: String },
+}
+
+impl fmt::Display for PostProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PostProcessError::UnknownEffect(effect) => {
+                let names: Vec<&str> = EFFECTS.iter().map(|effect| effect.name).collect();
+                write!(f, "Unknown effect {}, expected one of {}", effect, names.join(", "))
+            }
+            PostProcessError::UnknownParam { effect, param } => write!(f, "Effect {} has no parameter {}", effect, param),
+            PostProcessError::InvalidValue { effect, param, value } => write!(f, "Invalid value {} for {} {}", value, effect, param),
+        }
+    }
+}
+
+impl std::error::Error for PostProcessError {}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EffectSettings {
+    pub enabled: bool,
+    pub params: [f32; MAX_PARAMS],
+}
+
+/// Which effects are on and their parameters, by the index of the effect in [`EFFECTS`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostProcessing {
+    effects: Vec<EffectSettings>,
+}
+
+impl Default for PostProcessing {
+    fn default() -> Self {
+        PostProcessing::new()
+    }
+}
+
+impl PostProcessing {
+    /// Every effect off, with its default parameters
+    pub fn new() -> Self {
+        let effects = EFFECTS.iter().map(|effect| {
+            let mut params = [0.0; MAX_PARAMS];
+            for (value, param) in params.iter_mut().zip(effect.params) {
+                *value = param.default;
+            }
+            EffectSettings { enabled: false, params }
+        }).collect();
+
+        PostProcessing { effects }
+    }
+
+    pub fn settings(&self, effect: usize) -> EffectSettings {
+        self.effects[effect]
+    }
+
+    /// Indices of the effects that are on, in the order they run
+    pub fn enabled(&self) -> Vec<usize> {
+        (0..self.effects.len()).filter(|effect| self.effects[*effect].enabled).collect()
+    }
+
+    pub fn set_enabled(&mut self, effect: &str, enabled: bool) -> Result<(), PostProcessError> {
+        let index = find_effect(effect)?;
+        self.effects[index].enabled = enabled;
+        Ok(())
+    }
+
+    /// Sets a parameter, clamped to the range of the parameter
+    pub fn set_param(&mut self, effect: &str, param: &str, value: f32) -> Result<(), PostProcessError> {
+        let index = find_effect(effect)?;
+        let unknown = || PostProcessError::UnknownParam { effect: effect.to_string(), param: param.to_string() };
+        let param_index = EFFECTS[index].params.iter().position(|known| known.name.eq_ignore_ascii_case(param)).ok_or_else(unknown)?;
+
+        if !value.is_finite() {
+            return Err(PostProcessError::InvalidValue { effect: effect.to_string(), param: param.to_string(), value: value.to_string() });
+        }
+
+        let range = &EFFECTS[index].params[param_index];
+        self.effects[index].params[param_index] = value.clamp(range.min, range.max);
+        Ok(())
+    }
+
+    /// Turns on the listed effects and sets their parameters, see the module documentation. The
+    /// effects that aren't listed keep their settings.
+    pub fn apply(&mut self, text: &str) -> Result<(), PostProcessError> {
+        for entry in text.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let mut parts = entry.split(':');
+            let name = parts.next().unwrap_or("");
+
+            match name {
+                "crt" | "none" => {
+                    for settings in self.effects.iter_mut() {
+                        settings.enabled = name == "crt";
+                    }
+                    continue;
+                }
+                _ => self.set_enabled(name, true)?,
+            }
+
+            for setting in parts {
+                let (param, value) = setting.split_once('=').unwrap_or((setting, ""));
+                let invalid = || PostProcessError::InvalidValue { effect: name.to_string(), param: param.to_string(), value: value.to_string() };
+                self.set_param(name, param, value.parse::<f32>().map_err(|_| invalid())?)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn find_effect(name: &str) -> Result<usize, PostProcessError> {
+    EFFECTS.iter().position(|effect| effect.name.eq_ignore_ascii_case(name)).ok_or_else(|| PostProcessError::UnknownEffect(name.to_string()))
+}
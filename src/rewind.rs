@@ -0,0 +1,127 @@
+//! Bounded history of save states for playing the machine backwards.
+//!
+//! Only the newest state is kept in full. Every older state is stored as a delta that turns the
+//! state after it back into the older one: the two states are XORed and the result is run-length
+//! encoded, so the bytes that didn't change between frames take almost no space.
+
+use std::collections::VecDeque;
+
+pub struct RewindBuffer {
+    capacity: usize,
+    latest: Option<Vec<u8>>,
+    deltas: VecDeque<Vec<u8>>,
+}
+
+impl RewindBuffer {
+    /// Buffer holding at most `capacity` states, including the newest one. It always holds at
+    /// least the newest state.
+    pub fn new(capacity: usize) -> Self {
+        RewindBuffer {
+            capacity: capacity.max(1),
+            latest: None,
+            deltas: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, state: Vec<u8>) {
+        if let Some(previous) = self.latest.take() {
+            self.deltas.push_back(encode_delta(&state, &previous));
+
+            while self.deltas.len() >= self.capacity {
+                self.deltas.pop_front();
+            }
+        }
+
+        self.latest = Some(state);
+    }
+
+    /// Steps one state back and returns it, None when there is no older state left
+    pub fn rewind(&mut self) -> Option<&[u8]> {
+        let delta = self.deltas.pop_back()?;
+        let latest = self.latest.as_ref()?;
+
+        self.latest = Some(decode_delta(latest, &delta));
+        self.latest.as_deref()
+    }
+
+    pub fn clear(&mut self) {
+        self.latest = None;
+        self.deltas.clear();
+    }
+}
+
+/// Delta that turns `from` into `to`: the length of `to`, followed by runs of a zero count and a
+/// literal count, each followed by the literal XOR bytes
+fn encode_delta(from: &[u8], to: &[u8]) -> Vec<u8> {
+    let length = from.len().max(to.len());
+    let xor = |index: usize| from.get(index).unwrap_or(&0) ^ to.get(index).unwrap_or(&0);
+
+    let mut delta = Vec::new();
+    write_varint(&mut delta, to.len());
+
+    let mut index = 0;
+    while index < length {
+        let zeros_start = index;
+        while index < length && xor(index) == 0 {
+            index += 1;
+        }
+
+        let literal_start = index;
+        while index < length && xor(index) != 0 {
+            index += 1;
+        }
+
+        write_varint(&mut delta, literal_start - zeros_start);
+        write_varint(&mut delta, index - literal_start);
+        delta.extend((literal_start..index).map(xor));
+    }
+
+    delta
+}
+
+fn decode_delta(from: &[u8], delta: &[u8]) -> Vec<u8> {
+    let mut position = 0;
+    let to_length = read_varint(delta, &mut position);
+
+    let mut to = from.to_vec();
+    to.resize(from.len().max(to_length), 0);
+
+    let mut index = 0;
+    while position < delta.len() {
+        index += read_varint(delta, &mut position);
+        let literals = read_varint(delta, &mut position);
+
+        for &byte in &delta[position..position + literals] {
+            to[index] ^= byte;
+            index += 1;
+        }
+        position += literals;
+    }
+
+    to.truncate(to_length);
+    to
+}
+
+fn write_varint(output: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        output.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+fn read_varint(input: &[u8], position: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+
+    loop {
+        let byte = input[*position];
+        *position += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
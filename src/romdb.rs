@@ -0,0 +1,227 @@
+//! ROM metadata looked up by the SHA-1 hash of the ROM.
+//!
+//! The data uses the `programs.json` format of the community CHIP-8 database
+//! (<https://github.com/chip-8/chip-8-database>): a list of programs, each with its ROM files
+//! keyed by their hash. A copy with the bundled ROMs is compiled in, entries in an optional local
+//! `romdb.json` in the same format take precedence.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+use serde_json::{Map, Value};
+
+use crate::chip8::quirks::{DisplayWait, MemoryIncrement, QuirkConfig};
+use crate::hash::{parse_sha1, sha1};
+use crate::palette::{from_colors, Palette, parse_color};
+use crate::storage;
+
+pub const OVERRIDE_FILE: &str = "romdb.json";
+
+const EMBEDDED_DATABASE: &str = include_str!("roms/database.json");
+
+static DATABASE: OnceLock<RomDatabase> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RomInfo {
+    pub title: String,
+    pub authors: Vec<String>,
+    /// Database id of the platform the ROM was written for, e.g. `originalChip8` or `superchip`
+    pub platform: Option<String>,
+    /// Quirks of the platform with the ROM's own deviations applied, `None` for platforms this
+    /// emulator doesn't support
+    pub quirks: Option<QuirkConfig>,
+    /// Instructions per 60 Hz frame
+    pub tickrate: Option<u32>,
+    /// Keypad keys of the database's named buttons, like `up` or `a`
+    pub keys: Vec<(String, u8)>,
+    pub palette: Option<Palette>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DatabaseError {
+    InvalidJson(String),
+    /// The JSON is valid but doesn't have the expected structure, the text says where
+    InvalidFormat(String),
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseError::InvalidJson(error) => write!(f, "Invalid JSON: {}", error),
+            DatabaseError::InvalidFormat(location) => write!(f, "Unexpected data at {}", location),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+#[derive(Debug, Clone, Default)]
+pub struct RomDatabase {
+    roms: HashMap<[u8; 20], RomInfo>,
+}
+
+impl RomDatabase {
+    pub fn new() -> Self {
+        RomDatabase { roms: HashMap::new() }
+    }
+
+    /// Reads the programs of a `programs.json` file
+    pub fn from_json(json: &str) -> Result<Self, DatabaseError> {
+        let programs: Value = serde_json::from_str(json).map_err(|error| DatabaseError::InvalidJson(error.to_string()))?;
+        let programs = programs.as_array().ok_or_else(|| DatabaseError::InvalidFormat("the top level, expected a list of programs".to_string()))?;
+
+        let mut database = RomDatabase::new();
+        for (index, program) in programs.iter().enumerate() {
+            let program = program.as_object().ok_or_else(|| DatabaseError::InvalidFormat(format!("program {}", index)))?;
+            let title = program.get("title").and_then(Value::as_str).unwrap_or("Unknown");
+            let authors = read_strings(program.get("authors"));
+
+            let Some(roms) = program.get("roms") else {
+                continue;
+            };
+            let roms = roms.as_object().ok_or_else(|| DatabaseError::InvalidFormat(format!("the ROMs of {}", title)))?;
+
+            for (hash, rom) in roms {
+                let invalid = || DatabaseError::InvalidFormat(format!("ROM {} of {}", hash, title));
+                let key = parse_sha1(hash).ok_or_else(invalid)?;
+                let rom = rom.as_object().ok_or_else(invalid)?;
+                database.roms.insert(key, read_rom(title, &authors, rom));
+            }
+        }
+
+        Ok(database)
+    }
+
+    /// The compiled in database with the local override file applied
+    pub fn load() -> Self {
+        let mut database = RomDatabase::from_json(EMBEDDED_DATABASE).expect("Embedded ROM database is invalid");
+
+        if let Some(json) = storage::read(OVERRIDE_FILE) {
+            match RomDatabase::from_json(&json) {
+                Ok(overrides) => {
+                    log::info!("Loaded {} ROMs from {}", overrides.len(), OVERRIDE_FILE);
+                    database.merge(overrides);
+                }
+                Err(error) => log::error!("Ignoring {}: {}", OVERRIDE_FILE, error),
+            }
+        }
+
+        database
+    }
+
+    /// Adds the ROMs of the other database, replacing the ones both have
+    pub fn merge(&mut self, other: RomDatabase) {
+        self.roms.extend(other.roms);
+    }
+
+    pub fn get(&self, rom: &[u8]) -> Option<&RomInfo> {
+        self.roms.get(&sha1(rom))
+    }
+
+    pub fn len(&self) -> usize {
+        self.roms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roms.is_empty()
+    }
+}
+
+/// Looks the ROM up in the database, which is loaded on first use
+pub fn lookup(rom: &[u8]) -> Option<&'static RomInfo> {
+    DATABASE.get_or_init(RomDatabase::load).get(rom)
+}
+
+fn read_rom(title: &str, authors: &[String], rom: &Map<String, Value>) -> RomInfo {
+    let platform = rom.get("platforms")
+        .and_then(Value::as_array)
+        .and_then(|platforms| platforms.first())
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    let quirks = platform.as_deref().and_then(platform_quirks).map(|mut quirks| {
+        let overrides = rom.get("quirkyPlatforms")
+            .and_then(|platforms| platforms.get(platform.as_deref().unwrap_or_default()))
+            .and_then(Value::as_object);
+
+        if let Some(overrides) = overrides {
+            apply_quirks(&mut quirks, overrides);
+        }
+        quirks
+    });
+
+    let keys = rom.get("keys")
+        .and_then(Value::as_object)
+        .map(|keys| keys.iter()
+            .filter_map(|(name, key)| key.as_u64().filter(|key| *key < 16).map(|key| (name.clone(), key as u8)))
+            .collect())
+        .unwrap_or_default();
+
+    let palette = rom.get("colors")
+        .and_then(|colors| colors.get("pixels"))
+        .and_then(Value::as_array)
+        .and_then(|pixels| pixels.iter().map(|color| color.as_str().and_then(parse_color)).collect::<Option<Vec<_>>>())
+        .and_then(|colors| from_colors(&colors));
+
+    let rom_authors = read_strings(rom.get("authors"));
+
+    RomInfo {
+        title: title.to_string(),
+        authors: if rom_authors.is_empty() { authors.to_vec() } else { rom_authors },
+        platform,
+        quirks,
+        tickrate: rom.get("tickrate").and_then(Value::as_u64).map(|tickrate| tickrate as u32),
+        keys,
+        palette,
+    }
+}
+
+/// Quirks of the database platforms this emulator can run
+fn platform_quirks(platform: &str) -> Option<QuirkConfig> {
+    match platform {
+        "originalChip8" | "hybridVIP" => Some(QuirkConfig::cosmac_vip()),
+        "modernChip8" => Some(QuirkConfig { vf_reset: false, display_wait: DisplayWait::Never, ..QuirkConfig::cosmac_vip() }),
+        "chip48" => Some(QuirkConfig::chip48()),
+        "superchip1" => Some(QuirkConfig::schip_legacy()),
+        "superchip" => Some(QuirkConfig::schip_modern()),
+        "xochip" => Some(QuirkConfig::xo_chip()),
+        _ => None,
+    }
+}
+
+/// Applies the quirk switches of the database, which are named after the deviation from the
+/// original interpreter
+fn apply_quirks(quirks: &mut QuirkConfig, overrides: &Map<String, Value>) {
+    for (name, value) in overrides {
+        let Some(enabled) = value.as_bool() else {
+            continue;
+        };
+
+        match name.as_str() {
+            "shift" => quirks.source_vy_bitshift = !enabled,
+            "memoryIncrementByX" => set_memory_increment(quirks, MemoryIncrement::X, enabled),
+            "memoryLeaveIUnchanged" => set_memory_increment(quirks, MemoryIncrement::None, enabled),
+            "wrap" => quirks.sprite_clipping = !enabled,
+            "jump" => quirks.jump_vx_offset = enabled,
+            "vblank" => quirks.display_wait = if enabled { DisplayWait::Always } else { DisplayWait::Never },
+            "logic" => quirks.vf_reset = enabled,
+            _ => {}
+        }
+    }
+}
+
+/// Switches to the increment, or back to X + 1 when it is turned off
+fn set_memory_increment(quirks: &mut QuirkConfig, increment: MemoryIncrement, enabled: bool) {
+    if enabled {
+        quirks.memory_increment = increment;
+    } else if quirks.memory_increment == increment {
+        quirks.memory_increment = MemoryIncrement::XPlusOne;
+    }
+}
+
+fn read_strings(value: Option<&Value>) -> Vec<String> {
+    value.and_then(Value::as_array)
+        .map(|values| values.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,274 @@
+//! Runs the instructions and the 60 Hz timers on one emulated clock.
+//!
+//! The scheduler keeps the emulated time in seconds and hands out the instructions and timer ticks
+//! in the order they are due, so the timers tick between the right instructions no matter how
+//! irregular the host updates are. Event times are computed from counters instead of adding up
+//! intervals, which keeps the clock from drifting. After a stall, like a hidden window, only a
+//! short stretch of the missed time is caught up.
+//!
+//! With the COSMAC VIP timing the instructions don't run at a fixed rate. Every instruction takes
+//! the machine cycles it cost on the VIP and every frame is a 60 Hz slice of its 1.76 MHz clock,
+//! which starts with the cycles the display interrupt takes.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::chip8::timing::{FETCH_CYCLES, INTERRUPT_CYCLES, InstructionTiming, MACHINE_CYCLES_PER_SECOND};
+
+pub const TIMER_FREQUENCY: f64 = 60.0;
+
+/// Most emulated time that is caught up after the host fell behind
+pub const MAX_CATCH_UP: Duration = Duration::from_millis(250);
+
+/// How long the instruction rate is measured before the measurement is updated
+const MEASURE_PERIOD: Duration = Duration::from_secs(1);
+
+/// How fast the emulated time runs compared to real time
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Speed {
+    /// 2.0 runs twice as fast as the real machine, 0.5 half as fast
+    Scaled(f32),
+    /// As fast as the host can run, limited by the time the application gives it
+    Uncapped,
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Speed::Scaled(scale) => write!(f, "{}x", scale),
+            Speed::Uncapped => write!(f, "uncapped"),
+        }
+    }
+}
+
+/// How long an instruction takes
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Timing {
+    /// Every instruction takes the same time, at the set instructions per second
+    Fixed,
+    /// Every instruction takes the machine cycles it cost on a COSMAC VIP, see `Scheduler::spend`
+    CosmacVip,
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Timing::Fixed => write!(f, "fixed"),
+            Timing::CosmacVip => write!(f, "COSMAC VIP"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Tick {
+    Instruction,
+    /// The 60 Hz tick of the timers, which is also the vertical blank
+    Timer,
+}
+
+pub struct Scheduler {
+    instructions_per_second: f64,
+    speed: Speed,
+    /// Emulated time of the last tick handed out
+    time: f64,
+    /// Emulated time the machine should have run up to
+    target: f64,
+    /// Time of the last rate change, instructions are counted from there
+    instruction_base: f64,
+    instructions: u64,
+    timer_ticks: u64,
+    timing: Timing,
+    /// VIP machine cycles since the reset, the next instruction starts at this cycle
+    machine_cycles: f64,
+    /// The cost of a fetched instruction that waits for the display interrupt
+    waiting: Option<u32>,
+}
+
+impl Scheduler {
+    pub fn new(instructions_per_second: f64) -> Self {
+        Scheduler {
+            instructions_per_second,
+            speed: Speed::Scaled(1.0),
+            time: 0.0,
+            target: 0.0,
+            instruction_base: 0.0,
+            instructions: 0,
+            timer_ticks: 0,
+            timing: Timing::Fixed,
+            machine_cycles: 0.0,
+            waiting: None,
+        }
+    }
+
+    /// Starts the clock over, for a machine that was just reset
+    pub fn reset(&mut self) {
+        *self = Scheduler {
+            speed: self.speed,
+            timing: self.timing,
+            ..Scheduler::new(self.instructions_per_second)
+        };
+    }
+
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// Switches the timing from the last tick on
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.instruction_base = self.time;
+        self.instructions = 0;
+        self.machine_cycles = self.time * MACHINE_CYCLES_PER_SECOND;
+        self.waiting = None;
+        self.timing = timing;
+    }
+
+    pub fn instructions_per_second(&self) -> f64 {
+        self.instructions_per_second
+    }
+
+    /// Changes the rate from the last instruction on, the instructions before keep their times
+    pub fn set_instructions_per_second(&mut self, instructions_per_second: f64) {
+        self.instruction_base = self.last_instruction_time();
+        self.instructions = 0;
+        self.instructions_per_second = instructions_per_second;
+    }
+
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: Speed) {
+        self.speed = speed;
+    }
+
+    /// Emulated seconds since the reset
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Makes the emulated time for `elapsed` real time due. Uncapped makes the longest catch up
+    /// due, the application stops taking ticks when its time is up.
+    pub fn advance(&mut self, elapsed: Duration) {
+        let elapsed = match self.speed {
+            Speed::Scaled(scale) => elapsed.as_secs_f64() * scale as f64,
+            Speed::Uncapped => MAX_CATCH_UP.as_secs_f64(),
+        };
+
+        self.target = (self.target + elapsed).min(self.time + MAX_CATCH_UP.as_secs_f64());
+    }
+
+    /// Makes everything up to and including the next timer tick due
+    pub fn advance_frame(&mut self) {
+        self.target = self.next_timer_time();
+    }
+
+    /// Drops the time that is due, for a machine that is paused or stopped
+    pub fn skip(&mut self) {
+        self.target = self.time;
+    }
+
+    /// The next tick that is due, a timer tick goes after an instruction due at the same time so
+    /// the tick ends the frame
+    pub fn next_tick(&mut self) -> Option<Tick> {
+        let timer = self.next_timer_time();
+        let instruction = self.next_instruction_time();
+
+        if instruction <= timer && instruction <= self.target {
+            self.time = instruction;
+            self.instructions += 1;
+            self.machine_cycles += FETCH_CYCLES as f64;
+            Some(Tick::Instruction)
+        } else if timer <= self.target {
+            self.time = timer;
+            self.timer_ticks += 1;
+            self.interrupt();
+            Some(Tick::Timer)
+        } else {
+            None
+        }
+    }
+
+    /// Charges the instruction that was just handed out with its VIP cost, only counts with the
+    /// COSMAC VIP timing. An instruction that waits for the display interrupt pays after it.
+    pub fn spend(&mut self, timing: InstructionTiming) {
+        if self.timing != Timing::CosmacVip {
+            return;
+        }
+
+        if timing.waits_for_interrupt {
+            self.waiting = Some(timing.cycles);
+        } else {
+            self.machine_cycles += timing.cycles as f64;
+        }
+    }
+
+    /// The frame starts with the display interrupt, an instruction that still runs at the start of
+    /// the frame finishes first
+    fn interrupt(&mut self) {
+        let frame_start = self.time * MACHINE_CYCLES_PER_SECOND;
+        let waited = self.waiting.take().unwrap_or(0);
+        self.machine_cycles = self.machine_cycles.max(frame_start) + (INTERRUPT_CYCLES + waited) as f64;
+    }
+
+    fn next_timer_time(&self) -> f64 {
+        (self.timer_ticks + 1) as f64 / TIMER_FREQUENCY
+    }
+
+    fn next_instruction_time(&self) -> f64 {
+        match self.timing {
+            Timing::Fixed => self.instruction_base + (self.instructions + 1) as f64 / self.instructions_per_second,
+            Timing::CosmacVip if self.waiting.is_some() => f64::INFINITY,
+            Timing::CosmacVip => self.machine_cycles / MACHINE_CYCLES_PER_SECOND,
+        }
+    }
+
+    fn last_instruction_time(&self) -> f64 {
+        match self.timing {
+            Timing::Fixed => self.instruction_base + self.instructions as f64 / self.instructions_per_second,
+            Timing::CosmacVip => self.time,
+        }
+    }
+}
+
+/// Measures how many instructions actually run per second of real time
+pub struct InstructionMeter {
+    elapsed: Duration,
+    instructions: u64,
+    measured: f64,
+}
+
+impl Default for InstructionMeter {
+    fn default() -> Self {
+        InstructionMeter::new()
+    }
+}
+
+impl InstructionMeter {
+    pub fn new() -> Self {
+        InstructionMeter {
+            elapsed: Duration::ZERO,
+            instructions: 0,
+            measured: 0.0,
+        }
+    }
+
+    /// Adds the instructions that ran in `elapsed`, true when the measurement was updated
+    pub fn record(&mut self, elapsed: Duration, instructions: u64) -> bool {
+        self.elapsed += elapsed;
+        self.instructions += instructions;
+
+        if self.elapsed < MEASURE_PERIOD {
+            return false;
+        }
+
+        self.measured = self.instructions as f64 / self.elapsed.as_secs_f64();
+        self.elapsed = Duration::ZERO;
+        self.instructions = 0;
+        true
+    }
+
+    /// Instructions per second over the last measured period
+    pub fn instructions_per_second(&self) -> f64 {
+        self.measured
+    }
+}
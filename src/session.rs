@@ -0,0 +1,158 @@
+//! The machine that is running, the ROMs it can switch to and the states it can jump back to.
+//!
+//! Everything here works without a window, the windowed runtime adds the display, keys and
+//! colours on top. Quick-save slots remember the ROM they were saved with, and the rewind history
+//! holds one state per timer tick of the current run.
+
+use crate::chip8::{Chip8, Chip8Error, Chip8Rom};
+use crate::rewind::RewindBuffer;
+use crate::scheduler::Scheduler;
+
+pub const DEFAULT_INSTRUCTIONS_PER_SECOND: f32 = 700.0;
+
+pub const SAVE_SLOTS: usize = 4;
+
+// One state is kept per timer tick for 30 seconds
+const REWIND_STATES: usize = 30 * 60;
+
+struct SaveSlot {
+    rom: u8,
+    state: Vec<u8>,
+}
+
+/// The machine with its ROMs, save slots and rewind history. Every jump that isn't a timer tick
+/// starts the history over, so rewinding never mixes states of two runs.
+pub struct Session {
+    pub chip8: Chip8,
+    pub scheduler: Scheduler,
+    pub(crate) roms: Vec<Chip8Rom>,
+    pub(crate) current_rom: u8,
+    pub(crate) fault: Option<Chip8Error>,
+    pub(crate) exited: bool,
+    /// The speed for every ROM, set on launch or picked while running
+    pub(crate) instructions_per_second: Option<f32>,
+    save_slots: Vec<Option<SaveSlot>>,
+    rewind: RewindBuffer,
+}
+
+impl Session {
+    /// Creates the machine for the ROM without starting it, see `reset`
+    pub fn new(roms: Vec<Chip8Rom>, current_rom: u8, instructions_per_second: Option<f32>) -> Self {
+        let current_rom = if (current_rom as usize) < roms.len() { current_rom } else { 0 };
+
+        Session {
+            chip8: roms[current_rom as usize].to_device(),
+            scheduler: Scheduler::new(DEFAULT_INSTRUCTIONS_PER_SECOND as f64),
+            roms,
+            current_rom,
+            fault: None,
+            exited: false,
+            instructions_per_second,
+            save_slots: (0..SAVE_SLOTS).map(|_| None).collect(),
+            rewind: RewindBuffer::new(REWIND_STATES),
+        }
+    }
+
+    pub fn current_rom(&self) -> u8 {
+        self.current_rom
+    }
+
+    pub fn rom(&self) -> &Chip8Rom {
+        &self.roms[self.current_rom as usize]
+    }
+
+    /// Starts the current ROM from the beginning
+    pub fn reset(&mut self) {
+        self.chip8 = self.rom().to_device();
+        log::info!("Started {} with seed {:016X}", self.rom().name, self.chip8.seed());
+        self.apply_speed();
+        self.scheduler.reset();
+        self.fault = None;
+        self.exited = false;
+        self.rewind.clear();
+    }
+
+    pub fn set_rom(&mut self, id: u8) {
+        self.current_rom = id;
+        self.reset();
+    }
+
+    /// The speed set for every ROM, then the ROM database's tick rate
+    fn apply_speed(&mut self) {
+        let tick_rate = self.rom().info().and_then(|info| info.tickrate).map(|tickrate| tickrate as f32 * 60.0);
+        let instructions_per_second = self.instructions_per_second.or(tick_rate).unwrap_or(DEFAULT_INSTRUCTIONS_PER_SECOND);
+        self.scheduler.set_instructions_per_second(instructions_per_second as f64);
+    }
+
+    /// Changes the speed for every ROM
+    pub fn set_instructions_per_second(&mut self, instructions_per_second: f32) {
+        self.instructions_per_second = Some(instructions_per_second);
+        self.scheduler.set_instructions_per_second(instructions_per_second as f64);
+    }
+
+    pub fn save_state(&mut self, slot: u8) {
+        let Some(save_slot) = self.save_slots.get_mut(slot as usize) else {
+            log::warn!("No save slot {}", slot + 1);
+            return;
+        };
+
+        *save_slot = Some(SaveSlot {
+            rom: self.current_rom,
+            state: self.chip8.save_state(),
+        });
+        log::info!("Saved state to slot {}", slot + 1);
+    }
+
+    /// Restores the slot and switches to the ROM it was saved with, returns whether it was loaded
+    pub fn load_state(&mut self, slot: u8) -> bool {
+        let Some(Some(SaveSlot { rom, state })) = self.save_slots.get(slot as usize) else {
+            log::warn!("Save slot {} is empty", slot + 1);
+            return false;
+        };
+        let rom = *rom;
+
+        match self.chip8.load_state(state) {
+            Ok(()) => {
+                self.current_rom = rom;
+                self.apply_speed();
+                self.scheduler.reset();
+                self.rewind.clear();
+                self.fault = None;
+                self.exited = false;
+                log::info!("Loaded state from slot {}", slot + 1);
+                true
+            }
+            Err(error) => {
+                log::error!("Failed to load slot {}: {}", slot + 1, error);
+                false
+            }
+        }
+    }
+
+    /// The 60 Hz tick: keeps the state from before it and ticks the timers
+    pub fn timer_tick(&mut self) {
+        self.rewind.push(self.chip8.save_state());
+        self.chip8.update();
+    }
+
+    /// Restores the state from one tick ago, keeps the oldest state once the history runs out.
+    /// Returns whether the machine changed.
+    pub fn step_back(&mut self) -> bool {
+        let Some(state) = self.rewind.rewind() else {
+            return false;
+        };
+
+        match self.chip8.load_state(state) {
+            Ok(()) => {
+                self.fault = None;
+                self.exited = false;
+                true
+            }
+            Err(error) => {
+                log::error!("Failed to rewind: {}", error);
+                self.rewind.clear();
+                false
+            }
+        }
+    }
+}
@@ -1,12 +1,26 @@
 struct DisplayUniform {
-    values: array<vec4<u32>, 512>,
+    values: array<vec4<u32>, 128>,
+    palette: array<vec4<f32>, 4>,
     width: f32,
     height: f32,
+    columns: f32,
+    rows: f32,
+    persistence: u32,
+    decay: f32,
+    frames: u32,
+    reset: u32,
+    // Emulated frames since the last render
+    advance: u32,
 };
 
 @group(0) @binding(0)
 var<uniform> display: DisplayUniform;
 
+// Per pixel: the frames each plane was lit in, the current frame in the lowest bit, and the fading
+// intensity of each plane as float bits
+@group(0) @binding(1)
+var history: texture_2d<u32>;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
 };
@@ -26,27 +40,85 @@ fn vs_main(
     return out;
 }
 
+// History shader, renders the next history from the previous one and the current frame. The history
+// moves on by every emulated frame since the last render, the frames in between are taken to look
+// like the current one. Without a new frame only the current frame is replaced.
+
+fn plane_bit(index: u32, plane: u32) -> u32 {
+	let word = index / u32(32) + plane * u32(256);
+	let bit = index % u32(32);
+	return (display.values[word / u32(4)][word % u32(4)] >> bit) & u32(1);
+}
+
+@fragment
+fn fs_history(in: VertexOutput) -> @location(0) vec4<u32> {
+	let x = u32(in.clip_position.x);
+	let y = u32(in.clip_position.y);
+	if (f32(x) >= display.columns || f32(y) >= display.rows) {
+		return vec4<u32>(u32(0));
+	}
+
+	var previous = textureLoad(history, vec2<i32>(i32(x), i32(y)), 0);
+	if (display.reset != u32(0)) {
+		previous = vec4<u32>(u32(0));
+	}
+
+	let index = y * u32(display.columns) + x;
+	let lit = vec2<u32>(plane_bit(index, u32(0)), plane_bit(index, u32(1)));
+
+	let shift = min(display.advance, u32(31));
+	let fill = max((u32(1) << shift) - u32(1), u32(1));
+	let frames = ((previous.xy << vec2<u32>(shift)) & vec2<u32>(~fill)) | (lit * fill);
+	let intensity = max(vec2<f32>(lit), bitcast<vec2<f32>>(previous.zw) * display.decay);
+	return vec4<u32>(frames, bitcast<vec2<u32>>(intensity));
+}
+
 // Fragment shader
 
+// Counts the set bits, countOneBits has no counterpart in the GLSL of WebGL 2
+fn count_bits(value: u32) -> u32 {
+	var bits = value - ((value >> u32(1)) & u32(0x55555555));
+	bits = (bits & u32(0x33333333)) + ((bits >> u32(2)) & u32(0x33333333));
+	return (((bits + (bits >> u32(4))) & u32(0x0F0F0F0F)) * u32(0x01010101)) >> u32(24);
+}
+
+fn brightness(frames: u32, intensity: u32) -> f32 {
+	switch display.persistence {
+		// Last frame OR
+		case 1u: {
+			return f32((frames & u32(3)) != u32(0));
+		}
+		// Exponential decay
+		case 2u: {
+			return bitcast<f32>(intensity);
+		}
+		// Majority of the last frames
+		case 3u: {
+			let lit = count_bits(frames & ((u32(1) << display.frames) - u32(1)));
+			return f32(lit * u32(2) > display.frames);
+		}
+		default: {
+			return f32(frames & u32(1));
+		}
+	}
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
 	let posX = in.clip_position.x - 0.5;
 	let posY = in.clip_position.y - 0.5;
-	let x = ((posX / display.width ) * 64.0);
-	let y = ((posY / display.height) * 32.0);
-
-	var index : u32 = u32(((floor(y) * 64.0) + floor(x)));
-
-	let d = display.values[index / u32(4)];
-	let i = index % u32(4);
+	let x = ((posX / display.width ) * display.columns);
+	let y = ((posY / display.height) * display.rows);
 
-	var finalColor = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+	let pixel = textureLoad(history, vec2<i32>(i32(floor(x)), i32(floor(y))), 0);
+	let first = brightness(pixel.x, pixel.z);
+	let second = brightness(pixel.y, pixel.w);
 
-    if (d[i] > u32(0) ){
-        finalColor = vec4<f32>(0.427, 0.176, 0.969, 1.0);
-    }else{
-        finalColor = vec4<f32>(0.00, 0.00, 0.00, 1.0);
-    }
+	// Faded pixels blend between the colours of the planes that are still lit
+	let finalColor = display.palette[0] * (1.0 - first) * (1.0 - second)
+		+ display.palette[1] * first * (1.0 - second)
+		+ display.palette[2] * (1.0 - first) * second
+		+ display.palette[3] * first * second;
 
-    return vec4(pow(finalColor.rgb, vec3(2.2)), finalColor.a);;
+    return vec4(pow(finalColor.rgb, vec3(2.2)), finalColor.a);
 }
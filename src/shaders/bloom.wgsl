@@ -0,0 +1,19 @@
+// Bloom: bright pixels glow into their surroundings.
+// 0: strength, 1: radius in output pixels
+
+@fragment
+fn fs_effect(in: VertexOutput) -> @location(0) vec4<f32> {
+	let color = sample(in.uv);
+	let texel = param(1u) / effect.resolution;
+
+	// Two rings of samples around the pixel, the inner ring counts twice
+	var glow = vec3<f32>(0.0);
+	for (var index = 0; index < 12; index++) {
+		let angle = f32(index) * 0.5236;
+		let distance = select(1.0, 0.5, index % 2 == 0);
+		let offset = vec2<f32>(cos(angle), sin(angle)) * texel * distance;
+		glow += sample(in.uv + offset).rgb * (1.5 - distance);
+	}
+
+	return vec4<f32>(color.rgb + glow / 12.0 * param(0u), color.a);
+}
@@ -0,0 +1,13 @@
+// Curvature: bends the picture like the bulging glass of a tube.
+// 0: amount
+
+@fragment
+fn fs_effect(in: VertexOutput) -> @location(0) vec4<f32> {
+	let centered = in.uv * 2.0 - 1.0;
+	let bent = centered * (1.0 + param(0u) * centered.yx * centered.yx);
+	let uv = bent * 0.5 + 0.5;
+
+	// Outside the bent picture is the black border of the tube
+	let inside = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+	return select(vec4<f32>(0.0, 0.0, 0.0, 1.0), sample(uv), inside);
+}
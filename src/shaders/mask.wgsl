@@ -0,0 +1,12 @@
+// RGB mask: the red, green and blue stripes of an aperture grille.
+// 0: intensity, 1: stripe width in output pixels
+
+@fragment
+fn fs_effect(in: VertexOutput) -> @location(0) vec4<f32> {
+	let color = sample(in.uv);
+	let stripe = u32(in.clip_position.x / max(param(1u), 1.0)) % u32(3);
+
+	var mask = vec3<f32>(1.0 - param(0u));
+	mask[stripe] = 1.0;
+	return vec4<f32>(color.rgb * mask, color.a);
+}
@@ -0,0 +1,47 @@
+// Shared by every post-processing pass, the shader of the pass is appended to this. A pass reads
+// the output of the pass before it from `source` and writes its own through `fs_effect`.
+
+struct EffectUniform {
+    params: array<vec4<f32>, 2>,
+    // Size of the output in pixels
+    resolution: vec2<f32>,
+    // Size of the CHIP-8 display in pixels
+    display: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+@group(0) @binding(2)
+var<uniform> effect: EffectUniform;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(model.position, 1.0);
+    out.uv = vec2<f32>(model.position.x * 0.5 + 0.5, 0.5 - model.position.y * 0.5);
+    return out;
+}
+
+// The parameters in the order the effect lists them
+fn param(index: u32) -> f32 {
+	return effect.params[index / u32(4)][index % u32(4)];
+}
+
+fn sample(uv: vec2<f32>) -> vec4<f32> {
+	return textureSampleLevel(source, source_sampler, uv, 0.0);
+}
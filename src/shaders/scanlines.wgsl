@@ -0,0 +1,12 @@
+// Scanlines: dark gaps between the rows of the display.
+// 0: intensity, 1: lines per display pixel
+
+@fragment
+fn fs_effect(in: VertexOutput) -> @location(0) vec4<f32> {
+	let color = sample(in.uv);
+	let line = fract(in.uv.y * effect.display.y * param(1u));
+
+	// Brightest in the middle of a line, darkest between two lines
+	let shade = mix(1.0 - param(0u), 1.0, sin(line * 3.14159));
+	return vec4<f32>(color.rgb * shade, color.a);
+}
@@ -0,0 +1,11 @@
+// Vignette: the picture darkens towards the corners.
+// 0: intensity, 1: size of the bright centre
+
+@fragment
+fn fs_effect(in: VertexOutput) -> @location(0) vec4<f32> {
+	let color = sample(in.uv);
+	let distance = length(in.uv * 2.0 - 1.0) / sqrt(2.0);
+
+	let shade = 1.0 - param(0u) * smoothstep(param(1u), 1.0, distance);
+	return vec4<f32>(color.rgb * shade, color.a);
+}
@@ -0,0 +1,79 @@
+//! Text that outlives the session: files in the working directory on the desktop, local storage
+//! in the browser. Settings go to a `chip8` directory in the user's config directory instead of
+//! the working directory.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+#[cfg(target_arch = "wasm32")]
+const KEY_PREFIX: &str = "chip8/";
+
+pub fn write(name: &str, contents: &str) -> Result<(), String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::fs::write(name, contents).map_err(|error| error.to_string())
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let storage = local_storage().ok_or("Local storage is not available")?;
+        storage.set_item(&format!("{}{}", KEY_PREFIX, name), contents)
+            .map_err(|error| format!("{:?}", error))
+    }
+}
+
+pub fn read(name: &str) -> Option<String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::fs::read_to_string(name).ok()
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        local_storage()?.get_item(&format!("{}{}", KEY_PREFIX, name)).ok()?
+    }
+}
+
+pub fn write_config(name: &str, contents: &str) -> Result<(), String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let directory = config_dir().ok_or("No config directory")?;
+        std::fs::create_dir_all(&directory).map_err(|error| error.to_string())?;
+        std::fs::write(directory.join(name), contents).map_err(|error| error.to_string())
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        write(name, contents)
+    }
+}
+
+pub fn read_config(name: &str) -> Option<String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::fs::read_to_string(config_dir()?.join(name)).ok()
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        read(name)
+    }
+}
+
+/// `%APPDATA%\chip8` on Windows, `~/Library/Application Support/chip8` on macOS and
+/// `$XDG_CONFIG_HOME/chip8` or `~/.config/chip8` elsewhere
+#[cfg(not(target_arch = "wasm32"))]
+fn config_dir() -> Option<PathBuf> {
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library").join("Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from).or_else(|| home().map(|home| home.join(".config")))
+    };
+
+    base.map(|base| base.join("chip8"))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
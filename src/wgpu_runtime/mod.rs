@@ -1,5 +1,7 @@
+use std::path::PathBuf;
 use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
+use std::time::Duration;
 
 use bytemuck::Zeroable;
 use instant::Instant;
@@ -11,12 +13,17 @@ use winit::event_loop::{ControlFlow, EventLoop, EventLoopBuilder};
 use winit::keyboard::KeyCode;
 
 use crate::application::AppCommand;
+use crate::gamepad::{create_source, GamepadEvent, GamepadSource};
 use crate::wgpu_runtime::wgpu_context::WgpuContext;
 use crate::wgpu_runtime::wgpu_math::{Vec2f, Vec2i};
 
 pub mod wgpu_context;
 pub mod wgpu_math;
 
+/// Longest wait between updates when no events come in, so the application keeps running while
+/// the window isn't redrawn
+const UPDATE_INTERVAL: Duration = Duration::from_millis(4);
+
 pub struct RuntimeContext {
     pub gfx: WgpuContext,
 }
@@ -26,17 +33,18 @@ pub struct WgpuRuntime<AppData, RuntimeCommand> {
     context: RuntimeContext,
     event_loop: EventLoop<()>,
     callback: RuntimeCallbackFunctions<AppData, RuntimeCommand>,
-    logic_update_frame: f32,
+    gamepads: Box<dyn GamepadSource>,
     command_sender: Sender<RuntimeCommand>,
     command_receiver: Receiver<RuntimeCommand>,
 }
 
 pub struct RuntimeCallbackFunctions<AppData, RuntimeCommand> {
-    pub init: fn(&mut RuntimeContext) -> AppData,
-    pub update: fn(&mut RuntimeContext, &mut AppData, f32),
+    pub update: fn(&mut RuntimeContext, &mut AppData, Duration),
     pub render: fn(&mut RuntimeContext, &mut AppData, &Texture),
     pub resize: fn(&mut RuntimeContext, &mut AppData, Vec2i),
     pub key_input: fn(&mut RuntimeContext, &mut AppData, KeyCode, bool),
+    pub gamepad_input: fn(&mut RuntimeContext, &mut AppData, GamepadEvent),
+    pub file_dropped: fn(&mut RuntimeContext, &mut AppData, PathBuf),
     pub runtime_command: fn(&mut RuntimeContext, &mut AppData, RuntimeCommand),
 }
 
@@ -44,7 +52,7 @@ impl<AppData: 'static, RuntimeCommand: 'static> WgpuRuntime<AppData, RuntimeComm
     pub fn new(
         title: &str,
         window_size: Vec2i,
-        init_callback: fn(&mut RuntimeContext) -> AppData,
+        init_callback: impl FnOnce(&mut RuntimeContext) -> AppData,
     ) -> Self {
         WgpuRuntime::<AppData, RuntimeCommand>::init_logger();
         let event_loop = EventLoopBuilder::new().build().expect("Failed to create event loop");
@@ -58,13 +66,13 @@ impl<AppData: 'static, RuntimeCommand: 'static> WgpuRuntime<AppData, RuntimeComm
             },
             data: None,
             event_loop,
-            callback: RuntimeCallbackFunctions::new(init_callback),
-            logic_update_frame: 1000.0 / 60.0,
+            callback: RuntimeCallbackFunctions::new(),
+            gamepads: create_source(),
             command_sender: sender,
             command_receiver: receiver,
         };
 
-        runtime.data = Some((runtime.callback.init)(&mut runtime.context));
+        runtime.data = Some(init_callback(&mut runtime.context));
 
         runtime
     }
@@ -120,22 +128,24 @@ impl<AppData: 'static, RuntimeCommand: 'static> WgpuRuntime<AppData, RuntimeComm
                             event.state == Pressed,
                         );
                     }
+                    WindowEvent::DroppedFile(path) => {
+                        (callback.file_dropped)(context, data, path);
+                    }
                     _ => {}
                 }
                 Event::AboutToWait => {
-                    context.gfx.window.request_redraw();
-                }
-                Event::RedrawRequested(_) => {
+                    for event in self.gamepads.poll() {
+                        (callback.gamepad_input)(context, data, event);
+                    }
+
                     let now = Instant::now();
-                    context.gfx.elapsed_frame_time += now.duration_since
-                    (last_update_time).as_millis() as f32;
+                    (callback.update)(context, data, now.duration_since(last_update_time));
                     last_update_time = now;
 
-                    while context.gfx.elapsed_frame_time > self.logic_update_frame {
-                        context.gfx.elapsed_frame_time -= self.logic_update_frame;
-                        (callback.update)(context, data, self.logic_update_frame);
-                    }
-
+                    context.gfx.window.request_redraw();
+                    control_flow.set_wait_timeout(UPDATE_INTERVAL);
+                }
+                Event::RedrawRequested(_) => {
                     let frame = context.gfx.surface
                         .get_current_texture()
                         .expect("Failed to acquire next swap chain texture");
@@ -153,6 +163,14 @@ impl<AppData: 'static, RuntimeCommand: 'static> WgpuRuntime<AppData, RuntimeComm
         self.callback.key_input = callback;
     }
 
+    pub fn on_gamepad_event(&mut self, callback: fn(&mut RuntimeContext, &mut AppData, GamepadEvent)) {
+        self.callback.gamepad_input = callback;
+    }
+
+    pub fn on_file_dropped(&mut self, callback: fn(&mut RuntimeContext, &mut AppData, PathBuf)) {
+        self.callback.file_dropped = callback;
+    }
+
     pub fn on_render(&mut self, callback: fn(&mut RuntimeContext, &mut AppData, &Texture)) {
         self.callback.render = callback;
     }
@@ -161,7 +179,8 @@ impl<AppData: 'static, RuntimeCommand: 'static> WgpuRuntime<AppData, RuntimeComm
         self.callback.resize = callback;
     }
 
-    pub fn on_update(&mut self, callback: fn(&mut RuntimeContext, &mut AppData, f32)) {
+    /// Called after every batch of events with the real time since the last update
+    pub fn on_update(&mut self, callback: fn(&mut RuntimeContext, &mut AppData, Duration)) {
         self.callback.update = callback;
     }
 
@@ -189,13 +208,14 @@ impl<AppData: 'static, RuntimeCommand: 'static> WgpuRuntime<AppData, RuntimeComm
 }
 
 impl<AppData, RuntimeCommand> RuntimeCallbackFunctions<AppData, RuntimeCommand> {
-    pub fn new(init: fn(&mut RuntimeContext) -> AppData) -> Self {
+    pub fn new() -> Self {
         Self {
-            init,
             update: |_, _, _| {},
             render: |_, _, _| {},
             resize: |_, _, _| {},
             key_input: |_, _, _, _| {},
+            gamepad_input: |_, _, _| {},
+            file_dropped: |_, _, _| {},
             runtime_command: |_, _, _| {},
         }
     }
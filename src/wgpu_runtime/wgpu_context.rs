@@ -17,7 +17,6 @@ pub struct WgpuContext {
     pub adapter: Adapter,
     pub queue: Queue,
     pub texture_format: TextureFormat,
-    pub elapsed_frame_time: f32,
     pub index_buffer: Buffer,
     pub vertex_buffer: Buffer,
 }
@@ -114,7 +113,6 @@ impl WgpuContext {
             adapter,
             queue,
             texture_format,
-            elapsed_frame_time: 0.0,
             index_buffer,
             vertex_buffer,
         }
@@ -0,0 +1,27 @@
+//! Checks that disassembled ROMs assemble back into the same bytes.
+
+use std::fs;
+use std::path::PathBuf;
+
+use chip8::assembler::assemble;
+use chip8::disassembler::{disassemble, Syntax};
+
+#[test]
+fn every_bundled_rom_round_trips_through_octo_syntax() {
+    let roms = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src").join("roms");
+    let mut paths: Vec<PathBuf> = ["games", "tests"].iter()
+        .flat_map(|directory| fs::read_dir(roms.join(directory)).unwrap())
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ch8"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    for path in paths {
+        let rom = fs::read(&path).unwrap();
+        let source = disassemble(&rom, Syntax::Octo);
+        let assembly = assemble(&source).unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+
+        assert!(assembly.rom == rom, "{} assembles into different bytes", path.display());
+    }
+}
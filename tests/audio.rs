@@ -0,0 +1,44 @@
+//! Renders the beeper of a small program into samples with the WAV backend.
+
+use chip8::assembler::assemble;
+use chip8::audio::{AudioBackend, AudioConfig};
+use chip8::audio::wav::WavRenderer;
+use chip8::chip8::Chip8Rom;
+use chip8::headless::HeadlessRunner;
+
+const SAMPLE_RATE: u32 = 48000;
+const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE / 60) as usize;
+
+#[test]
+fn beeper_follows_sound_timer() {
+    // Sound the buzzer for 30 frames, then wait forever
+    let assembly = assemble(": main\n v0 := 30\n buzzer := v0\n: loop\n jump loop\n").unwrap();
+    let chip8 = Chip8Rom::new("buzzer", assembly.rom).to_device();
+
+    let mut runner = HeadlessRunner::new(chip8, 12);
+    let mut renderer = WavRenderer::new(AudioConfig::new(), SAMPLE_RATE);
+    for _ in 0..60 {
+        runner.run_frame().unwrap();
+        renderer.frame(runner.chip8.sound_timer() > 0);
+    }
+
+    let frames: Vec<&[i16]> = renderer.samples().chunks(SAMPLES_PER_FRAME).collect();
+    assert_eq!(frames.len(), 60);
+
+    let peak = |frame: &[i16]| frame.iter().map(|sample| sample.unsigned_abs()).max().unwrap();
+    assert!(frames[1..28].iter().all(|frame| peak(frame) > i16::MAX as u16 / 5));
+    assert!(frames[32..].iter().all(|frame| peak(frame) == 0));
+}
+
+#[test]
+fn wav_header() {
+    let mut renderer = WavRenderer::new(AudioConfig::new(), 44100);
+    renderer.frame(true);
+
+    let wav = renderer.to_wav();
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), 44100);
+    assert_eq!(u32::from_le_bytes(wav[40..44].try_into().unwrap()), 735 * 2);
+    assert_eq!(wav.len(), 44 + 735 * 2);
+}
@@ -0,0 +1,47 @@
+//! Checks the arguments of the `chip8` binary that are handled before a window opens.
+
+use std::process::{Command, Output};
+
+fn chip8(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_chip8")).args(args).output().unwrap()
+}
+
+#[test]
+fn help_goes_to_stdout() {
+    for flag in ["--help", "-h"] {
+        let output = chip8(&[flag]);
+
+        assert_eq!(output.status.code(), Some(0));
+        assert!(String::from_utf8_lossy(&output.stdout).starts_with("Usage: chip8"));
+        assert!(String::from_utf8_lossy(&output.stdout).contains("--list-roms"));
+        assert!(output.stderr.is_empty());
+    }
+}
+
+#[test]
+fn bad_arguments_print_the_usage_to_stderr() {
+    for args in [&["--scale", "0"][..], &["--ips"], &["--profile", "chip-9"], &["--rom", "no such rom"], &["a.ch8", "b.ch8"], &["--unknown"]] {
+        let output = chip8(args);
+
+        assert_eq!(output.status.code(), Some(1), "{:?}", args);
+        assert!(output.stdout.is_empty(), "{:?}", args);
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("Usage: chip8"), "{:?}", args);
+    }
+}
+
+#[test]
+fn lists_the_bundled_roms() {
+    let output = chip8(&["--list-roms"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(stdout.lines().next(), Some("  0  Test: IBM Logo"));
+}
+
+#[test]
+fn reports_a_missing_rom_file() {
+    let output = chip8(&["missing.ch8"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("missing.ch8"));
+}
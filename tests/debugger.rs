@@ -0,0 +1,217 @@
+//! Checks breakpoints, watchpoints and stepping on small assembled programs.
+
+use chip8::assembler::{assemble, Assembly};
+use chip8::chip8::{Chip8, Chip8Rom, CycleOutcome};
+use chip8::chip8::debugger::{AccessKind, Breakpoint, Comparison, DebugEvent, DebugOutcome, Debugger, RegisterCondition, WatchKind, Watchpoint};
+
+const COUNTER: &str = "
+: main
+  v0 := 0
+: count
+  v0 += 1
+  jump count
+";
+
+// Every instruction that touches memory has a label and starts from the data again, save and load
+// move the index past it
+const MEMORY: &str = "
+: main
+  v0 := 123
+  i := data
+: store
+  save v2
+  i := data
+: restore
+  load v1
+  i := data
+: digits
+  bcd v0
+  i := data
+: draw
+  sprite v0 v0 3
+: end
+  jump end
+: data
+  0 0 0
+";
+
+const SUBROUTINE: &str = "
+: main
+  add
+: after
+  v1 := 1
+: end
+  jump end
+: add
+  v0 := 1
+  v0 += 1
+  return
+";
+
+fn machine(source: &str) -> (Chip8, Assembly) {
+    let assembly = assemble(source).unwrap();
+    let chip8 = Chip8Rom::new("debug", assembly.rom.clone()).to_device();
+    (chip8, assembly)
+}
+
+fn label(assembly: &Assembly, name: &str) -> u16 {
+    assembly.symbols.iter().find(|(symbol, _)| symbol == name).unwrap().1
+}
+
+fn run_until_stopped(debugger: &mut Debugger, chip8: &mut Chip8) -> DebugEvent {
+    for _ in 0..1000 {
+        match debugger.cycle(chip8).unwrap() {
+            DebugOutcome::Stopped(event) => return event,
+            DebugOutcome::Cycle(CycleOutcome::BlockedOnDraw) => chip8.update(),
+            DebugOutcome::Cycle(_) => {}
+            DebugOutcome::Paused => panic!("Paused without stopping"),
+        }
+    }
+    panic!("Never stopped");
+}
+
+fn runs_without_stopping(debugger: &mut Debugger, chip8: &mut Chip8) -> bool {
+    (0..100).all(|_| matches!(debugger.cycle(chip8), Ok(DebugOutcome::Cycle(_))))
+}
+
+#[test]
+fn breakpoint_stops_before_the_instruction() {
+    let (mut chip8, assembly) = machine(COUNTER);
+    let count = label(&assembly, "count");
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(Breakpoint { address: count, condition: None });
+
+    assert_eq!(run_until_stopped(&mut debugger, &mut chip8), DebugEvent::Breakpoint { address: count });
+    assert_eq!(chip8.program_counter(), count);
+    assert_eq!(chip8.registers()[0], 0);
+    assert!(debugger.is_paused());
+    assert_eq!(debugger.cycle(&mut chip8), Ok(DebugOutcome::Paused));
+}
+
+#[test]
+fn resuming_on_a_breakpoint_runs_the_instruction() {
+    let (mut chip8, assembly) = machine(COUNTER);
+    let count = label(&assembly, "count");
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(Breakpoint { address: count, condition: None });
+    run_until_stopped(&mut debugger, &mut chip8);
+
+    debugger.resume(&chip8);
+    assert_eq!(debugger.cycle(&mut chip8), Ok(DebugOutcome::Cycle(CycleOutcome::Executed)));
+    assert_eq!(chip8.registers()[0], 1);
+
+    // The next time around the loop stops again
+    assert_eq!(run_until_stopped(&mut debugger, &mut chip8), DebugEvent::Breakpoint { address: count });
+    assert_eq!(chip8.registers()[0], 1);
+}
+
+#[test]
+fn conditional_breakpoint_waits_for_the_register() {
+    let (mut chip8, assembly) = machine(COUNTER);
+    let count = label(&assembly, "count");
+    let mut debugger = Debugger::new();
+    let condition = RegisterCondition { register: 0, comparison: Comparison::Equal, value: 5 };
+    debugger.add_breakpoint(Breakpoint { address: count, condition: Some(condition) });
+
+    assert_eq!(run_until_stopped(&mut debugger, &mut chip8), DebugEvent::Breakpoint { address: count });
+    assert_eq!(chip8.registers()[0], 5);
+
+    // Replacing the breakpoint changes the condition
+    let condition = RegisterCondition { register: 0, comparison: Comparison::GreaterOrEqual, value: 9 };
+    debugger.add_breakpoint(Breakpoint { address: count, condition: Some(condition) });
+    debugger.resume(&chip8);
+    run_until_stopped(&mut debugger, &mut chip8);
+    assert_eq!(chip8.registers()[0], 9);
+}
+
+#[test]
+fn write_watchpoint_stops_on_save_and_bcd() {
+    let (mut chip8, assembly) = machine(MEMORY);
+    let last = label(&assembly, "data") + 2;
+    let watchpoint = Watchpoint { start: last, end: last, kind: WatchKind::Write };
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(watchpoint);
+
+    let event = run_until_stopped(&mut debugger, &mut chip8);
+    let DebugEvent::Watchpoint { address: stopped, access, .. } = event else {
+        panic!("Expected a watchpoint, got {:?}", event);
+    };
+    assert_eq!(stopped, label(&assembly, "store"));
+    assert_eq!(access.kind, AccessKind::Write);
+    assert_eq!((access.start, access.end), (last as usize - 2, last as usize + 1));
+
+    debugger.resume(&chip8);
+    let event = run_until_stopped(&mut debugger, &mut chip8);
+    assert!(matches!(event, DebugEvent::Watchpoint { address, .. } if address == label(&assembly, "digits")));
+
+    debugger.resume(&chip8);
+    assert!(runs_without_stopping(&mut debugger, &mut chip8));
+}
+
+#[test]
+fn read_watchpoint_stops_on_load_and_sprite() {
+    let (mut chip8, assembly) = machine(MEMORY);
+    let second = label(&assembly, "data") + 1;
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint { start: second, end: second, kind: WatchKind::Read });
+
+    let event = run_until_stopped(&mut debugger, &mut chip8);
+    assert!(matches!(event, DebugEvent::Watchpoint { address, .. } if address == label(&assembly, "restore")));
+    // The data was saved, not read yet
+    assert_eq!(chip8.registers()[1], 0);
+
+    debugger.resume(&chip8);
+    let event = run_until_stopped(&mut debugger, &mut chip8);
+    assert!(matches!(event, DebugEvent::Watchpoint { address, .. } if address == label(&assembly, "draw")));
+
+    debugger.resume(&chip8);
+    assert!(runs_without_stopping(&mut debugger, &mut chip8));
+}
+
+#[test]
+fn removed_watchpoint_no_longer_stops() {
+    let (mut chip8, assembly) = machine(MEMORY);
+    let data = label(&assembly, "data");
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint { start: data, end: data + 2, kind: WatchKind::ReadWrite });
+    debugger.remove_watchpoint(data, data + 2);
+
+    assert!(runs_without_stopping(&mut debugger, &mut chip8));
+}
+
+#[test]
+fn step_over_runs_the_whole_subroutine() {
+    let (mut chip8, assembly) = machine(SUBROUTINE);
+    let mut debugger = Debugger::new();
+    debugger.pause();
+
+    debugger.step_over(&chip8);
+    assert_eq!(run_until_stopped(&mut debugger, &mut chip8), DebugEvent::StepComplete { address: label(&assembly, "after") });
+    assert_eq!(chip8.registers()[0], 2);
+    assert!(chip8.stack().is_empty());
+}
+
+#[test]
+fn step_into_enters_the_subroutine() {
+    let (mut chip8, assembly) = machine(SUBROUTINE);
+    let mut debugger = Debugger::new();
+    debugger.pause();
+
+    debugger.step_into(&chip8);
+    assert_eq!(run_until_stopped(&mut debugger, &mut chip8), DebugEvent::StepComplete { address: label(&assembly, "add") });
+    assert_eq!(chip8.stack().len(), 1);
+}
+
+#[test]
+fn step_out_returns_to_the_caller() {
+    let (mut chip8, assembly) = machine(SUBROUTINE);
+    let add = label(&assembly, "add");
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(Breakpoint { address: add, condition: None });
+    run_until_stopped(&mut debugger, &mut chip8);
+
+    debugger.step_out(&chip8);
+    assert_eq!(run_until_stopped(&mut debugger, &mut chip8), DebugEvent::StepComplete { address: label(&assembly, "after") });
+    assert_eq!(chip8.registers()[0], 2);
+    assert_eq!(chip8.registers()[1], 0);
+}
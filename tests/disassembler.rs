@@ -0,0 +1,16 @@
+//! Checks tracing and listing ROMs.
+
+use chip8::disassembler::{disassemble, Syntax};
+
+#[test]
+fn code_can_run_to_the_end_of_64k_memory() {
+    // Straight code up to a skip in the very last word, neither the skip nor the instruction
+    // before it have anything after them
+    let mut rom = [0x60, 0x00].repeat((0x10000 - 0x200) / 2 - 1);
+    rom.extend_from_slice(&[0x30, 0x00]);
+
+    let listing = disassemble(&rom, Syntax::Cowgod);
+    let last_lines: Vec<&str> = listing.lines().rev().take(2).collect();
+
+    assert_eq!(last_lines, ["FFFE  3000              SE V0, 0x00", "FFFC  6000              LD V0, 0x00"]);
+}
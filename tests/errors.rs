@@ -0,0 +1,66 @@
+//! Checks that broken programs stop the machine with an error instead of a panic.
+
+use chip8::chip8::{Chip8, Chip8Error, Chip8Rom, MAX_STACK_DEPTH};
+use chip8::chip8::quirks::QuirkConfig;
+
+fn run(chip8: &mut Chip8, cycles: usize) -> Result<(), Chip8Error> {
+    for _ in 0..cycles {
+        chip8.cycle()?;
+    }
+    Ok(())
+}
+
+#[test]
+fn unknown_instruction() {
+    let mut chip8 = Chip8Rom::new("unknown", vec![0x60, 0x01, 0xFF, 0xFF]).to_device();
+
+    assert_eq!(run(&mut chip8, 2), Err(Chip8Error::UnknownInstruction { address: 0x202, instruction: 0xFFFF }));
+    assert_eq!(chip8.program_counter(), 0x202);
+    // The faulting instruction didn't run
+    assert_eq!(chip8.total_cycles(), 1);
+}
+
+#[test]
+fn stack_underflow() {
+    let mut chip8 = Chip8Rom::new("underflow", vec![0x00, 0xEE]).to_device();
+
+    assert_eq!(run(&mut chip8, 1), Err(Chip8Error::StackUnderflow { address: 0x200 }));
+}
+
+#[test]
+fn memory_out_of_bounds() {
+    // Stores V0 to V2 at 0xFFF, the last two registers don't fit
+    let mut chip8 = Chip8Rom::new("memory", vec![0xAF, 0xFF, 0xF2, 0x55]).to_device();
+
+    assert_eq!(run(&mut chip8, 2), Err(Chip8Error::MemoryOutOfBounds { address: 0x202, memory_address: 0x1001 }));
+}
+
+#[test]
+fn program_counter_out_of_bounds() {
+    // The instruction at 0xFFF would need a byte past the end of memory
+    let mut chip8 = Chip8Rom::new("jump", vec![0x1F, 0xFF]).to_device();
+
+    assert_eq!(run(&mut chip8, 2), Err(Chip8Error::ProgramCounterOutOfBounds { address: 0xFFF }));
+}
+
+#[test]
+fn waiting_for_input_at_the_end_of_memory() {
+    // Runs to the last word of 64 KiB memory, which waits for a key after the program counter
+    // wrapped around to 0
+    let mut rom = [0x60, 0x00].repeat((0xFFFE - 0x200) / 2);
+    rom.extend_from_slice(&[0xF0, 0x0A]);
+    let quirks = QuirkConfig { extended_memory: true, ..QuirkConfig::new() };
+    let mut chip8 = Chip8Rom::new_quirks("wrap", rom, quirks).to_device();
+
+    assert_eq!(run(&mut chip8, (0xFFFE - 0x200) / 2 + 2), Ok(()));
+    assert_eq!(chip8.program_counter(), 0xFFFE);
+}
+
+#[test]
+fn stack_overflow() {
+    // Calls itself until the stack is full
+    let mut chip8 = Chip8Rom::new("overflow", vec![0x22, 0x00]).to_device();
+
+    assert_eq!(run(&mut chip8, MAX_STACK_DEPTH), Ok(()));
+    assert_eq!(run(&mut chip8, 1), Err(Chip8Error::StackOverflow { address: 0x200 }));
+}
@@ -0,0 +1,79 @@
+//! Names and stores gamepad bindings next to the keyboard bindings.
+
+use winit::keyboard::KeyCode;
+
+use chip8::gamepad::{button_from_name, button_name, default_layout, GamepadButton, MAX_GAMEPADS, STANDARD_BUTTONS};
+use chip8::keymap::{KeyConfig, KeyConfigError, KEYPAD_ORDER, Rebinding};
+
+const PONG2: &str = "a60611339661e3ab2d8af024ad1da5880a6f8665";
+
+#[test]
+fn names_every_button_of_every_pad() {
+    for gamepad in 0..MAX_GAMEPADS {
+        for button in STANDARD_BUTTONS {
+            assert_eq!(button_from_name(&button_name((gamepad, button))), Some((gamepad, button)));
+        }
+    }
+
+    assert_eq!(button_name((1, GamepadButton::DPadUp)), "Gamepad2DPadUp");
+    for name in ["GamepadSouth", "Gamepad0South", "Gamepad5South", "Gamepad1Turbo", "Gamepad1"] {
+        assert_eq!(button_from_name(name), None, "{}", name);
+    }
+}
+
+#[test]
+fn every_pad_gets_the_default_layout() {
+    let layout = default_layout();
+
+    for gamepad in 0..MAX_GAMEPADS {
+        assert_eq!(layout[&(gamepad, GamepadButton::DPadUp)], 0x2);
+        assert_eq!(layout[&(gamepad, GamepadButton::South)], 0x5);
+    }
+}
+
+#[test]
+fn reads_buttons_next_to_keys() {
+    let text = format!("chip8-keys 1\n[default]\nGamepad1South 6\n[rom {}]\nArrowUp 1\nGamepad2DPadUp C\nGamepad2DPadDown D\n", PONG2);
+    let config = KeyConfig::from_text(&text).unwrap();
+
+    // Only buttons in the default section leave the keyboard layout alone
+    assert_eq!(config.default, None);
+    assert_eq!(config.gamepad_default.as_ref().unwrap()[&(0, GamepadButton::South)], 0x6);
+
+    let buttons = config.gamepad_roms.values().next().unwrap();
+    assert_eq!(buttons[&(1, GamepadButton::DPadUp)], 0xC);
+    assert_eq!(config.roms.values().next().unwrap()[&KeyCode::ArrowUp], 0x1);
+
+    assert_eq!(KeyConfig::from_text(&config.to_text()).unwrap(), config);
+    assert!(matches!(KeyConfig::from_text("chip8-keys 1\n[default]\nGamepad9South 1\n"), Err(KeyConfigError::InvalidLine { .. })));
+}
+
+#[test]
+fn binding_a_button_only_replaces_buttons_of_the_same_pad() {
+    let mut config = KeyConfig::new();
+    config.bind_buttons(None, &[((0, GamepadButton::North), 0x5)]);
+
+    let buttons = config.gamepad_default.as_ref().unwrap();
+    assert_eq!(buttons[&(0, GamepadButton::North)], 0x5);
+    assert!(!buttons.contains_key(&(0, GamepadButton::South)));
+    assert_eq!(buttons[&(1, GamepadButton::South)], 0x5);
+    assert_eq!(config.default, None);
+
+    config.reset(None);
+    assert_eq!(config, KeyConfig::new());
+}
+
+#[test]
+fn rebinding_takes_buttons() {
+    let mut rebinding = Rebinding::new(None);
+    rebinding.bind_button((1, GamepadButton::West));
+    for _ in 1..16 {
+        rebinding.skip();
+    }
+
+    let mut config = KeyConfig::new();
+    rebinding.finish(&mut config);
+
+    assert_eq!(config.default, None);
+    assert_eq!(config.gamepad_default.unwrap()[&(1, GamepadButton::West)], KEYPAD_ORDER[0]);
+}
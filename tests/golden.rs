@@ -0,0 +1,126 @@
+//! Runs the bundled test ROMs headlessly under every quirk profile and compares the final display
+//! with the golden framebuffers in `tests/golden`.
+//!
+//! After an intended change to the output, regenerate the golden files with
+//! `UPDATE_GOLDEN=1 cargo test --test golden` and review the differences before committing them.
+
+use std::path::PathBuf;
+use std::{env, fs};
+
+use chip8::chip8::Chip8Rom;
+use chip8::chip8::display::Display;
+use chip8::chip8::quirks::{PROFILE_NAMES, QuirkConfig};
+use chip8::headless::{from_ascii, HeadlessRunner, parse_key_script, to_ascii};
+
+const FRAMES: u32 = 600;
+const INSTRUCTIONS_PER_FRAME: u32 = 30;
+const SEED: u64 = 0;
+
+#[test]
+fn ibm() {
+    check_rom("ibm", include_bytes!("../src/roms/tests/ibm.ch8"), |_| "");
+}
+
+#[test]
+fn corax_plus() {
+    check_rom("corax.plus", include_bytes!("../src/roms/tests/corax.plus.ch8"), |_| "");
+}
+
+#[test]
+fn flags() {
+    check_rom("flags", include_bytes!("../src/roms/tests/flags.ch8"), |_| "");
+}
+
+#[test]
+fn quirks() {
+    // Pick the platform in the menu that matches the profile being tested. The ROM has no CHIP-48
+    // option, so CHIP-48 runs the CHIP-8 checks: its golden shows where CHIP-48 differs from the
+    // COSMAC VIP.
+    check_rom("quirks", include_bytes!("../src/roms/tests/quirks.ch8"), |profile| match profile {
+        "schip-modern" => "60 2 down\n65 2 up\n120 1 down\n125 1 up",
+        "schip-legacy" => "60 2 down\n65 2 up\n120 2 down\n125 2 up",
+        "xo-chip" => "60 3 down\n65 3 up",
+        _ => "60 1 down\n65 1 up",
+    });
+}
+
+#[test]
+fn keypad() {
+    // Start the Ex9E test and hold down key 5 until the end
+    check_rom("keypad", include_bytes!("../src/roms/tests/keypad.ch8"), |_| "60 1 down\n65 1 up\n100 5 down");
+}
+
+fn check_rom(name: &str, data: &[u8], key_script: fn(&str) -> &'static str) {
+    let mut failures = vec![];
+
+    for profile in PROFILE_NAMES {
+        let quirks = QuirkConfig::from_profile_name(profile).unwrap();
+        let mut chip8 = Chip8Rom::new_quirks(name, data.to_vec(), quirks).to_device();
+        chip8.set_seed(SEED);
+        let events = parse_key_script(key_script(profile)).unwrap();
+
+        let mut runner = HeadlessRunner::new(chip8, INSTRUCTIONS_PER_FRAME).with_key_events(events);
+        if let Err(error) = runner.run_frames(FRAMES) {
+            failures.push(format!("{} ({}) halted after {} frames: {}", name, profile, runner.frame(), error));
+            continue;
+        }
+
+        let actual = &runner.chip8.display;
+        let path = golden_path(name, profile);
+
+        if env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, to_ascii(actual)).unwrap();
+            continue;
+        }
+
+        let expected = fs::read_to_string(&path)
+            .unwrap_or_else(|error| panic!("Missing golden file {}: {}", path.display(), error));
+        let expected = from_ascii(&expected)
+            .unwrap_or_else(|| panic!("Golden file {} is not a valid display", path.display()));
+
+        if &expected != actual {
+            failures.push(format!("{} ({}) differs from {}:\n{}", name, profile, path.display(), diff(&expected, actual)));
+        }
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+fn golden_path(name: &str, profile: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(name).join(format!("{}.txt", profile))
+}
+
+/// The actual display with every differing pixel marked: `X` is lit but should be off, `O` is
+/// off but should be lit
+fn diff(expected: &Display, actual: &Display) -> String {
+    if expected.width() != actual.width() || expected.height() != actual.height() {
+        return format!(
+            "expected a {}x{} display, got {}x{}\n{}",
+            expected.width(), expected.height(), actual.width(), actual.height(), to_ascii(actual)
+        );
+    }
+
+    let actual_text = to_ascii(actual);
+    let mut output = String::new();
+    let mut differences = 0;
+
+    for (y, row) in actual_text.lines().enumerate() {
+        for (x, character) in row.chars().enumerate() {
+            match (expected.get(x, y), actual.get(x, y)) {
+                (expected, actual) if expected == actual => output.push(character),
+                (0, _) => {
+                    differences += 1;
+                    output.push('X');
+                }
+                _ => {
+                    differences += 1;
+                    output.push('O');
+                }
+            }
+        }
+        output.push('\n');
+    }
+
+    format!("{} pixels differ (X: unexpected, O: missing)\n{}", differences, output)
+}
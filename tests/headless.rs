@@ -0,0 +1,204 @@
+//! Checks the headless runner, its image output and the `chip8-headless` binary.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+use chip8::assembler::assemble;
+use chip8::chip8::Chip8Rom;
+use chip8::chip8::display::Display;
+use chip8::hash::crc32;
+use chip8::headless::{from_ascii, HeadlessRunner, parse_key_script, to_ascii, to_pbm, to_png};
+
+/// Polls every key until one is held and draws its hex digit in the corner
+const SHOW_KEY: &str = "
+: main
+  v2 := 15
+: poll
+  v0 += 1
+  v0 &= v2
+  if v0 key then jump show
+  jump poll
+: show
+  i := hex v0
+  sprite v1 v1 5
+: end
+  jump end
+";
+
+/// A lores display with a pixel of every plane combination
+fn planes() -> Display {
+    let mut rows = vec![".".repeat(64); 32];
+    rows[0] = format!("#+@{}", ".".repeat(61));
+    rows[31] = format!("{}@", ".".repeat(63));
+    from_ascii(&rows.join("\n")).unwrap()
+}
+
+fn lit_pixels(display: &Display) -> usize {
+    display.pixels().iter().filter(|pixel| **pixel != 0).count()
+}
+
+/// The chunks of a PNG file after checking their CRCs
+fn png_chunks(png: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    let mut chunks = vec![];
+    let mut rest = &png[8..];
+
+    while !rest.is_empty() {
+        let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+        let kind: [u8; 4] = rest[4..8].try_into().unwrap();
+        let data = rest[8..8 + length].to_vec();
+        let checksum = u32::from_be_bytes(rest[8 + length..12 + length].try_into().unwrap());
+        assert_eq!(crc32(&rest[4..8 + length]), checksum, "CRC of {}", String::from_utf8_lossy(&kind));
+
+        chunks.push((kind, data));
+        rest = &rest[12 + length..];
+    }
+
+    chunks
+}
+
+/// Unpacks a zlib stream of stored deflate blocks and checks its Adler-32
+fn inflate_stored(stream: &[u8]) -> Vec<u8> {
+    assert_eq!(stream[0] & 0x0F, 8, "Not deflate");
+    assert_eq!(u16::from_be_bytes([stream[0], stream[1]]) % 31, 0, "Bad zlib header check");
+    let mut data = vec![];
+    let mut position = 2;
+
+    loop {
+        let header = stream[position];
+        assert_eq!(header >> 1, 0, "Not a stored block");
+        let length = u16::from_le_bytes([stream[position + 1], stream[position + 2]]);
+        let inverse = u16::from_le_bytes([stream[position + 3], stream[position + 4]]);
+        assert_eq!(length, !inverse);
+
+        data.extend_from_slice(&stream[position + 5..position + 5 + length as usize]);
+        position += 5 + length as usize;
+        if header & 1 == 1 {
+            break;
+        }
+    }
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in &data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    assert_eq!(&stream[position..], &((b << 16) | a).to_be_bytes());
+
+    data
+}
+
+#[test]
+fn pbm_has_a_bit_for_every_pixel() {
+    let display = planes();
+    let pbm = to_pbm(&display);
+
+    let header = b"P4\n64 32\n";
+    assert_eq!(&pbm[..header.len()], header);
+    let bits = &pbm[header.len()..];
+    assert_eq!(bits.len(), 64 / 8 * 32);
+    assert_eq!(bits.iter().map(|byte| byte.count_ones() as usize).sum::<usize>(), lit_pixels(&display));
+    assert_eq!(bits[0], 0b1110_0000);
+    assert_eq!(bits[bits.len() - 1], 0b0000_0001);
+}
+
+#[test]
+fn png_decodes_to_the_scaled_display() {
+    let display = planes();
+    let scale = 3;
+    let chunks = png_chunks(&to_png(&display, scale));
+    let kinds: Vec<&[u8]> = chunks.iter().map(|(kind, _)| kind.as_slice()).collect();
+    assert_eq!(kinds, [b"IHDR".as_slice(), b"PLTE", b"IDAT", b"IEND"]);
+
+    let header = &chunks[0].1;
+    let (width, height) = (64 * scale, 32 * scale);
+    assert_eq!(u32::from_be_bytes(header[..4].try_into().unwrap()), width as u32);
+    assert_eq!(u32::from_be_bytes(header[4..8].try_into().unwrap()), height as u32);
+    assert_eq!(header[8..], [8, 3, 0, 0, 0]);
+    assert_eq!(chunks[1].1.len(), 4 * 3);
+
+    let image = inflate_stored(&chunks[2].1);
+    assert_eq!(image.len(), (width + 1) * height);
+    let rows: Vec<&[u8]> = image.chunks(width + 1).collect();
+    assert!(rows.iter().all(|row| row[0] == 0));
+    for (y, row) in rows.iter().enumerate() {
+        for (x, pixel) in row[1..].iter().enumerate() {
+            assert_eq!(*pixel, display.get(x / scale, y / scale), "Pixel {},{}", x, y);
+        }
+    }
+    assert_eq!(rows.iter().map(|row| row[1..].iter().filter(|pixel| **pixel != 0).count()).sum::<usize>(), lit_pixels(&display) * scale * scale);
+}
+
+#[test]
+fn large_png_is_split_into_stored_blocks() {
+    // 1025 bytes per row for 512 rows, more than fits in one block
+    let display = Display::new(128, 64);
+    let chunks = png_chunks(&to_png(&display, 8));
+
+    assert_eq!(inflate_stored(&chunks[2].1).len(), (128 * 8 + 1) * 64 * 8);
+}
+
+#[test]
+fn key_script_drives_the_runner() {
+    let rom = assemble(SHOW_KEY).unwrap().rom;
+    // Held for long enough that the loop gets around to it
+    let events = parse_key_script("10 7 down\n20 7 up").unwrap();
+    let mut runner = HeadlessRunner::new(Chip8Rom::new("keys", rom).to_device(), 10).with_key_events(events);
+
+    runner.run_frames(10).unwrap();
+    assert_eq!(lit_pixels(&runner.chip8.display), 0);
+
+    runner.run_frames(15).unwrap();
+    assert_eq!(runner.frame(), 25);
+    let rows: Vec<String> = to_ascii(&runner.chip8.display).lines().take(6).map(|row| row[..8].to_string()).collect();
+    assert_eq!(rows, ["####....", "...#....", "..#.....", ".#......", ".#......", "........"]);
+}
+
+fn headless(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_chip8-headless")).args(args).output().unwrap()
+}
+
+fn bundled_rom(name: &str) -> String {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src").join("roms").join("tests").join(name).to_string_lossy().to_string()
+}
+
+#[test]
+fn binary_prints_the_display() {
+    let output = headless(&[&bundled_rom("ibm.ch8"), "--profile", "cosmac-vip", "--ipf", "30", "--frames", "600"]);
+    let golden = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join("ibm").join("cosmac-vip.txt");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), fs::read_to_string(golden).unwrap());
+}
+
+#[test]
+fn binary_writes_the_format_of_the_output_file() {
+    let path = std::env::temp_dir().join(format!("chip8-headless-{}.png", std::process::id()));
+    let output = headless(&[&bundled_rom("ibm.ch8"), "--frames", "10", "--output", &path.to_string_lossy()]);
+    let png = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(png_chunks(&png)[0].0, *b"IHDR");
+}
+
+#[test]
+fn binary_rejects_bad_arguments() {
+    for args in [&["--frames", "lots"][..], &[], &["a.ch8", "b.ch8"], &["a.ch8", "--profile", "chip-9"], &["a.ch8", "--ipf"]] {
+        let output = headless(args);
+
+        assert_eq!(output.status.code(), Some(1), "{:?}", args);
+        assert!(output.stdout.is_empty(), "{:?}", args);
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("Usage"), "{:?}", args);
+    }
+}
+
+#[test]
+fn binary_reports_a_missing_rom() {
+    let output = headless(&["missing.ch8"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("missing.ch8"));
+}
@@ -0,0 +1,62 @@
+//! Reads and writes key binding files and runs the rebinding flow without a window.
+
+use winit::keyboard::KeyCode;
+
+use chip8::keymap::{default_layout, KeyConfig, KeyConfigError, KEYPAD_ORDER, Rebinding};
+
+const TETRIS: &str = "5f518084744bf3cb8733f6e5454dfd1634320563";
+
+#[test]
+fn reads_defaults_and_rom_overrides() {
+    let text = format!("chip8-keys 1\n# AZERTY\n[default]\nKeyA 4\nKeyZ 5\n[rom {}]\nArrowLeft 5\nArrowRight 6 # move\n", TETRIS);
+    let config = KeyConfig::from_text(&text).unwrap();
+
+    let default = config.default.as_ref().unwrap();
+    assert_eq!(default.len(), 2);
+    assert_eq!(default[&KeyCode::KeyA], 0x4);
+
+    let (hash, overrides) = config.roms.iter().next().unwrap();
+    assert_eq!(to_hex(hash), TETRIS);
+    assert_eq!(overrides[&KeyCode::ArrowRight], 0x6);
+
+    assert_eq!(KeyConfig::from_text(&config.to_text()).unwrap(), config);
+}
+
+#[test]
+fn rejects_invalid_files() {
+    assert_eq!(KeyConfig::from_text(""), Err(KeyConfigError::InvalidHeader));
+    assert_eq!(KeyConfig::from_text("chip8-keys 2"), Err(KeyConfigError::UnsupportedVersion(2)));
+
+    for line in ["KeyA 4", "[default]\nKeyA G", "[default]\nHyper 1", "[rom 1234]", "[player 2]"] {
+        let text = format!("chip8-keys 1\n{}\n", line);
+        assert!(matches!(KeyConfig::from_text(&text), Err(KeyConfigError::InvalidLine { .. })), "{}", line);
+    }
+}
+
+#[test]
+fn rebinding_replaces_keys() {
+    let mut rebinding = Rebinding::new(None);
+    assert_eq!(rebinding.current(), Some(KEYPAD_ORDER[0]));
+
+    rebinding.bind(KeyCode::ArrowUp);
+    rebinding.skip();
+    // Binding a key a second time moves it to the key being asked for
+    rebinding.bind(KeyCode::ArrowUp);
+    for _ in 3..16 {
+        rebinding.skip();
+    }
+    assert_eq!(rebinding.current(), None);
+
+    let mut config = KeyConfig::new();
+    rebinding.finish(&mut config);
+
+    let default = config.default.unwrap();
+    assert_eq!(default[&KeyCode::ArrowUp], KEYPAD_ORDER[2]);
+    assert!(!default.contains_key(&KeyCode::Digit3));
+    assert_eq!(default[&KeyCode::Digit1], 0x1);
+    assert_eq!(default.len(), default_layout().len());
+}
+
+fn to_hex(hash: &[u8; 20]) -> String {
+    hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
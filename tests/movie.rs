@@ -0,0 +1,113 @@
+//! Checks that a recorded movie plays back into exactly the same run.
+
+use chip8::chip8::{Chip8, Chip8Rom, CycleOutcome};
+use chip8::chip8::quirks::{DisplayWait, QuirkConfig};
+use chip8::movie::{Movie, MovieError, MoviePlayer, MovieRecorder};
+use chip8::scheduler::{Scheduler, Tick, Timing};
+
+const TETRIS: &[u8] = include_bytes!("../src/roms/games/tetris.ch8");
+
+/// Runs a frame the way the application does: the scheduler hands out the instructions and timer
+/// ticks, the recorded key changes are applied before each instruction
+fn run_frame(chip8: &mut Chip8, scheduler: &mut Scheduler, mut player: Option<&mut MoviePlayer>) {
+    scheduler.advance_frame();
+
+    while let Some(tick) = scheduler.next_tick() {
+        if tick == Tick::Timer {
+            chip8.update();
+            continue;
+        }
+
+        if let Some(player) = player.as_mut() {
+            player.apply(chip8);
+        }
+        let timing = (scheduler.timing() == Timing::CosmacVip).then(|| chip8.vip_timing());
+        if chip8.cycle().unwrap() == CycleOutcome::Executed {
+            if let Some(timing) = timing {
+                scheduler.spend(timing);
+            }
+        }
+    }
+}
+
+/// Plays tetris for a while with a few key presses, returns the machine and the movie. Without
+/// waiting for the display the instruction rate decides how far the game gets between timer ticks.
+fn record(scheduler: &mut Scheduler) -> (Chip8, Movie) {
+    let quirks = QuirkConfig { display_wait: DisplayWait::Never, ..QuirkConfig::new() };
+    let mut chip8 = Chip8Rom::new_quirks("tetris", TETRIS.to_vec(), quirks).to_device();
+    chip8.set_seed(42);
+
+    let mut recorder = MovieRecorder::new(TETRIS, &chip8, scheduler);
+    for frame in 0..400 {
+        match frame {
+            30 | 90 | 200 => recorder.set_input(&mut chip8, 6, true),
+            40 | 95 | 260 => recorder.set_input(&mut chip8, 6, false),
+            120 => recorder.set_input(&mut chip8, 4, true),
+            125 => recorder.set_input(&mut chip8, 4, false),
+            _ => {}
+        }
+        run_frame(&mut chip8, scheduler, None);
+    }
+
+    let movie = Movie::from_text(&recorder.finish(&chip8).to_text()).unwrap();
+    (chip8, movie)
+}
+
+/// Plays the movie on a machine and scheduler that start out in a different state
+fn replay(movie: Movie, mut scheduler: Scheduler) -> Chip8 {
+    let mut replayed = Chip8::new();
+    replayed.set_seed(7);
+
+    let mut player = MoviePlayer::start(movie, TETRIS, &mut replayed, &mut scheduler).unwrap();
+    while !player.finished(&replayed) {
+        run_frame(&mut replayed, &mut scheduler, Some(&mut player));
+    }
+    replayed
+}
+
+#[test]
+fn playback_repeats_the_recording() {
+    let (chip8, movie) = record(&mut Scheduler::new(900.0));
+    let replayed = replay(movie, Scheduler::new(900.0));
+
+    assert!(replayed.display.pixels() == chip8.display.pixels());
+    assert_eq!(replayed.registers(), chip8.registers());
+    assert!(replayed.save_state() == chip8.save_state());
+}
+
+#[test]
+fn playback_uses_the_recorded_speed() {
+    let mut scheduler = Scheduler::new(600.0);
+    let (chip8, movie) = record(&mut scheduler);
+    assert_eq!(movie.instructions_per_second, 600.0);
+
+    // The timers would tick between other instructions at the configured rate
+    let replayed = replay(movie, Scheduler::new(1500.0));
+
+    assert_eq!(replayed.registers(), chip8.registers());
+    assert!(replayed.save_state() == chip8.save_state());
+}
+
+#[test]
+fn playback_uses_the_recorded_timing() {
+    let mut scheduler = Scheduler::new(700.0);
+    scheduler.set_timing(Timing::CosmacVip);
+    let (chip8, movie) = record(&mut scheduler);
+    assert_eq!(movie.timing, Timing::CosmacVip);
+
+    let replayed = replay(movie, Scheduler::new(700.0));
+
+    assert_eq!(replayed.registers(), chip8.registers());
+    assert!(replayed.save_state() == chip8.save_state());
+}
+
+#[test]
+fn version_1_movies_play_at_the_default_speed() {
+    let text = "chip8-movie 1\nrom 0000000000000000000000000000000000000000\nquirks 1 2 1 1 1 0 0\nseed 2a\nend 10\n";
+    let movie = Movie::from_text(text).unwrap();
+
+    assert_eq!(movie.instructions_per_second, 700.0);
+    assert_eq!(movie.timing, Timing::Fixed);
+    assert_eq!(Movie::from_text(&text.replace("movie 1", "movie 2")), Err(MovieError::Missing("instructions per second")));
+    assert_eq!(Movie::from_text(&text.replace("movie 1", "movie 3")), Err(MovieError::UnsupportedVersion(3)));
+}
@@ -0,0 +1,54 @@
+//! Checks the named themes and reading and writing the stored palettes.
+
+use chip8::palette::{AMBER, DEFAULT_PALETTE, GREEN_PHOSPHOR, parse_palette, palette_to_text, PaletteConfig, PaletteConfigError, THEMES};
+
+const PONG_2: &str = "a60611339661e3ab2d8af024ad1da5880a6f8665";
+
+#[test]
+fn themes_parse_by_name() {
+    assert_eq!(parse_palette("Amber"), Some(AMBER));
+    assert_eq!(parse_palette("purple"), Some(DEFAULT_PALETTE));
+
+    for (name, palette) in THEMES {
+        assert_eq!(palette_to_text(&palette), name);
+    }
+}
+
+#[test]
+fn custom_colours_round_trip() {
+    let palette = parse_palette("#102030,F0E0D0").unwrap();
+    assert_eq!(palette_to_text(&palette), "102030,F0E0D0");
+
+    let planes = parse_palette("000000,FF0000,00FF00,0000FF").unwrap();
+    assert_eq!(palette_to_text(&planes), "000000,FF0000,00FF00,0000FF");
+}
+
+#[test]
+fn config_round_trips() {
+    let text = format!("chip8-palettes 1\ndefault green\nrom {} 102030,F0E0D0\n", PONG_2);
+    let config = PaletteConfig::from_text(&text).unwrap();
+
+    assert_eq!(config.default, Some(GREEN_PHOSPHOR));
+    assert_eq!(config.roms.len(), 1);
+    assert_eq!(config.to_text(), text);
+}
+
+#[test]
+fn clearing_a_rom_keeps_the_default() {
+    let mut config = PaletteConfig::new();
+    let hash = [7; 20];
+
+    config.set(None, Some(AMBER));
+    config.set(Some(hash), Some(GREEN_PHOSPHOR));
+    config.set(Some(hash), None);
+
+    assert_eq!(config.default, Some(AMBER));
+    assert!(config.roms.is_empty());
+}
+
+#[test]
+fn unknown_themes_are_rejected() {
+    let error = PaletteConfig::from_text("chip8-palettes 1\ndefault sepia\n").unwrap_err();
+
+    assert_eq!(error, PaletteConfigError::InvalidLine { line: 2, text: "default sepia".to_string() });
+}
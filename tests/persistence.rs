@@ -0,0 +1,55 @@
+//! Checks the persistence modes and reading and writing the stored modes.
+
+use chip8::persistence::{DEFAULT_HALF_LIFE, Persistence, PersistenceConfig, PersistenceConfigError};
+
+const PONG_2: &str = "a60611339661e3ab2d8af024ad1da5880a6f8665";
+
+#[test]
+fn modes_parse_with_and_without_settings() {
+    assert_eq!(Persistence::parse("off"), Some(Persistence::Off));
+    assert_eq!(Persistence::parse("Last-Frame"), Some(Persistence::LastFrame));
+    assert_eq!(Persistence::parse("decay"), Some(Persistence::Decay { half_life: DEFAULT_HALF_LIFE }));
+    assert_eq!(Persistence::parse("decay:80"), Some(Persistence::Decay { half_life: 80.0 }));
+    assert_eq!(Persistence::parse("majority:5"), Some(Persistence::Majority { frames: 5 }));
+
+    assert_eq!(Persistence::parse("decay:0"), None);
+    assert_eq!(Persistence::parse("majority:32"), None);
+    assert_eq!(Persistence::parse("off:1"), None);
+}
+
+#[test]
+fn decay_halves_every_half_life() {
+    let persistence = Persistence::Decay { half_life: 40.0 };
+
+    assert_eq!(persistence.decay(40.0), 0.5);
+    assert_eq!(persistence.decay(80.0), 0.25);
+    assert_eq!(Persistence::LastFrame.decay(40.0), 0.0);
+}
+
+#[test]
+fn config_round_trips() {
+    let text = format!("chip8-persistence 1\ndefault decay:80\nrom {} majority:3\n", PONG_2);
+    let config = PersistenceConfig::from_text(&text).unwrap();
+
+    assert_eq!(config.default, Some(Persistence::Decay { half_life: 80.0 }));
+    assert_eq!(config.to_text(), text);
+}
+
+#[test]
+fn roms_fall_back_to_the_default() {
+    let mut config = PersistenceConfig::new();
+    assert_eq!(config.get(&[1; 20]), Persistence::LastFrame);
+
+    config.set(None, Some(Persistence::Off));
+    config.set(Some([2; 20]), Some(Persistence::Majority { frames: 3 }));
+
+    assert_eq!(config.get(&[1; 20]), Persistence::Off);
+    assert_eq!(config.get(&[2; 20]), Persistence::Majority { frames: 3 });
+}
+
+#[test]
+fn invalid_modes_are_rejected() {
+    let error = PersistenceConfig::from_text("chip8-persistence 1\ndefault blur\n").unwrap_err();
+
+    assert_eq!(error, PersistenceConfigError::InvalidLine { line: 2, text: "default blur".to_string() });
+}
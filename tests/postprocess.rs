@@ -0,0 +1,64 @@
+//! Checks the list of post-processing effects and picking them by text.
+
+use chip8::postprocess::{EFFECTS, MAX_PARAMS, PostProcessError, PostProcessing};
+
+fn index(name: &str) -> usize {
+    EFFECTS.iter().position(|effect| effect.name == name).unwrap()
+}
+
+#[test]
+fn every_effect_fits_the_pass_uniform() {
+    for effect in EFFECTS.iter() {
+        assert!(effect.source.contains("fn fs_effect"), "{} has no fs_effect", effect.name);
+        assert!(effect.params.len() <= MAX_PARAMS, "{} has too many parameters", effect.name);
+        assert!(effect.params.iter().all(|param| (param.min..=param.max).contains(&param.default)));
+    }
+}
+
+#[test]
+fn effects_start_off_with_their_defaults() {
+    let post_processing = PostProcessing::new();
+
+    assert!(post_processing.enabled().is_empty());
+    assert_eq!(post_processing.settings(index("scanlines")).params[0], EFFECTS[index("scanlines")].params[0].default);
+}
+
+#[test]
+fn list_turns_on_effects_in_their_order() {
+    let mut post_processing = PostProcessing::new();
+    post_processing.apply("vignette:intensity=0.6:size=0.3, scanlines").unwrap();
+
+    assert_eq!(post_processing.enabled(), vec![index("scanlines"), index("vignette")]);
+    assert_eq!(post_processing.settings(index("vignette")).params[..2], [0.6, 0.3]);
+}
+
+#[test]
+fn crt_and_none_switch_every_effect() {
+    let mut post_processing = PostProcessing::new();
+
+    post_processing.apply("crt").unwrap();
+    assert_eq!(post_processing.enabled().len(), EFFECTS.len());
+
+    post_processing.apply("none,bloom").unwrap();
+    assert_eq!(post_processing.enabled(), vec![index("bloom")]);
+}
+
+#[test]
+fn values_are_clamped_to_their_range() {
+    let mut post_processing = PostProcessing::new();
+    post_processing.set_param("scanlines", "intensity", 3.0).unwrap();
+
+    assert_eq!(post_processing.settings(index("scanlines")).params[0], 1.0);
+}
+
+#[test]
+fn unknown_names_are_rejected() {
+    let mut post_processing = PostProcessing::new();
+
+    assert_eq!(post_processing.apply("blur"), Err(PostProcessError::UnknownEffect("blur".to_string())));
+    assert_eq!(
+        post_processing.apply("bloom:size=2"),
+        Err(PostProcessError::UnknownParam { effect: "bloom".to_string(), param: "size".to_string() }),
+    );
+    assert!(matches!(post_processing.apply("bloom:strength=lots"), Err(PostProcessError::InvalidValue { .. })));
+}
@@ -0,0 +1,43 @@
+//! Checks quirks that depend on the state of the machine.
+
+use chip8::chip8::{Chip8Rom, CycleOutcome};
+use chip8::chip8::quirks::{MemoryIncrement, QuirkConfig};
+
+#[test]
+fn legacy_superchip_only_waits_for_the_display_in_lores() {
+    // Draws a sprite in lores, switches to hires and draws it again
+    let rom = vec![0xD0, 0x01, 0x00, 0xFF, 0xD0, 0x01, 0x00, 0xE0];
+    let mut chip8 = Chip8Rom::new_quirks("wait", rom, QuirkConfig::schip_legacy()).to_device();
+
+    assert_eq!(chip8.cycle(), Ok(CycleOutcome::Executed));
+    assert_eq!(chip8.cycle(), Ok(CycleOutcome::BlockedOnDraw));
+
+    chip8.update();
+    for _ in 0..3 {
+        assert_eq!(chip8.cycle(), Ok(CycleOutcome::Executed));
+    }
+}
+
+#[test]
+fn memory_increment_moves_the_index_past_the_registers() {
+    // Sets V0 to V2, saves them at 0x300 and loads them back from wherever the index ends up
+    let rom = vec![0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0xA3, 0x00, 0xF2, 0x55, 0xF2, 0x65];
+    let cases = [
+        (MemoryIncrement::X, 0x302, [3, 0, 0]),
+        (MemoryIncrement::XPlusOne, 0x303, [0, 0, 0]),
+        (MemoryIncrement::None, 0x300, [1, 2, 3]),
+    ];
+
+    for (memory_increment, index, registers) in cases {
+        let quirks = QuirkConfig { memory_increment, ..QuirkConfig::new() };
+        let mut chip8 = Chip8Rom::new_quirks("memory", rom.clone(), quirks).to_device();
+        for _ in 0..5 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(chip8.index_register(), index, "{:?}", memory_increment);
+
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.registers()[..3], registers, "{:?}", memory_increment);
+        assert_eq!(chip8.index_register(), index + (index - 0x300), "{:?}", memory_increment);
+    }
+}
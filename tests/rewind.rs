@@ -0,0 +1,60 @@
+//! Checks that rewinding gives back every stored state exactly.
+
+use chip8::rewind::RewindBuffer;
+
+fn rewind_all(buffer: &mut RewindBuffer) -> Vec<Vec<u8>> {
+    let mut states = vec![];
+    while let Some(state) = buffer.rewind() {
+        states.push(state.to_vec());
+    }
+    states
+}
+
+#[test]
+fn states_of_different_lengths_come_back() {
+    let states = vec![vec![1, 2, 3], vec![1, 2, 3, 4, 5, 6], vec![9], vec![], vec![7, 7, 7, 7]];
+    let mut buffer = RewindBuffer::new(10);
+    for state in &states {
+        buffer.push(state.clone());
+    }
+
+    let expected: Vec<_> = states[..states.len() - 1].iter().rev().cloned().collect();
+    assert_eq!(rewind_all(&mut buffer), expected);
+}
+
+#[test]
+fn long_runs_of_equal_bytes_come_back() {
+    // Runs longer than a single varint byte can count, with changes in between and at the end
+    let first = vec![0xAA; 70_000];
+    let mut second = first.clone();
+    second[0] = 0;
+    second[300] = 1;
+    second[301] = 2;
+    second[69_999] = 3;
+    second.extend_from_slice(&[0xAA; 200]);
+
+    let mut buffer = RewindBuffer::new(3);
+    buffer.push(first.clone());
+    buffer.push(second);
+
+    assert_eq!(rewind_all(&mut buffer), vec![first]);
+}
+
+#[test]
+fn the_oldest_state_is_dropped_when_full() {
+    let mut buffer = RewindBuffer::new(3);
+    for frame in 0..5u8 {
+        buffer.push(vec![frame; 4]);
+    }
+
+    assert_eq!(rewind_all(&mut buffer), vec![vec![3; 4], vec![2; 4]]);
+}
+
+#[test]
+fn no_capacity_still_keeps_the_newest_state() {
+    let mut buffer = RewindBuffer::new(0);
+    buffer.push(vec![1]);
+    buffer.push(vec![2]);
+
+    assert_eq!(buffer.rewind(), None);
+}
@@ -0,0 +1,36 @@
+//! Checks that a seeded run can be repeated exactly.
+
+use chip8::chip8::Chip8Rom;
+use chip8::headless::{HeadlessRunner, parse_key_script};
+
+const INSTRUCTIONS_PER_FRAME: u32 = 15;
+
+/// Every frame of the run and the save state of the machine at the end
+fn run(rom: &[u8], seed: u64, key_script: &str, frames: u32) -> (Vec<Vec<u8>>, Vec<u8>) {
+    let mut chip8 = Chip8Rom::new("rng", rom.to_vec()).to_device();
+    chip8.set_seed(seed);
+    let mut runner = HeadlessRunner::new(chip8, INSTRUCTIONS_PER_FRAME).with_key_events(parse_key_script(key_script).unwrap());
+
+    let frames = (0..frames).map(|_| {
+        runner.run_frame().unwrap();
+        runner.chip8.display.pixels().to_vec()
+    }).collect();
+
+    (frames, runner.chip8.save_state())
+}
+
+#[test]
+fn same_seed_and_input_give_the_same_frames() {
+    let rom = include_bytes!("../src/roms/games/tetris.ch8");
+    let keys = "30 6 down\n40 6 up\n90 4 down\n95 4 up\n200 5 down\n260 5 up";
+
+    assert!(run(rom, 1234, keys, 400) == run(rom, 1234, keys, 400));
+}
+
+#[test]
+fn different_seeds_give_different_frames() {
+    // Every wall of the maze is picked at random
+    let rom = include_bytes!("../src/roms/games/maze.ch8");
+
+    assert!(run(rom, 1, "", 60).0.last() != run(rom, 2, "", 60).0.last());
+}
@@ -0,0 +1,80 @@
+//! Looks up ROMs in the compiled in database and reads entries in the community database format.
+
+use chip8::chip8::Chip8Rom;
+use chip8::chip8::quirks::{MemoryIncrement, QuirkConfig};
+use chip8::romdb::{DatabaseError, RomDatabase};
+
+#[test]
+fn bundled_roms_keep_their_quirks() {
+    let rom = |data: &[u8]| Chip8Rom::new("rom", data.to_vec());
+
+    assert_eq!(rom(include_bytes!("../src/roms/tests/quirks.ch8")).effective_quirks(), QuirkConfig::cosmac_vip());
+    assert_eq!(rom(include_bytes!("../src/roms/tests/flags.ch8")).effective_quirks(), QuirkConfig::schip_modern());
+    assert_eq!(rom(include_bytes!("../src/roms/games/blinky.ch8")).effective_quirks(), QuirkConfig::chip48());
+
+    let pong = rom(include_bytes!("../src/roms/games/pong.ch8"));
+    let info = pong.info().unwrap();
+    assert_eq!(info.title, "Pong");
+    assert_eq!(info.platform.as_deref(), Some("originalChip8"));
+    assert!(info.keys.contains(&("up".to_string(), 0x1)));
+}
+
+#[test]
+fn picked_quirks_win_over_the_database() {
+    let rom = Chip8Rom::new_quirks("flags", include_bytes!("../src/roms/tests/flags.ch8").to_vec(), QuirkConfig::xo_chip());
+    assert_eq!(rom.effective_quirks(), QuirkConfig::xo_chip());
+
+    let unknown = Chip8Rom::new("unknown", vec![0x12, 0x00]);
+    assert!(unknown.info().is_none());
+    assert_eq!(unknown.effective_quirks(), QuirkConfig::new());
+}
+
+#[test]
+fn reads_community_format() {
+    let rom = [0x00, 0xE0, 0x12, 0x00];
+    let json = r##"[
+        {
+            "title": "Example",
+            "authors": ["Someone"],
+            "roms": {
+                "2cdd5bd3f4e30a4d56d9a8841ffcd5fbc2d0f735": {
+                    "file": "example.ch8",
+                    "platforms": ["superchip", "xochip"],
+                    "quirkyPlatforms": { "superchip": { "shift": false, "memoryIncrementByX": true, "wrap": true } },
+                    "tickrate": 30,
+                    "keys": { "left": 7, "right": 9, "a": 99 },
+                    "colors": { "pixels": ["#102030", "#ffffff"] }
+                }
+            }
+        }
+    ]"##;
+
+    let database = RomDatabase::from_json(json).unwrap();
+    let info = database.get(&rom).unwrap();
+
+    assert_eq!(info.title, "Example");
+    assert_eq!(info.authors, vec!["Someone".to_string()]);
+    assert_eq!(info.platform.as_deref(), Some("superchip"));
+    assert_eq!(info.tickrate, Some(30));
+    assert_eq!(info.keys, vec![("left".to_string(), 7), ("right".to_string(), 9)]);
+
+    let quirks = info.quirks.unwrap();
+    assert!(quirks.source_vy_bitshift);
+    assert_eq!(quirks.memory_increment, MemoryIncrement::X);
+    assert!(!quirks.sprite_clipping);
+    assert!(quirks.jump_vx_offset);
+
+    let palette = info.palette.unwrap();
+    assert_eq!(palette[0], [16.0 / 255.0, 32.0 / 255.0, 48.0 / 255.0, 1.0]);
+    assert_eq!(palette[3], [1.0, 1.0, 1.0, 1.0]);
+}
+
+#[test]
+fn rejects_other_formats() {
+    assert!(matches!(RomDatabase::from_json("{"), Err(DatabaseError::InvalidJson(_))));
+    assert!(matches!(RomDatabase::from_json("{}"), Err(DatabaseError::InvalidFormat(_))));
+    assert!(matches!(
+        RomDatabase::from_json(r#"[{ "title": "Bad", "roms": { "not a hash": {} } }]"#),
+        Err(DatabaseError::InvalidFormat(_))
+    ));
+}
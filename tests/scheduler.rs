@@ -0,0 +1,261 @@
+//! Checks the order and timing of the instructions and timer ticks handed out by the scheduler.
+
+use std::time::Duration;
+
+use chip8::chip8::Chip8Rom;
+use chip8::chip8::timing::{InstructionTiming, KEY_SCAN_CYCLES};
+use chip8::scheduler::{InstructionMeter, MAX_CATCH_UP, Scheduler, Speed, Tick, Timing};
+
+/// Advances by `elapsed` in `steps` updates and counts the ticks that became due
+fn run(scheduler: &mut Scheduler, elapsed: Duration, steps: u32) -> (u64, u64) {
+    let (mut instructions, mut timers) = (0, 0);
+
+    for _ in 0..steps {
+        scheduler.advance(elapsed / steps);
+        while let Some(tick) = scheduler.next_tick() {
+            match tick {
+                Tick::Instruction => instructions += 1,
+                Tick::Timer => timers += 1,
+            }
+        }
+    }
+
+    (instructions, timers)
+}
+
+#[test]
+fn irregular_updates_do_not_drift() {
+    // 7 ms updates don't line up with the 60 Hz timer or the instructions
+    let mut scheduler = Scheduler::new(700.0);
+    let (instructions, timers) = run(&mut scheduler, Duration::from_millis(7 * 6000), 6000);
+    // Half a millisecond more so no tick is due right at the end
+    let (last_instructions, last_timers) = run(&mut scheduler, Duration::from_micros(500), 1);
+
+    assert_eq!(instructions + last_instructions, 700 * 42);
+    assert_eq!(timers + last_timers, 60 * 42);
+}
+
+#[test]
+fn timer_ticks_between_the_right_instructions() {
+    // 600 instructions per second puts exactly ten instructions in every frame
+    let mut scheduler = Scheduler::new(600.0);
+    scheduler.advance(Duration::from_millis(51));
+
+    let mut ticks = vec![];
+    while let Some(tick) = scheduler.next_tick() {
+        ticks.push(tick);
+    }
+
+    let timers: Vec<usize> = ticks.iter().enumerate().filter(|(_, tick)| **tick == Tick::Timer).map(|(index, _)| index).collect();
+    assert_eq!(timers, vec![10, 21, 32]);
+    assert_eq!(ticks.len(), 33);
+}
+
+#[test]
+fn catch_up_is_bounded() {
+    let mut scheduler = Scheduler::new(1000.0);
+    let (instructions, _) = run(&mut scheduler, Duration::from_secs(10), 1);
+
+    assert_eq!(instructions, MAX_CATCH_UP.as_millis() as u64);
+}
+
+#[test]
+fn speed_scales_the_emulated_time() {
+    let mut scheduler = Scheduler::new(600.0);
+    scheduler.set_speed(Speed::Scaled(0.5));
+    let (instructions, timers) = run(&mut scheduler, Duration::from_millis(2010), 20);
+
+    assert_eq!(instructions, 603);
+    assert_eq!(timers, 60);
+}
+
+#[test]
+fn advancing_a_frame_ends_on_the_timer_tick() {
+    let mut scheduler = Scheduler::new(600.0);
+    scheduler.advance_frame();
+
+    let mut ticks = vec![];
+    while let Some(tick) = scheduler.next_tick() {
+        ticks.push(tick);
+    }
+
+    assert_eq!(ticks.len(), 11);
+    assert_eq!(ticks.last(), Some(&Tick::Timer));
+}
+
+#[test]
+fn rate_changes_keep_the_clock() {
+    let mut scheduler = Scheduler::new(600.0);
+    let (instructions, _) = run(&mut scheduler, Duration::from_micros(100_500), 1);
+    assert_eq!(instructions, 60);
+
+    // The new rate counts from the last instruction at 0.1 seconds
+    scheduler.set_instructions_per_second(1200.0);
+    let (instructions, timers) = run(&mut scheduler, Duration::from_micros(100_500), 1);
+
+    assert_eq!(instructions, 121);
+    assert_eq!(timers, 6);
+}
+
+#[test]
+fn skipping_drops_the_due_time() {
+    let mut scheduler = Scheduler::new(600.0);
+    scheduler.advance(Duration::from_millis(100));
+    scheduler.next_tick();
+    scheduler.skip();
+
+    assert_eq!(scheduler.next_tick(), None);
+}
+
+/// Instructions in each of the next frames, when every instruction only costs its fetch
+fn vip_frames(scheduler: &mut Scheduler, frames: usize) -> Vec<u32> {
+    let mut counts = vec![0];
+    for _ in 0..frames {
+        scheduler.advance_frame();
+        while let Some(tick) = scheduler.next_tick() {
+            match tick {
+                Tick::Instruction => *counts.last_mut().unwrap() += 1,
+                Tick::Timer => counts.push(0),
+            }
+        }
+    }
+
+    counts.pop();
+    counts
+}
+
+#[test]
+fn vip_frames_start_with_the_display_interrupt() {
+    let mut scheduler = Scheduler::new(600.0);
+    scheduler.set_timing(Timing::CosmacVip);
+
+    // A frame is 3668 machine cycles, after the first the interrupt takes 1054 of them. The last
+    // fetch of the second frame runs into the third.
+    assert_eq!(vip_frames(&mut scheduler, 3), vec![92, 66, 65]);
+}
+
+#[test]
+fn vip_drawing_waits_for_the_interrupt() {
+    let mut scheduler = Scheduler::new(600.0);
+    scheduler.set_timing(Timing::CosmacVip);
+    scheduler.advance_frame();
+
+    assert_eq!(scheduler.next_tick(), Some(Tick::Instruction));
+    scheduler.spend(InstructionTiming { cycles: 1000, waits_for_interrupt: true });
+    assert_eq!(scheduler.next_tick(), Some(Tick::Timer));
+
+    // The sprite is drawn after the interrupt, which leaves room for 41 more fetches
+    assert_eq!(vip_frames(&mut scheduler, 1), vec![41]);
+}
+
+#[test]
+fn vip_timing_follows_the_published_costs() {
+    let timing = |rom: Vec<u8>| Chip8Rom::new("timing", rom).to_device().vip_timing();
+
+    // 27, 45, 200 and 164 µs
+    assert_eq!(timing(vec![0x60, 0x05]).cycles, 6);
+    assert_eq!(timing(vec![0xF0, 0x15]).cycles, 10);
+    assert_eq!(timing(vec![0x81, 0x24]).cycles, 44);
+    assert_eq!(timing(vec![0xC0, 0xFF]).cycles, 36);
+    assert!(!timing(vec![0x60, 0x05]).waits_for_interrupt);
+}
+
+/// Instructions a ROM gets through in its first frame with VIP timing
+fn vip_instructions_in_frame(rom: Vec<u8>) -> u32 {
+    let mut chip8 = Chip8Rom::new("timing", rom).to_device();
+    let mut scheduler = Scheduler::new(600.0);
+    scheduler.set_timing(Timing::CosmacVip);
+    scheduler.advance_frame();
+
+    let mut instructions = 0;
+    while let Some(Tick::Instruction) = scheduler.next_tick() {
+        let timing = chip8.vip_timing();
+        chip8.cycle().unwrap();
+        scheduler.spend(timing);
+        instructions += 1;
+    }
+    instructions
+}
+
+#[test]
+fn vip_save_and_load_cost_a_pass_per_register() {
+    let timing = |instruction: u16| Chip8Rom::new("timing", instruction.to_be_bytes().to_vec()).to_device().vip_timing().cycles;
+
+    assert_eq!(timing(0xF055), 28);
+    assert_eq!(timing(0xFF55), 238);
+    assert_eq!(timing(0xF065), timing(0xF055));
+    assert_eq!(timing(0xFF65), timing(0xFF55));
+
+    // Both loop over `i := 0x300`, the save and a jump back: 183 and 393 machine cycles a loop in
+    // a frame of 3668
+    let one_register = vip_instructions_in_frame(vec![0xA3, 0x00, 0xF0, 0x55, 0x12, 0x00]);
+    let all_registers = vip_instructions_in_frame(vec![0xA3, 0x00, 0xFF, 0x55, 0x12, 0x00]);
+    assert_eq!((one_register, all_registers), (61, 29));
+}
+
+#[test]
+fn vip_bcd_costs_a_pass_per_hundred_and_ten() {
+    let bcd = |value: u8| Chip8Rom::new("timing", vec![0x60, value, 0xF0, 0x33]).to_device();
+    let timing = |value: u8| {
+        let mut chip8 = bcd(value);
+        chip8.cycle().unwrap();
+        chip8.vip_timing().cycles
+    };
+
+    // The ones are what is left over and cost nothing
+    assert_eq!(timing(0), 84);
+    assert_eq!(timing(9), 84);
+    assert_eq!(timing(10), 100);
+    assert_eq!(timing(199), 84 + 16 * 10);
+    assert_eq!(timing(255), 84 + 16 * 7);
+}
+
+#[test]
+fn vip_key_wait_costs_a_keypad_scan_every_pass() {
+    let mut chip8 = Chip8Rom::new("timing", vec![0xF0, 0x0A]).to_device();
+
+    for _ in 0..3 {
+        assert_eq!(chip8.vip_timing().cycles, KEY_SCAN_CYCLES);
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.program_counter(), 0x200);
+    }
+    // 200 machine cycles a pass instead of the 40 of the fetch alone
+    assert_eq!(vip_instructions_in_frame(vec![0xF0, 0x0A]), 19);
+}
+
+#[test]
+fn vip_sprites_off_a_byte_boundary_cost_more() {
+    // Draws at x 0, then moves to x 3 and draws again
+    let mut chip8 = Chip8Rom::new("timing", vec![0xD0, 0x05, 0x60, 0x03, 0xD0, 0x05]).to_device();
+    assert_eq!(chip8.vip_timing(), InstructionTiming { cycles: 136, waits_for_interrupt: true });
+
+    chip8.cycle().unwrap();
+    chip8.update();
+    chip8.cycle().unwrap();
+    assert_eq!(chip8.vip_timing(), InstructionTiming { cycles: 196, waits_for_interrupt: true });
+}
+
+#[test]
+fn fixed_timing_ignores_the_cost() {
+    let mut scheduler = Scheduler::new(600.0);
+    scheduler.advance_frame();
+
+    let mut instructions = 0;
+    while let Some(tick) = scheduler.next_tick() {
+        if tick == Tick::Instruction {
+            scheduler.spend(InstructionTiming { cycles: 3000, waits_for_interrupt: true });
+            instructions += 1;
+        }
+    }
+
+    assert_eq!(instructions, 10);
+}
+
+#[test]
+fn meter_measures_once_per_second() {
+    let mut meter = InstructionMeter::new();
+
+    assert!(!meter.record(Duration::from_millis(500), 300));
+    assert!(meter.record(Duration::from_millis(500), 400));
+    assert_eq!(meter.instructions_per_second(), 700.0);
+}
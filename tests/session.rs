@@ -0,0 +1,98 @@
+//! Checks that jumping between ROMs and saved states takes the machine's settings along.
+
+use chip8::chip8::Chip8Rom;
+use chip8::scheduler::Tick;
+use chip8::session::{DEFAULT_INSTRUCTIONS_PER_SECOND, Session};
+
+/// Two ROMs that count up in a different register, so their states never match
+fn session() -> Session {
+    let roms = vec![
+        Chip8Rom::new("A", vec![0x70, 0x01, 0x12, 0x00]),
+        Chip8Rom::new("B", vec![0x71, 0x01, 0x12, 0x00]),
+    ];
+    let mut session = Session::new(roms, 0, None);
+    session.reset();
+    session
+}
+
+fn run_frames(session: &mut Session, frames: u32) {
+    for _ in 0..frames {
+        session.scheduler.advance_frame();
+        while let Some(tick) = session.scheduler.next_tick() {
+            match tick {
+                Tick::Instruction => {
+                    session.chip8.cycle().unwrap();
+                }
+                Tick::Timer => session.timer_tick(),
+            }
+        }
+    }
+}
+
+#[test]
+fn loading_a_slot_switches_back_to_its_rom_and_speed() {
+    let mut session = session();
+    run_frames(&mut session, 5);
+    session.save_state(0);
+    let saved = session.chip8.save_state();
+
+    // ROM B runs at a speed of its own, like a database tick rate would give it
+    session.set_rom(1);
+    session.scheduler.set_instructions_per_second(60.0);
+    run_frames(&mut session, 5);
+
+    assert!(session.load_state(0));
+    assert_eq!(session.current_rom(), 0);
+    assert_eq!(session.chip8.save_state(), saved);
+    assert_eq!(session.scheduler.instructions_per_second(), DEFAULT_INSTRUCTIONS_PER_SECOND as f64);
+    // The clock starts over like after a reset
+    assert_eq!(session.scheduler.time(), 0.0);
+}
+
+#[test]
+fn loading_an_empty_slot_changes_nothing() {
+    let mut session = session();
+    run_frames(&mut session, 2);
+    let state = session.chip8.save_state();
+
+    assert!(!session.load_state(0));
+    assert!(!session.load_state(9));
+    assert_eq!(session.chip8.save_state(), state);
+}
+
+#[test]
+fn rewinding_after_loading_a_slot_stays_in_the_loaded_run() {
+    let mut session = session();
+    run_frames(&mut session, 5);
+    session.save_state(0);
+    let saved = session.chip8.save_state();
+
+    run_frames(&mut session, 5);
+    assert!(session.load_state(0));
+
+    // Nothing from before the load is left to go back to
+    assert!(!session.step_back());
+    assert_eq!(session.chip8.save_state(), saved);
+
+    // Goes back to the loaded state and no further
+    session.timer_tick();
+    session.timer_tick();
+    assert!(session.step_back());
+    assert_eq!(session.chip8.save_state(), saved);
+    assert!(!session.step_back());
+}
+
+#[test]
+fn rewinding_after_switching_roms_stays_in_the_new_rom() {
+    let mut session = session();
+    run_frames(&mut session, 5);
+    session.save_state(0);
+
+    session.set_rom(1);
+    run_frames(&mut session, 5);
+    assert!(session.load_state(0));
+    session.set_rom(1);
+
+    assert!(!session.step_back());
+    assert_eq!(session.chip8.total_cycles(), 0);
+}
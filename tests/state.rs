@@ -0,0 +1,91 @@
+//! Checks saving and restoring the machine and rejecting states that can't be restored.
+
+use chip8::chip8::{Chip8, Chip8Rom};
+use chip8::chip8::state::StateError;
+use chip8::hash::crc32;
+
+/// Calls itself forever, each call is one more entry on the stack
+const RECURSE: [u8; 2] = [0x22, 0x00];
+
+fn running(rom: &[u8], cycles: usize) -> Chip8 {
+    let mut chip8 = Chip8Rom::new("state", rom.to_vec()).to_device();
+    chip8.set_seed(7);
+    for _ in 0..cycles {
+        chip8.cycle().unwrap();
+    }
+    chip8
+}
+
+/// Replaces the payload of a state and fixes its length and checksum
+fn with_payload(state: &[u8], version: u16, payload: &[u8]) -> Vec<u8> {
+    let mut rebuilt = state[..4].to_vec();
+    rebuilt.extend_from_slice(&version.to_le_bytes());
+    rebuilt.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    rebuilt.extend_from_slice(payload);
+    rebuilt.extend_from_slice(&crc32(payload).to_le_bytes());
+    rebuilt
+}
+
+#[test]
+fn states_round_trip_with_a_deep_stack() {
+    let chip8 = running(&RECURSE, 300);
+    let state = chip8.save_state();
+
+    let mut restored = Chip8::new();
+    restored.load_state(&state).unwrap();
+
+    assert_eq!(restored.stack().len(), 300);
+    assert_eq!(restored.program_counter(), 0x200);
+    assert_eq!(restored.save_state(), state);
+}
+
+#[test]
+fn version_1_states_still_load() {
+    let chip8 = running(&RECURSE, 3);
+    let state = chip8.save_state();
+    let payload = &state[10..state.len() - 4];
+
+    // Version 1 has a u8 stack depth and no generator state at the end
+    let stack_offset = 7 + 4 + 0x1000 + 16 + 4;
+    let mut old_payload = payload[..stack_offset].to_vec();
+    old_payload.push(payload[stack_offset]);
+    old_payload.extend_from_slice(&payload[stack_offset + 2..payload.len() - 2 - 16]);
+
+    let mut restored = Chip8::new();
+    restored.set_seed(99);
+    restored.load_state(&with_payload(&state, 1, &old_payload)).unwrap();
+
+    assert_eq!(restored.stack(), chip8.stack());
+    assert_eq!(restored.seed(), 99);
+}
+
+#[test]
+fn broken_states_are_rejected() {
+    let state = running(&RECURSE, 3).save_state();
+    let payload = &state[10..state.len() - 4];
+
+    let mut bad_magic = state.clone();
+    bad_magic[0] = b'X';
+    let mut bad_checksum = state.clone();
+    *bad_checksum.last_mut().unwrap() ^= 1;
+    let truncated_payload = with_payload(&state, 3, &payload[..payload.len() - 1]);
+
+    let mut chip8 = Chip8::new();
+    assert_eq!(chip8.load_state(&bad_magic), Err(StateError::InvalidMagic));
+    assert_eq!(chip8.load_state(&bad_checksum), Err(StateError::ChecksumMismatch));
+    assert_eq!(chip8.load_state(&state[..state.len() - 1]), Err(StateError::Truncated));
+    assert_eq!(chip8.load_state(&truncated_payload), Err(StateError::Truncated));
+}
+
+#[test]
+fn failed_loads_leave_the_machine_alone() {
+    let mut chip8 = running(&RECURSE, 5);
+    let before = chip8.save_state();
+
+    let mut other = running(&RECURSE, 9).save_state();
+    let length = other.len();
+    other[length - 1] ^= 1;
+
+    assert!(chip8.load_state(&other).is_err());
+    assert_eq!(chip8.save_state(), before);
+}